@@ -1,5 +1,4 @@
 use anyhow::Result;
-use std::env;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
@@ -60,10 +59,3 @@ pub async fn write_atomic(dest: &Path, bytes: impl AsRef<[u8]>) -> Result<()> {
         return Ok(());
     }
 }
-
-/// Resolve EXT_CACHE_ROOT from environment with sensible default.
-pub fn ext_cache_root_from_env() -> PathBuf {
-    env::var("EXT_CACHE_ROOT")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from("/tmp/alga-ext-cache"))
-}
@@ -1,2 +1,3 @@
 pub mod fs;
 pub mod lru;
+pub mod space;
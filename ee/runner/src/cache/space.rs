@@ -0,0 +1,135 @@
+// Free-space guard for the bundle cache root.
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::util::errors::CacheSpaceError;
+
+/// Default headroom kept free on the cache filesystem before extractions are refused.
+pub const DEFAULT_CACHE_MIN_FREE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Reports the bytes available to the runner on the filesystem holding a path.
+pub trait FreeSpaceProbe: Send + Sync {
+    fn available_bytes(&self, path: &Path) -> io::Result<u64>;
+}
+
+/// `statvfs`-backed probe used outside of tests.
+pub struct StatvfsProbe;
+
+impl FreeSpaceProbe for StatvfsProbe {
+    #[cfg(unix)]
+    fn available_bytes(&self, path: &Path) -> io::Result<u64> {
+        let stat = rustix::fs::statvfs(existing_ancestor(path))?;
+        Ok(stat.f_bavail.saturating_mul(stat.f_frsize))
+    }
+
+    #[cfg(not(unix))]
+    fn available_bytes(&self, _path: &Path) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "free space probe is only available on unix",
+        ))
+    }
+}
+
+/// The cache root may not exist before the first extraction; probe its closest existing parent.
+#[cfg(unix)]
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|p| !p.as_os_str().is_empty() && p.exists())
+        .unwrap_or(Path::new("."))
+}
+
+/// Refuses new bundle extractions once the cache filesystem drops below `min_free_bytes`.
+pub struct CacheSpaceGuard {
+    min_free_bytes: u64,
+    probe: Box<dyn FreeSpaceProbe>,
+}
+
+impl CacheSpaceGuard {
+    pub fn new(min_free_bytes: u64, probe: Box<dyn FreeSpaceProbe>) -> Self {
+        Self {
+            min_free_bytes,
+            probe,
+        }
+    }
+
+    /// Guard backed by [`StatvfsProbe`]; a `min_free_bytes` of 0 disables the check.
+    pub fn statvfs(min_free_bytes: u64) -> Self {
+        Self::new(min_free_bytes, Box::new(StatvfsProbe))
+    }
+
+    /// Probe failures are logged and let the extraction through; only a confirmed shortfall refuses it.
+    pub fn check(&self, cache_root: &Path) -> Result<(), CacheSpaceError> {
+        if self.min_free_bytes == 0 {
+            return Ok(());
+        }
+        let available_bytes = match self.probe.available_bytes(cache_root) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                tracing::warn!(cache_root=%cache_root.display(), error=%err, "Could not read free space for bundle cache");
+                return Ok(());
+            }
+        };
+        CACHE_SPACE_METRICS
+            .available_bytes
+            .store(available_bytes, Ordering::Relaxed);
+        if available_bytes < self.min_free_bytes {
+            CACHE_SPACE_METRICS
+                .refused_extractions
+                .fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                cache_root=%cache_root.display(),
+                available_bytes,
+                min_free_bytes = self.min_free_bytes,
+                "Refusing bundle extraction: cache filesystem is low on space"
+            );
+            return Err(CacheSpaceError::LowDiskSpace {
+                available_bytes,
+                min_free_bytes: self.min_free_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Runner-side cache gauges, appended to `/metrics` next to the guest metrics.
+#[derive(Default)]
+pub struct CacheSpaceMetrics {
+    available_bytes: AtomicU64,
+    refused_extractions: AtomicU64,
+}
+
+pub static CACHE_SPACE_METRICS: CacheSpaceMetrics = CacheSpaceMetrics {
+    available_bytes: AtomicU64::new(0),
+    refused_extractions: AtomicU64::new(0),
+};
+
+impl CacheSpaceMetrics {
+    pub fn refused_extractions(&self) -> u64 {
+        self.refused_extractions.load(Ordering::Relaxed)
+    }
+
+    /// Prometheus text exposition; the gauge reports the last probe result.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# TYPE alga_runner_cache_available_bytes gauge");
+        let _ = writeln!(
+            out,
+            "alga_runner_cache_available_bytes {}",
+            self.available_bytes.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            out,
+            "# TYPE alga_runner_cache_extractions_refused_total counter"
+        );
+        let _ = writeln!(
+            out,
+            "alga_runner_cache_extractions_refused_total {}",
+            self.refused_extractions()
+        );
+        out
+    }
+}
@@ -0,0 +1,535 @@
+// Runner startup configuration, read from the environment once and validated as a whole
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use url::Url;
+
+use crate::cache::space::DEFAULT_CACHE_MIN_FREE_BYTES;
+use crate::engine::host_api::HostRuntimeConfig;
+use crate::engine::loader::{
+    EngineConfig, S3AddressingStyle, S3PresignConfig, DEFAULT_BUNDLE_FETCH_TIMEOUT,
+};
+use crate::engine::metrics::{DEFAULT_MAX_SERIES, DEFAULT_MAX_SERIES_PER_EXTENSION};
+use crate::registry::client::{ResourcePolicy, DEFAULT_LOOKUP_TIMEOUT};
+use crate::signing::TrustedKeys;
+use crate::util::errors::ConfigError;
+use crate::util::limits::LimitPolicyMode;
+
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_CACHE_ROOT: &str = "/tmp/alga-ext-cache";
+const DEFAULT_BUNDLE_STORE_BASE: &str = "http://localhost:9000/alga-ext/";
+const DEFAULT_S3_REGION: &str = "us-east-1";
+
+/// Settings the HTTP server needs at startup. Build with [`RunnerConfig::from_env`]; every
+/// invalid or missing value is reported together rather than failing on the first.
+#[derive(Debug, Clone)]
+pub struct RunnerConfig {
+    /// Listen port (`PORT`, default 8080).
+    pub port: u16,
+    /// Registry API base (`REGISTRY_BASE_URL`, required).
+    pub registry_base_url: Url,
+    /// Bundle store base (`BUNDLE_STORE_BASE`); readiness skips the bundle store when unset.
+    pub bundle_store_base: Option<Url>,
+    /// Extension cache directory (`EXT_CACHE_ROOT`).
+    pub cache_root: PathBuf,
+    /// Free space kept on the cache filesystem before extractions are refused
+    /// (`EXT_CACHE_MIN_FREE_BYTES`, 0 disables the check).
+    pub cache_min_free_bytes: u64,
+    /// Largest static UI file served (`EXT_STATIC_MAX_FILE_BYTES`).
+    pub max_file_bytes: Option<u64>,
+    /// Validate installs against the registry (`EXT_STATIC_STRICT_VALIDATION`, default on).
+    pub strict_validation: bool,
+    /// Budget for registry lookups including retries (`REGISTRY_LOOKUP_TIMEOUT_MS`).
+    pub registry_lookup_timeout: Duration,
+    /// Budget for each bundle archive or signature download (`BUNDLE_FETCH_TIMEOUT_MS`).
+    pub bundle_fetch_timeout: Duration,
+    /// Only run content hashes the registry lists as approved (`EXT_REQUIRE_APPROVED_VERSIONS`).
+    pub require_approved_versions: bool,
+    /// Runner-wide limit ceilings (`EXT_MAX_MEMORY_MB`, `EXT_MAX_TIMEOUT_MS`).
+    pub runner_policy: ResourcePolicy,
+    /// Handling of execute limits above the policy (`EXT_LIMIT_POLICY_MODE`).
+    pub limit_policy_mode: LimitPolicyMode,
+    /// Wasmtime sizing and execution defaults (`WASM_*`, `EXT_DEFAULT_*`).
+    pub engine: EngineConfig,
+    /// Host capability settings handed to every guest (`EXT_EGRESS_*`, `UI_PROXY_*`,
+    /// `EXT_STORAGE_MAX_VALUE_BYTES`, `EXT_WASI_ENV_ALLOWLIST`).
+    pub runtime: HostRuntimeConfig,
+    /// Deep readiness probe object (`BUNDLE_STORE_HEALTH_KEY`) and its expected sha256
+    /// (`BUNDLE_STORE_HEALTH_SHA256`).
+    pub health_probe_key: Option<String>,
+    pub health_probe_sha256: Option<String>,
+    /// Reported as-is by `/readyz` (`EXT_CACHE_MAX_BYTES`).
+    pub cache_max_bytes: Option<String>,
+    /// Ed25519 keys trusted for bundle signatures, read from the PEM file at
+    /// `SIGNING_TRUST_BUNDLE`; bundles are only checked against their SHA-256 when unset.
+    pub trusted_keys: Option<TrustedKeys>,
+    /// Presigned bundle downloads, enabled when both `S3_ACCESS_KEY` and `S3_SECRET_KEY` (or
+    /// their `MINIO_*` fallbacks) are set; they require `BUNDLE_STORE_BASE`.
+    pub s3_presign: Option<S3PresignConfig>,
+    /// Mask sensitive values in logged request/response bodies (`LOG_BODY_REDACTION`, default on).
+    pub log_body_redaction: bool,
+    /// Guest metric series kept across all extensions (`EXT_METRICS_MAX_SERIES`).
+    pub metrics_max_series: usize,
+    /// Guest metric series one tenant/extension pair may create
+    /// (`EXT_METRICS_MAX_SERIES_PER_EXTENSION`).
+    pub metrics_max_series_per_extension: usize,
+}
+
+impl RunnerConfig {
+    /// Bundle store base used for fetches; a local MinIO when `BUNDLE_STORE_BASE` is unset.
+    pub fn bundle_store_url(&self) -> Url {
+        self.bundle_store_base
+            .clone()
+            .unwrap_or_else(|| Url::parse(DEFAULT_BUNDLE_STORE_BASE).expect("valid default URL"))
+    }
+
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self::from_lookup(|key| env::var(key).ok())?)
+    }
+
+    /// Parse and validate every setting `lookup` provides.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut problems = Vec::new();
+        let value = |key: &str| {
+            lookup(key)
+                .map(|raw| raw.trim().to_string())
+                .filter(|raw| !raw.is_empty())
+        };
+
+        let port =
+            parse(&mut problems, "PORT", value("PORT"), "a port number").unwrap_or(DEFAULT_PORT);
+        let registry_base_url = match value("REGISTRY_BASE_URL") {
+            Some(raw) => parse_url(&mut problems, "REGISTRY_BASE_URL", &raw),
+            None => {
+                problems.push("REGISTRY_BASE_URL is not set".to_string());
+                None
+            }
+        };
+        let bundle_store_base = value("BUNDLE_STORE_BASE")
+            .and_then(|raw| parse_url(&mut problems, "BUNDLE_STORE_BASE", &raw));
+        let cache_root = value("EXT_CACHE_ROOT")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_ROOT));
+        let cache_min_free_bytes = parse(
+            &mut problems,
+            "EXT_CACHE_MIN_FREE_BYTES",
+            value("EXT_CACHE_MIN_FREE_BYTES"),
+            "a byte count",
+        )
+        .unwrap_or(DEFAULT_CACHE_MIN_FREE_BYTES);
+        let max_file_bytes = parse(
+            &mut problems,
+            "EXT_STATIC_MAX_FILE_BYTES",
+            value("EXT_STATIC_MAX_FILE_BYTES"),
+            "a byte count",
+        );
+        let strict_validation = parse_bool(
+            &mut problems,
+            "EXT_STATIC_STRICT_VALIDATION",
+            value("EXT_STATIC_STRICT_VALIDATION"),
+        )
+        .unwrap_or(true);
+        let registry_lookup_timeout = match parse::<u64>(
+            &mut problems,
+            "REGISTRY_LOOKUP_TIMEOUT_MS",
+            value("REGISTRY_LOOKUP_TIMEOUT_MS"),
+            "a positive number of milliseconds",
+        ) {
+            Some(0) => {
+                problems.push("REGISTRY_LOOKUP_TIMEOUT_MS must be greater than zero".to_string());
+                DEFAULT_LOOKUP_TIMEOUT
+            }
+            Some(ms) => Duration::from_millis(ms),
+            None => DEFAULT_LOOKUP_TIMEOUT,
+        };
+        let bundle_fetch_timeout = match parse::<u64>(
+            &mut problems,
+            "BUNDLE_FETCH_TIMEOUT_MS",
+            value("BUNDLE_FETCH_TIMEOUT_MS"),
+            "a positive number of milliseconds",
+        ) {
+            Some(0) => {
+                problems.push("BUNDLE_FETCH_TIMEOUT_MS must be greater than zero".to_string());
+                DEFAULT_BUNDLE_FETCH_TIMEOUT
+            }
+            Some(ms) => Duration::from_millis(ms),
+            None => DEFAULT_BUNDLE_FETCH_TIMEOUT,
+        };
+        let require_approved_versions = parse_bool(
+            &mut problems,
+            "EXT_REQUIRE_APPROVED_VERSIONS",
+            value("EXT_REQUIRE_APPROVED_VERSIONS"),
+        )
+        .unwrap_or(false);
+        let runner_policy = ResourcePolicy {
+            max_memory_mb: parse(
+                &mut problems,
+                "EXT_MAX_MEMORY_MB",
+                value("EXT_MAX_MEMORY_MB"),
+                "a number of megabytes",
+            ),
+            max_timeout_ms: parse(
+                &mut problems,
+                "EXT_MAX_TIMEOUT_MS",
+                value("EXT_MAX_TIMEOUT_MS"),
+                "a number of milliseconds",
+            ),
+        };
+        let limit_policy_mode = match value("EXT_LIMIT_POLICY_MODE") {
+            None => LimitPolicyMode::Clamp,
+            Some(raw) => match raw.to_ascii_lowercase().as_str() {
+                "clamp" => LimitPolicyMode::Clamp,
+                "reject" => LimitPolicyMode::Reject,
+                _ => {
+                    problems.push(format!(
+                        "EXT_LIMIT_POLICY_MODE must be 'clamp' or 'reject', got {raw:?}"
+                    ));
+                    LimitPolicyMode::Clamp
+                }
+            },
+        };
+        let trusted_keys = match value("SIGNING_TRUST_BUNDLE") {
+            Some(path) => match TrustedKeys::from_pem_file(Path::new(&path)) {
+                Ok(keys) => Some(keys),
+                Err(err) => {
+                    problems.push(format!("SIGNING_TRUST_BUNDLE is unusable: {err}"));
+                    None
+                }
+            },
+            None => None,
+        };
+        let log_body_redaction = parse_bool(
+            &mut problems,
+            "LOG_BODY_REDACTION",
+            value("LOG_BODY_REDACTION"),
+        )
+        .unwrap_or(true);
+        let metrics_max_series = match parse::<usize>(
+            &mut problems,
+            "EXT_METRICS_MAX_SERIES",
+            value("EXT_METRICS_MAX_SERIES"),
+            "a positive integer",
+        ) {
+            Some(0) => {
+                problems.push("EXT_METRICS_MAX_SERIES must be greater than zero".to_string());
+                DEFAULT_MAX_SERIES
+            }
+            Some(n) => n,
+            None => DEFAULT_MAX_SERIES,
+        };
+        let metrics_max_series_per_extension = match parse::<usize>(
+            &mut problems,
+            "EXT_METRICS_MAX_SERIES_PER_EXTENSION",
+            value("EXT_METRICS_MAX_SERIES_PER_EXTENSION"),
+            "a positive integer",
+        ) {
+            Some(0) => {
+                problems.push(
+                    "EXT_METRICS_MAX_SERIES_PER_EXTENSION must be greater than zero".to_string(),
+                );
+                DEFAULT_MAX_SERIES_PER_EXTENSION
+            }
+            Some(n) => n,
+            None => DEFAULT_MAX_SERIES_PER_EXTENSION,
+        };
+        let s3_presign = s3_presign_from_lookup(value, bundle_store_base.as_ref(), &mut problems);
+        let runtime = HostRuntimeConfig::from_lookup(value, &mut problems);
+        let engine = EngineConfig::default().apply_overrides(value, &mut problems);
+        if let Err(err) = engine.validate() {
+            problems.push(err.to_string());
+        }
+
+        match registry_base_url {
+            Some(registry_base_url) if problems.is_empty() => Ok(Self {
+                port,
+                registry_base_url,
+                bundle_store_base,
+                cache_root,
+                cache_min_free_bytes,
+                max_file_bytes,
+                strict_validation,
+                registry_lookup_timeout,
+                bundle_fetch_timeout,
+                require_approved_versions,
+                runner_policy,
+                limit_policy_mode,
+                engine,
+                runtime,
+                health_probe_key: value("BUNDLE_STORE_HEALTH_KEY"),
+                health_probe_sha256: value("BUNDLE_STORE_HEALTH_SHA256"),
+                cache_max_bytes: value("EXT_CACHE_MAX_BYTES"),
+                trusted_keys,
+                s3_presign,
+                log_body_redaction,
+                metrics_max_series,
+                metrics_max_series_per_extension,
+            }),
+            _ => Err(ConfigError { problems }),
+        }
+    }
+}
+
+fn s3_presign_from_lookup(
+    value: impl Fn(&str) -> Option<String>,
+    base: Option<&Url>,
+    problems: &mut Vec<String>,
+) -> Option<S3PresignConfig> {
+    let style = match value("S3_ADDRESSING_STYLE") {
+        None => S3AddressingStyle::Path,
+        Some(raw) => S3AddressingStyle::parse(&raw).unwrap_or_else(|| {
+            problems.push(format!(
+                "S3_ADDRESSING_STYLE must be 'path' or 'virtual', got {raw:?}"
+            ));
+            S3AddressingStyle::Path
+        }),
+    };
+    let endpoint =
+        value("S3_ENDPOINT").filter(|raw| parse_url(problems, "S3_ENDPOINT", raw).is_some());
+    let access_key = value("S3_ACCESS_KEY").or_else(|| value("MINIO_ACCESS_KEY"));
+    let secret_key = value("S3_SECRET_KEY").or_else(|| value("MINIO_SECRET_KEY"));
+    match (access_key, secret_key) {
+        (Some(access_key), Some(secret_key)) => Some(S3PresignConfig {
+            base: match base {
+                Some(base) => base.clone(),
+                // An invalid BUNDLE_STORE_BASE has already been reported.
+                None if value("BUNDLE_STORE_BASE").is_some() => return None,
+                None => {
+                    problems.push(
+                        "S3_ACCESS_KEY and S3_SECRET_KEY require BUNDLE_STORE_BASE".to_string(),
+                    );
+                    return None;
+                }
+            },
+            access_key,
+            secret_key,
+            style,
+            endpoint,
+            region: value("S3_REGION").unwrap_or_else(|| DEFAULT_S3_REGION.to_string()),
+        }),
+        (None, None) => None,
+        _ => {
+            problems.push("S3_ACCESS_KEY and S3_SECRET_KEY must be set together".to_string());
+            None
+        }
+    }
+}
+
+fn parse<T: FromStr>(
+    problems: &mut Vec<String>,
+    key: &str,
+    raw: Option<String>,
+    expected: &str,
+) -> Option<T> {
+    let raw = raw?;
+    match raw.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            problems.push(format!("{key} must be {expected}, got {raw:?}"));
+            None
+        }
+    }
+}
+
+pub(crate) fn parse_bool(
+    problems: &mut Vec<String>,
+    key: &str,
+    raw: Option<String>,
+) -> Option<bool> {
+    let raw = raw?;
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => {
+            problems.push(format!("{key} must be true or false, got {raw:?}"));
+            None
+        }
+    }
+}
+
+fn parse_url(problems: &mut Vec<String>, key: &str, raw: &str) -> Option<Url> {
+    match Url::parse(raw) {
+        Ok(url) => Some(url),
+        Err(err) => {
+            problems.push(format!("{key} is not a valid URL ({err}): {raw:?}"));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::host_api::EgressMode;
+    use std::collections::HashMap;
+
+    fn config_from(vars: &[(&str, &str)]) -> Result<RunnerConfig, ConfigError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        RunnerConfig::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn parses_a_complete_environment() {
+        let cfg = config_from(&[
+            ("PORT", "9090"),
+            ("REGISTRY_BASE_URL", "http://registry.internal:3000"),
+            ("BUNDLE_STORE_BASE", "http://minio:9000/alga-ext/"),
+            ("EXT_CACHE_ROOT", "/var/cache/ext"),
+            ("EXT_CACHE_MIN_FREE_BYTES", "0"),
+            ("EXT_STATIC_MAX_FILE_BYTES", "1048576"),
+            ("EXT_STATIC_STRICT_VALIDATION", "false"),
+            ("REGISTRY_LOOKUP_TIMEOUT_MS", "750"),
+            ("BUNDLE_FETCH_TIMEOUT_MS", "15000"),
+            ("EXT_REQUIRE_APPROVED_VERSIONS", "TRUE"),
+            ("EXT_MAX_MEMORY_MB", "512"),
+            ("EXT_MAX_TIMEOUT_MS", "30000"),
+            ("EXT_LIMIT_POLICY_MODE", "reject"),
+            ("WASM_POOL_TOTAL_COMPONENTS", "64"),
+            ("EXT_DEFAULT_TIMEOUT_MS", "5000"),
+            ("BUNDLE_STORE_HEALTH_KEY", "healthz/custom.txt"),
+            ("EXT_EGRESS_MODE", "deny-all"),
+            ("S3_ACCESS_KEY", "minio"),
+            ("MINIO_SECRET_KEY", "minio-secret"),
+            ("S3_ADDRESSING_STYLE", "virtual"),
+            ("LOG_BODY_REDACTION", "off"),
+            ("EXT_METRICS_MAX_SERIES", "500"),
+            ("STORAGE_API_BASE_URL", "http://server:3000/api"),
+            ("RUNNER_SERVICE_TOKEN", "runner-token"),
+        ])
+        .unwrap();
+
+        assert_eq!(cfg.port, 9090);
+        assert_eq!(
+            cfg.registry_base_url.as_str(),
+            "http://registry.internal:3000/"
+        );
+        assert_eq!(
+            cfg.bundle_store_base.as_ref().map(Url::as_str),
+            Some("http://minio:9000/alga-ext/")
+        );
+        assert_eq!(cfg.cache_root, PathBuf::from("/var/cache/ext"));
+        assert_eq!(cfg.cache_min_free_bytes, 0);
+        assert_eq!(cfg.max_file_bytes, Some(1_048_576));
+        assert!(!cfg.strict_validation);
+        assert_eq!(cfg.registry_lookup_timeout, Duration::from_millis(750));
+        assert_eq!(cfg.bundle_fetch_timeout, Duration::from_secs(15));
+        assert!(cfg.require_approved_versions);
+        assert_eq!(
+            cfg.runner_policy,
+            ResourcePolicy {
+                max_memory_mb: Some(512),
+                max_timeout_ms: Some(30_000),
+            }
+        );
+        assert_eq!(cfg.limit_policy_mode, LimitPolicyMode::Reject);
+        assert_eq!(cfg.engine.pool_total_components, 64);
+        assert_eq!(cfg.engine.default_timeout_ms, Some(5_000));
+        assert_eq!(cfg.health_probe_key.as_deref(), Some("healthz/custom.txt"));
+        assert_eq!(cfg.health_probe_sha256, None);
+        assert_eq!(cfg.runtime.egress_mode, EgressMode::DenyAll);
+        let presign = cfg.s3_presign.as_ref().unwrap();
+        assert_eq!(presign.base.as_str(), "http://minio:9000/alga-ext/");
+        assert_eq!(presign.access_key, "minio");
+        assert_eq!(presign.secret_key, "minio-secret");
+        assert_eq!(presign.style, S3AddressingStyle::Virtual);
+        assert_eq!(presign.region, DEFAULT_S3_REGION);
+        assert!(!cfg.log_body_redaction);
+        assert_eq!(cfg.metrics_max_series, 500);
+        assert_eq!(
+            cfg.metrics_max_series_per_extension,
+            DEFAULT_MAX_SERIES_PER_EXTENSION
+        );
+        let api = &cfg.runtime.internal_api;
+        assert_eq!(api.base_url.as_deref(), Some("http://server:3000"));
+        assert_eq!(
+            api.user_service_base_url.as_deref(),
+            Some("http://server:3000")
+        );
+        assert_eq!(api.token.as_deref(), Some("runner-token"));
+
+        let minimal = config_from(&[("REGISTRY_BASE_URL", "http://registry:3000")]).unwrap();
+        assert_eq!(minimal.port, DEFAULT_PORT);
+        assert_eq!(minimal.bundle_store_base, None);
+        assert_eq!(minimal.cache_min_free_bytes, DEFAULT_CACHE_MIN_FREE_BYTES);
+        assert!(minimal.strict_validation);
+        assert_eq!(minimal.bundle_fetch_timeout, DEFAULT_BUNDLE_FETCH_TIMEOUT);
+        assert_eq!(minimal.limit_policy_mode, LimitPolicyMode::Clamp);
+        assert_eq!(minimal.engine, EngineConfig::default());
+        assert_eq!(minimal.s3_presign, None);
+        assert!(minimal.log_body_redaction);
+        assert_eq!(
+            minimal.runtime.internal_api.base_url.as_deref(),
+            Some("http://registry:3000")
+        );
+    }
+
+    #[test]
+    fn s3_keys_require_a_bundle_store() {
+        let err = config_from(&[
+            ("REGISTRY_BASE_URL", "http://registry:3000"),
+            ("S3_ACCESS_KEY", "minio"),
+            ("S3_SECRET_KEY", "minio-secret"),
+        ])
+        .unwrap_err();
+        assert_eq!(err.problems.len(), 1, "{err}");
+        assert!(err.problems[0].contains("BUNDLE_STORE_BASE"), "{err}");
+    }
+
+    #[test]
+    fn reports_every_problem_at_once() {
+        let err = config_from(&[
+            ("PORT", "http"),
+            ("BUNDLE_STORE_BASE", "not a url"),
+            ("EXT_CACHE_MIN_FREE_BYTES", "-1"),
+            ("EXT_STATIC_STRICT_VALIDATION", "maybe"),
+            ("REGISTRY_LOOKUP_TIMEOUT_MS", "0"),
+            ("BUNDLE_FETCH_TIMEOUT_MS", "soon"),
+            ("EXT_LIMIT_POLICY_MODE", "ignore"),
+            ("SIGNING_TRUST_BUNDLE", "/nonexistent/trust.pem"),
+            ("LOG_BODY_REDACTION", "sometimes"),
+            ("EXT_METRICS_MAX_SERIES", "0"),
+            ("EXT_METRICS_MAX_SERIES_PER_EXTENSION", "many"),
+            ("S3_ADDRESSING_STYLE", "diagonal"),
+            ("S3_ACCESS_KEY", "minio"),
+            ("EXT_EGRESS_MODE", "open"),
+            ("EXT_AUDIT_LOG_URL", "audit sink"),
+            ("STORAGE_API_BASE_URL", "server"),
+            ("WASM_POOL_TOTAL_STACKS", "lots"),
+            ("WASM_EPOCH_TICK_MS", "0"),
+        ])
+        .unwrap_err();
+
+        let expected = [
+            "PORT",
+            "REGISTRY_BASE_URL is not set",
+            "BUNDLE_STORE_BASE",
+            "EXT_CACHE_MIN_FREE_BYTES",
+            "EXT_STATIC_STRICT_VALIDATION",
+            "REGISTRY_LOOKUP_TIMEOUT_MS",
+            "BUNDLE_FETCH_TIMEOUT_MS",
+            "EXT_LIMIT_POLICY_MODE",
+            "SIGNING_TRUST_BUNDLE",
+            "LOG_BODY_REDACTION",
+            "EXT_METRICS_MAX_SERIES",
+            "EXT_METRICS_MAX_SERIES_PER_EXTENSION",
+            "S3_ADDRESSING_STYLE",
+            "S3_SECRET_KEY",
+            "EXT_EGRESS_MODE",
+            "EXT_AUDIT_LOG_URL",
+            "STORAGE_API_BASE_URL",
+            "WASM_POOL_TOTAL_STACKS",
+            "epoch_tick_ms",
+        ];
+        assert_eq!(err.problems.len(), expected.len(), "{err}");
+        for (problem, key) in err.problems.iter().zip(expected) {
+            assert!(problem.contains(key), "{problem} should mention {key}");
+        }
+        let message = err.to_string();
+        assert!(message.starts_with("invalid runner configuration"));
+        assert_eq!(message.lines().count(), expected.len() + 1);
+    }
+}
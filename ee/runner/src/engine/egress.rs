@@ -0,0 +1,181 @@
+//! Rejects `http.fetch` targets that resolve to loopback, private or link-local addresses
+//! (`EXT_EGRESS_BLOCK_PRIVATE_IPS`, default off).
+
+use std::error::Error as StdError;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use url::{Host, Url};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EgressBlocked {
+    /// The URL has no host to resolve.
+    MissingHost,
+    /// The host did not resolve.
+    Unresolvable(String),
+    /// The host resolved to a loopback, private, link-local or otherwise internal address.
+    PrivateAddress(IpAddr),
+}
+
+impl std::fmt::Display for EgressBlocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EgressBlocked::MissingHost => write!(f, "url has no host"),
+            EgressBlocked::Unresolvable(err) => write!(f, "host did not resolve: {err}"),
+            EgressBlocked::PrivateAddress(ip) => {
+                write!(f, "host resolves to internal address {ip}")
+            }
+        }
+    }
+}
+
+impl StdError for EgressBlocked {}
+
+/// True for globally routable unicast addresses.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_ipv4(v4),
+        IpAddr::V6(v6) => is_public_ipv6(v6),
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT (100.64.0.0/10)
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments (192.0.0.0/24)
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking (198.18.0.0/15)
+        || (a == 198 && (b == 18 || b == 19))
+        // Reserved (240.0.0.0/4)
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(v4);
+    }
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // Documentation (2001:db8::/32)
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+fn first_internal(addrs: &[SocketAddr]) -> Option<IpAddr> {
+    addrs
+        .iter()
+        .map(SocketAddr::ip)
+        .find(|ip| !is_public_ip(*ip))
+}
+
+async fn resolve_public(host: &str, port: u16) -> Result<Vec<SocketAddr>, EgressBlocked> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|err| EgressBlocked::Unresolvable(err.to_string()))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(EgressBlocked::Unresolvable("no addresses".into()));
+    }
+    match first_internal(&addrs) {
+        Some(ip) => Err(EgressBlocked::PrivateAddress(ip)),
+        None => Ok(addrs),
+    }
+}
+
+/// Reject `url` when its host is, or resolves to, a non-public address.
+pub async fn check_target(url: &Url) -> Result<(), EgressBlocked> {
+    match url.host() {
+        None => Err(EgressBlocked::MissingHost),
+        Some(Host::Ipv4(ip)) if !is_public_ipv4(ip) => {
+            Err(EgressBlocked::PrivateAddress(IpAddr::V4(ip)))
+        }
+        Some(Host::Ipv6(ip)) if !is_public_ipv6(ip) => {
+            Err(EgressBlocked::PrivateAddress(IpAddr::V6(ip)))
+        }
+        Some(Host::Ipv4(_)) | Some(Host::Ipv6(_)) => Ok(()),
+        Some(Host::Domain(domain)) => {
+            let port = url.port_or_known_default().unwrap_or(0);
+            resolve_public(domain, port).await.map(|_| ())
+        }
+    }
+}
+
+/// reqwest resolver that refuses names resolving to any non-public address.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PublicOnlyResolver;
+
+impl Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = resolve_public(name.as_str(), 0)
+                .await
+                .map_err(|err| Box::new(err) as Box<dyn StdError + Send + Sync>)?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_internal_ranges() {
+        for internal in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+        ] {
+            let ip: IpAddr = internal.parse().unwrap();
+            assert!(!is_public_ip(ip), "{internal} should be internal");
+        }
+        for public in ["93.184.216.34", "8.8.8.8", "2606:4700:4700::1111"] {
+            let ip: IpAddr = public.parse().unwrap();
+            assert!(is_public_ip(ip), "{public} should be public");
+        }
+    }
+
+    #[tokio::test]
+    async fn blocks_hosts_resolving_to_private_addresses() {
+        let url = Url::parse("http://localhost:8080/hook").unwrap();
+        assert!(matches!(
+            check_target(&url).await,
+            Err(EgressBlocked::PrivateAddress(ip)) if ip.is_loopback()
+        ));
+
+        let metadata = Url::parse("http://169.254.169.254/latest/meta-data").unwrap();
+        assert!(matches!(
+            check_target(&metadata).await,
+            Err(EgressBlocked::PrivateAddress(_))
+        ));
+
+        let public = Url::parse("https://93.184.216.34/").unwrap();
+        assert_eq!(check_target(&public).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn resolver_rejects_private_answers() {
+        let name: Name = "localhost".parse().unwrap();
+        assert!(PublicOnlyResolver.resolve(name).await.is_err());
+    }
+}
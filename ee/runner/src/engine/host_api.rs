@@ -1,13 +1,18 @@
 use super::component;
-use super::loader::{HostExecutionContext, HostState};
+use super::egress;
+use super::http_upload::{HttpUpload, UploadError};
+use super::loader::{HostExecutionContext, HostState, SecretMaterial};
+use super::metrics::{MetricKind, MetricRejection, MetricsRegistry, EXT_METRICS};
 use crate::models::{
     ExecuteRequest as ModelExecuteRequest, ExecuteResponse as ModelExecuteResponse, HttpPayload,
+    UserInfo,
 };
 use crate::providers::{
     CAP_CLIENT_READ, CAP_CONTEXT_READ, CAP_HTTP_FETCH, CAP_INVOICE_MANUAL_CREATE, CAP_LOG_EMIT,
-    CAP_SCHEDULER_MANAGE, CAP_SECRETS_GET, CAP_SERVICE_READ, CAP_STORAGE_KV, CAP_UI_PROXY,
-    CAP_USER_READ,
+    CAP_METRICS_EMIT, CAP_SCHEDULER_MANAGE, CAP_SECRETS_GET, CAP_SERVICE_READ, CAP_STORAGE_KV,
+    CAP_UI_PROXY, CAP_USER_READ,
 };
+use crate::util::{redact, request_id};
 use anyhow::{anyhow, Context};
 use base64::Engine as _;
 use once_cell::sync::Lazy;
@@ -22,91 +27,289 @@ use wasmtime::component::{Accessor, HasSelf, Linker};
 use component::alga::extension::types;
 use component::alga::extension::{
     clients::{self, ClientReadError, ClientSummary, ClientsListInput, ClientsListResult},
-    context,
-    http::{self, HttpError, HttpRequest, HttpResponse},
+    context, context_v2, context_v3,
+    http::{self, HttpError, HttpRequest, HttpResponse, HttpUploadError},
     invoicing::{self, CreateManualInvoiceInput, CreateManualInvoiceResult},
     logging,
+    metrics::{self, MetricTag, MetricsError},
     scheduler::{
         self, CreateScheduleInput, CreateScheduleResult, DeleteScheduleResult, EndpointInfo,
         ScheduleInfo, SchedulerError, UpdateScheduleInput, UpdateScheduleResult,
     },
     secrets::{self, SecretError},
+    services::{self, ServiceReadError, ServiceSummary, ServicesListInput, ServicesListResult},
     storage::{self, StorageEntry, StorageError},
-    services::{
-        self, ServiceReadError, ServiceSummary, ServicesListInput, ServicesListResult,
-    },
+    storage_v2,
     types::{
-        ContextData, ExecuteRequest as WitExecuteRequest, ExecuteResponse as WitExecuteResponse,
-        HttpHeader, ServiceBillingMethod, ServiceItemKind, UserData, UserDataV2, UserError,
+        ContextData, ContextError, ExecuteRequest as WitExecuteRequest,
+        ExecuteResponse as WitExecuteResponse, HttpHeader, ServiceBillingMethod, ServiceItemKind,
+        StorageErrorV2, UserData, UserDataV2, UserError,
     },
     ui_proxy::{self, ProxyError},
-    user,
-    user_v2,
+    user, user_v2,
 };
 
+/// Matches the storage API's default per-value quota.
+pub const DEFAULT_STORAGE_MAX_VALUE_BYTES: usize = 64 * 1024;
+
+/// Outbound policy for `http.fetch`, selected by `EXT_EGRESS_MODE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EgressMode {
+    /// Any host when `EXT_EGRESS_ALLOWLIST` is empty, otherwise only listed hosts (default).
+    AllowAll,
+    /// No outbound fetches at all.
+    DenyAll,
+    /// Only listed hosts; an empty allowlist denies everything.
+    Allowlist,
+}
+
+impl EgressMode {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "allow-all" => Some(EgressMode::AllowAll),
+            "deny-all" => Some(EgressMode::DenyAll),
+            "allowlist" => Some(EgressMode::Allowlist),
+            _ => None,
+        }
+    }
+}
+
+/// How a tenant's `egress_allowlist` combines with the global policy (`EXT_EGRESS_TENANT_OVERLAY`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TenantEgressOverlay {
+    /// Hosts on the tenant list are allowed in addition to the global policy.
+    Union,
+    /// The tenant list only narrows what the global policy allows (default). The list comes
+    /// from the execute request, so by default a caller can restrict egress but never widen it.
+    Intersect,
+}
+
+impl TenantEgressOverlay {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "union" => Some(TenantEgressOverlay::Union),
+            "intersect" => Some(TenantEgressOverlay::Intersect),
+            _ => None,
+        }
+    }
+}
+
+/// Trimmed, lowercased hosts with blanks dropped.
+pub fn normalize_allowlist<I, S>(hosts: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    hosts
+        .into_iter()
+        .map(|host| host.as_ref().trim().to_ascii_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+/// Runner → server internal API endpoints and the runner's service token, shared by the
+/// storage, scheduler, invoicing, clients, services and user capabilities.
+#[derive(Clone, Default)]
+pub struct InternalApiConfig {
+    /// `STORAGE_API_BASE_URL`, falling back to `REGISTRY_BASE_URL`.
+    pub base_url: Option<String>,
+    /// `USER_SERVICE_BASE_URL`, falling back to `base_url`.
+    pub user_service_base_url: Option<String>,
+    /// `RUNNER_STORAGE_API_TOKEN`, falling back to `RUNNER_SERVICE_TOKEN`.
+    pub token: Option<String>,
+}
+
+impl std::fmt::Debug for InternalApiConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InternalApiConfig")
+            .field("base_url", &self.base_url)
+            .field("user_service_base_url", &self.user_service_base_url)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl InternalApiConfig {
+    fn from_lookup(value: impl Fn(&str) -> Option<String>, problems: &mut Vec<String>) -> Self {
+        let mut base = |keys: &[&str]| {
+            let (key, raw) = keys
+                .iter()
+                .find_map(|key| value(key).map(|raw| (*key, raw)))?;
+            match Url::parse(&raw) {
+                Ok(_) => Some(normalize_internal_base_url(raw)),
+                Err(err) => {
+                    problems.push(format!("{key} is not a valid URL ({err}): {raw:?}"));
+                    None
+                }
+            }
+        };
+        let base_url = base(&["STORAGE_API_BASE_URL", "REGISTRY_BASE_URL"]);
+        let user_service_base_url = base(&["USER_SERVICE_BASE_URL"]).or_else(|| base_url.clone());
+        Self {
+            base_url,
+            user_service_base_url,
+            token: value("RUNNER_STORAGE_API_TOKEN").or_else(|| value("RUNNER_SERVICE_TOKEN")),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct HostRuntimeConfig {
+    pub egress_mode: EgressMode,
     pub egress_allowlist: Vec<String>,
+    pub egress_tenant_overlay: TenantEgressOverlay,
+    /// Reject fetches whose host resolves to a non-public address (`EXT_EGRESS_BLOCK_PRIVATE_IPS`).
+    /// Off by default so allowlisted internal hosts keep working; startup warns while it is off.
+    pub egress_block_private_ips: bool,
     pub ui_proxy_base: Option<Url>,
     pub ui_proxy_auth: Option<String>,
     pub ui_proxy_timeout: Duration,
+    /// Largest base64-encoded value `storage.put` forwards to the storage API.
+    pub storage_max_value_bytes: usize,
+    /// Capability audit trail (`EXT_AUDIT_LOG_PATH` or `EXT_AUDIT_LOG_URL`).
+    pub audit: AuditLog,
+    pub internal_api: InternalApiConfig,
+    /// Install config keys exposed to the guest as WASI env vars (`EXT_WASI_ENV_ALLOWLIST`);
+    /// a trailing `*` matches a prefix.
+    pub wasi_env_allowlist: Vec<String>,
 }
 
 impl Default for HostRuntimeConfig {
     fn default() -> Self {
         Self {
+            egress_mode: EgressMode::AllowAll,
             egress_allowlist: Vec::new(),
+            egress_tenant_overlay: TenantEgressOverlay::Intersect,
+            egress_block_private_ips: false,
             ui_proxy_base: None,
             ui_proxy_auth: None,
             ui_proxy_timeout: Duration::from_millis(5_000),
+            storage_max_value_bytes: DEFAULT_STORAGE_MAX_VALUE_BYTES,
+            audit: AuditLog::default(),
+            internal_api: InternalApiConfig::default(),
+            wasi_env_allowlist: Vec::new(),
         }
     }
 }
 
+impl std::fmt::Debug for HostRuntimeConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostRuntimeConfig")
+            .field("egress_mode", &self.egress_mode)
+            .field("egress_allowlist", &self.egress_allowlist)
+            .field("egress_tenant_overlay", &self.egress_tenant_overlay)
+            .field("egress_block_private_ips", &self.egress_block_private_ips)
+            .field("ui_proxy_base", &self.ui_proxy_base)
+            .field(
+                "ui_proxy_auth",
+                &self.ui_proxy_auth.as_ref().map(|_| "<redacted>"),
+            )
+            .field("ui_proxy_timeout", &self.ui_proxy_timeout)
+            .field("storage_max_value_bytes", &self.storage_max_value_bytes)
+            .field("internal_api", &self.internal_api)
+            .field("wasi_env_allowlist", &self.wasi_env_allowlist)
+            .finish_non_exhaustive()
+    }
+}
+
 impl HostRuntimeConfig {
-    pub fn from_env() -> Self {
+    /// Defaults with each variable `value` returns applied; invalid values are recorded in
+    /// `problems` so startup can report them with the rest of the runner configuration.
+    pub(crate) fn from_lookup(
+        value: impl Fn(&str) -> Option<String>,
+        problems: &mut Vec<String>,
+    ) -> Self {
         let mut cfg = Self::default();
-        cfg.egress_allowlist = std::env::var("EXT_EGRESS_ALLOWLIST")
-            .ok()
+        cfg.egress_allowlist = value("EXT_EGRESS_ALLOWLIST")
+            .map(|s| normalize_allowlist(s.split(',')))
+            .unwrap_or_default();
+        if let Some(raw) = value("EXT_EGRESS_TENANT_OVERLAY") {
+            match TenantEgressOverlay::parse(&raw) {
+                Some(overlay) => cfg.egress_tenant_overlay = overlay,
+                None => problems.push(format!(
+                    "EXT_EGRESS_TENANT_OVERLAY must be 'union' or 'intersect', got {raw:?}"
+                )),
+            }
+        }
+        cfg.wasi_env_allowlist = value("EXT_WASI_ENV_ALLOWLIST")
             .map(|s| {
                 s.split(',')
-                    .map(|item| item.trim().to_ascii_lowercase())
+                    .map(|item| item.trim().to_string())
                     .filter(|item| !item.is_empty())
                     .collect()
             })
             .unwrap_or_default();
+        if let Some(raw) = value("EXT_EGRESS_MODE") {
+            match EgressMode::parse(&raw) {
+                Some(mode) => cfg.egress_mode = mode,
+                None => problems.push(format!(
+                    "EXT_EGRESS_MODE must be 'allow-all', 'deny-all' or 'allowlist', got {raw:?}"
+                )),
+            }
+        }
+        if let Some(block) = crate::config::parse_bool(
+            problems,
+            "EXT_EGRESS_BLOCK_PRIVATE_IPS",
+            value("EXT_EGRESS_BLOCK_PRIVATE_IPS"),
+        ) {
+            cfg.egress_block_private_ips = block;
+        }
 
-        if let Ok(base) = std::env::var("UI_PROXY_BASE_URL") {
+        cfg.audit = AuditLog::from_lookup(&value, problems);
+        cfg.internal_api = InternalApiConfig::from_lookup(&value, problems);
+
+        if let Some(base) = value("UI_PROXY_BASE_URL") {
             match Url::parse(&base) {
                 Ok(url) => cfg.ui_proxy_base = Some(url),
-                Err(err) => {
-                    tracing::warn!(base = %base, error = %err, "UI_PROXY_BASE_URL is invalid; UI proxy calls disabled");
-                }
+                Err(err) => problems.push(format!(
+                    "UI_PROXY_BASE_URL is not a valid URL ({err}): {base:?}"
+                )),
             }
         }
-
-        if let Ok(auth) = std::env::var("UI_PROXY_AUTH_KEY") {
-            if !auth.trim().is_empty() {
-                cfg.ui_proxy_auth = Some(auth);
-            }
+        cfg.ui_proxy_auth = value("UI_PROXY_AUTH_KEY");
+        match value("UI_PROXY_TIMEOUT_MS").map(|raw| (raw.parse::<u64>(), raw)) {
+            Some((Ok(ms), _)) if ms > 0 => cfg.ui_proxy_timeout = Duration::from_millis(ms),
+            Some((_, raw)) => problems.push(format!(
+                "UI_PROXY_TIMEOUT_MS must be a positive number of milliseconds, got {raw:?}"
+            )),
+            None => {}
         }
-
-        if let Ok(raw_timeout) = std::env::var("UI_PROXY_TIMEOUT_MS") {
-            match raw_timeout.parse::<u64>() {
-                Ok(ms) if ms > 0 => {
-                    cfg.ui_proxy_timeout = Duration::from_millis(ms);
-                }
-                Ok(_) => {
-                    tracing::warn!(value = %raw_timeout, "UI_PROXY_TIMEOUT_MS must be > 0; falling back to default");
-                }
-                Err(err) => {
-                    tracing::warn!(value = %raw_timeout, error = %err, "failed to parse UI_PROXY_TIMEOUT_MS; using default");
-                }
-            }
+        match value("EXT_STORAGE_MAX_VALUE_BYTES").map(|raw| (raw.parse::<usize>(), raw)) {
+            Some((Ok(bytes), _)) if bytes > 0 => cfg.storage_max_value_bytes = bytes,
+            Some((_, raw)) => problems.push(format!(
+                "EXT_STORAGE_MAX_VALUE_BYTES must be a positive byte count, got {raw:?}"
+            )),
+            None => {}
         }
 
         cfg
     }
+
+    /// Warn about permissive or contradictory egress settings; called once by `run`.
+    pub fn log_startup_warnings(&self) {
+        match self.egress_mode {
+            EgressMode::AllowAll if self.egress_allowlist.is_empty() => {
+                tracing::warn!(
+                    "EXT_EGRESS_MODE is allow-all and EXT_EGRESS_ALLOWLIST is empty: extensions may fetch ANY host; set EXT_EGRESS_MODE=allowlist or deny-all in production"
+                );
+            }
+            EgressMode::DenyAll if !self.egress_allowlist.is_empty() => {
+                tracing::warn!(
+                    egress_allowlist=?self.egress_allowlist,
+                    "EXT_EGRESS_MODE is deny-all; EXT_EGRESS_ALLOWLIST is ignored"
+                );
+            }
+            EgressMode::Allowlist if self.egress_allowlist.is_empty() => {
+                tracing::warn!("EXT_EGRESS_MODE is allowlist but EXT_EGRESS_ALLOWLIST is empty; all egress is denied");
+            }
+            _ => {}
+        }
+        if !self.egress_block_private_ips {
+            tracing::warn!(
+                "EXT_EGRESS_BLOCK_PRIVATE_IPS is off; http.fetch may reach internal addresses. Set it to true unless extensions need allowlisted internal hosts"
+            );
+        }
+    }
 }
 
 static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
@@ -124,46 +327,26 @@ static HTTP_FETCH_CLIENT: Lazy<Client> = Lazy::new(|| {
         .expect("http fetch client")
 });
 
-static STORAGE_BASE_URL: Lazy<Option<String>> = Lazy::new(|| {
-    std::env::var("STORAGE_API_BASE_URL")
-        .or_else(|_| std::env::var("REGISTRY_BASE_URL"))
-        .ok()
-        .map(normalize_internal_base_url)
-});
-static RUNNER_STORAGE_API_TOKEN: Lazy<Option<String>> = Lazy::new(|| {
-    std::env::var("RUNNER_STORAGE_API_TOKEN")
-        .or_else(|_| std::env::var("RUNNER_SERVICE_TOKEN"))
-        .ok()
-});
-
-// Scheduler API uses the same base URL and token as storage
-static SCHEDULER_BASE_URL: Lazy<Option<String>> = Lazy::new(|| {
-    std::env::var("STORAGE_API_BASE_URL")
-        .or_else(|_| std::env::var("REGISTRY_BASE_URL"))
-        .ok()
-        .map(normalize_internal_base_url)
+/// Fetch client used while the private-address guard is on; re-checks resolution at connect time.
+static HTTP_FETCH_GUARDED_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(30))
+        .redirect(Policy::none())
+        .dns_resolver(std::sync::Arc::new(egress::PublicOnlyResolver))
+        .build()
+        .expect("guarded http fetch client")
 });
 
-// Invoicing API uses the same base URL and token as storage/scheduler
-static INVOICING_BASE_URL: Lazy<Option<String>> = Lazy::new(|| {
-    std::env::var("STORAGE_API_BASE_URL")
-        .or_else(|_| std::env::var("REGISTRY_BASE_URL"))
-        .ok()
-        .map(normalize_internal_base_url)
-});
+const USER_LOOKUP_CACHE_TTL: Duration = Duration::from_secs(60);
 
-static CLIENTS_BASE_URL: Lazy<Option<String>> = Lazy::new(|| {
-    std::env::var("STORAGE_API_BASE_URL")
-        .or_else(|_| std::env::var("REGISTRY_BASE_URL"))
-        .ok()
-        .map(normalize_internal_base_url)
-});
+/// Users resolved from the user service, keyed by (tenant, user id). Misses are cached too.
+type UserLookupCache = moka::future::Cache<(String, String), Option<UserInfo>>;
 
-static SERVICES_BASE_URL: Lazy<Option<String>> = Lazy::new(|| {
-    std::env::var("STORAGE_API_BASE_URL")
-        .or_else(|_| std::env::var("REGISTRY_BASE_URL"))
-        .ok()
-        .map(normalize_internal_base_url)
+static USER_LOOKUP_CACHE: Lazy<UserLookupCache> = Lazy::new(|| {
+    moka::future::Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(USER_LOOKUP_CACHE_TTL)
+        .build()
 });
 
 /// Normalize the configured base URL for runner → server internal calls.
@@ -316,9 +499,11 @@ fn require_service_read_access(
     Ok(install_id)
 }
 
-fn is_host_allowed(allowlist: &[String], url: &Url) -> bool {
-    if allowlist.is_empty() {
-        return true;
+fn is_host_allowed(mode: EgressMode, allowlist: &[String], url: &Url) -> bool {
+    match mode {
+        EgressMode::DenyAll => return false,
+        EgressMode::AllowAll if allowlist.is_empty() => return true,
+        EgressMode::AllowAll | EgressMode::Allowlist => {}
     }
     let host = match url.host_str() {
         Some(h) => h.to_ascii_lowercase(),
@@ -332,6 +517,42 @@ fn is_host_allowed(allowlist: &[String], url: &Url) -> bool {
     })
 }
 
+/// Which allowlist rejected an `http.fetch` target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EgressDenial {
+    Global,
+    Tenant,
+    /// Union overlay: neither the global policy nor the tenant list allows the host.
+    GlobalAndTenant,
+}
+
+fn check_egress(
+    config: &HostRuntimeConfig,
+    tenant_allowlist: Option<&[String]>,
+    url: &Url,
+) -> Result<(), EgressDenial> {
+    let global_ok = is_host_allowed(config.egress_mode, &config.egress_allowlist, url);
+    let Some(tenant_allowlist) = tenant_allowlist else {
+        return if global_ok {
+            Ok(())
+        } else {
+            Err(EgressDenial::Global)
+        };
+    };
+    // deny-all is absolute; tenant lists cannot reopen egress.
+    if config.egress_mode == EgressMode::DenyAll {
+        return Err(EgressDenial::Global);
+    }
+    let tenant_ok = is_host_allowed(EgressMode::Allowlist, tenant_allowlist, url);
+    match config.egress_tenant_overlay {
+        TenantEgressOverlay::Union if global_ok || tenant_ok => Ok(()),
+        TenantEgressOverlay::Union => Err(EgressDenial::GlobalAndTenant),
+        TenantEgressOverlay::Intersect if !global_ok => Err(EgressDenial::Global),
+        TenantEgressOverlay::Intersect if !tenant_ok => Err(EgressDenial::Tenant),
+        TenantEgressOverlay::Intersect => Ok(()),
+    }
+}
+
 fn clone_context_for_host(state: &HostState) -> HostExecutionContext {
     state.context.clone()
 }
@@ -356,6 +577,228 @@ fn redact_identifier(value: &str) -> String {
     format!("{}…{}", prefix, suffix)
 }
 
+/// Where capability audit records go.
+pub enum AuditSink {
+    /// Append JSON lines to a file (`EXT_AUDIT_LOG_PATH`).
+    File(std::fs::File),
+    /// POST each JSON line to an endpoint (`EXT_AUDIT_LOG_URL`), with `EXT_AUDIT_LOG_TOKEN`
+    /// as a bearer token when set.
+    Endpoint { url: Url, token: Option<String> },
+}
+
+/// Records waiting for the sink; once full, new records are dropped with a warning so a slow
+/// sink never stalls host calls.
+const AUDIT_QUEUE_CAPACITY: usize = 1024;
+
+/// Opt-in JSON-lines trail of host capability calls; disabled unless a sink is configured.
+#[derive(Clone, Default)]
+pub struct AuditLog(Option<AuditQueue>);
+
+#[derive(Clone)]
+enum AuditQueue {
+    Channel(tokio::sync::mpsc::Sender<Value>),
+    #[cfg(test)]
+    Memory(std::sync::Arc<std::sync::Mutex<Vec<Value>>>),
+}
+
+/// Writes queued records to `sink` one at a time until every sender is gone.
+async fn drain_audit_queue(mut sink: AuditSink, mut queue: tokio::sync::mpsc::Receiver<Value>) {
+    let client = Client::new();
+    while let Some(event) = queue.recv().await {
+        match &mut sink {
+            AuditSink::File(file) => {
+                use std::io::Write as _;
+                if let Err(err) = writeln!(file, "{event}") {
+                    tracing::warn!(error=%err, "capability audit write failed");
+                }
+            }
+            AuditSink::Endpoint { url, token } => {
+                let mut request = client
+                    .post(url.clone())
+                    .header("content-type", "application/x-ndjson")
+                    .body(format!("{event}\n"));
+                if let Some(token) = token.as_deref() {
+                    request = request.bearer_auth(token);
+                }
+                if let Err(err) = request.send().await {
+                    tracing::warn!(error=%err, "capability audit delivery failed");
+                }
+            }
+        }
+    }
+}
+
+impl AuditLog {
+    /// The sink `EXT_AUDIT_LOG_PATH` (preferred) or `EXT_AUDIT_LOG_URL` names, with its writer
+    /// started; disabled when neither is set. A file that cannot be opened or an invalid URL
+    /// is recorded in `problems`.
+    fn from_lookup(value: impl Fn(&str) -> Option<String>, problems: &mut Vec<String>) -> Self {
+        if let Some(path) = value("EXT_AUDIT_LOG_PATH") {
+            return match std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+            {
+                Ok(file) => AuditLog::spawn(AuditSink::File(file)),
+                Err(err) => {
+                    problems.push(format!(
+                        "EXT_AUDIT_LOG_PATH could not be opened ({err}): {path:?}"
+                    ));
+                    AuditLog::default()
+                }
+            };
+        }
+        if let Some(raw) = value("EXT_AUDIT_LOG_URL") {
+            return match Url::parse(&raw) {
+                Ok(url) => AuditLog::spawn(AuditSink::Endpoint {
+                    url,
+                    token: value("EXT_AUDIT_LOG_TOKEN"),
+                }),
+                Err(err) => {
+                    problems.push(format!(
+                        "EXT_AUDIT_LOG_URL is not a valid URL ({err}): {raw:?}"
+                    ));
+                    AuditLog::default()
+                }
+            };
+        }
+        AuditLog::default()
+    }
+
+    /// Start the single background writer for `sink`. It runs on its own thread so file writes
+    /// and deliveries never block a runtime worker, and so the log can be built before the
+    /// runtime exists.
+    pub fn spawn(sink: AuditSink) -> Self {
+        let (sender, queue) = tokio::sync::mpsc::channel(AUDIT_QUEUE_CAPACITY);
+        let started = std::thread::Builder::new()
+            .name("capability-audit".into())
+            .spawn(move || {
+                match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime.block_on(drain_audit_queue(sink, queue)),
+                    Err(err) => {
+                        tracing::error!(error=%err, "capability audit writer could not start")
+                    }
+                }
+            });
+        if let Err(err) = started {
+            tracing::error!(error=%err, "capability audit writer could not start; capability audit disabled");
+            return Self::default();
+        }
+        Self(Some(AuditQueue::Channel(sender)))
+    }
+
+    /// Record one capability call. `target` must already be redacted.
+    fn record<T, E: AuditedError>(
+        &self,
+        ctx: &HostExecutionContext,
+        capability: &str,
+        target: String,
+        result: &Result<T, E>,
+    ) {
+        let Some(queue) = &self.0 else {
+            return;
+        };
+        let mut event = serde_json::json!({
+            "ts": humantime::format_rfc3339_millis(std::time::SystemTime::now()).to_string(),
+            "capability": capability,
+            "tenant": ctx.tenant_id,
+            "extension": ctx.extension_id,
+            "request_id": ctx.request_id,
+            "target": target,
+            "outcome": if result.is_ok() { "ok" } else { "error" },
+        });
+        if let Err(err) = result {
+            event["error"] = Value::String(err.audit_name().to_string());
+        }
+
+        match queue {
+            AuditQueue::Channel(sender) => {
+                if let Err(err) = sender.try_send(event) {
+                    tracing::warn!(error=%err, "capability audit record dropped");
+                }
+            }
+            #[cfg(test)]
+            AuditQueue::Memory(events) => events.lock().unwrap().push(event),
+        }
+    }
+}
+
+/// WIT error enums recorded by name in audit events.
+trait AuditedError {
+    fn audit_name(&self) -> &'static str;
+}
+
+impl AuditedError for HttpError {
+    fn audit_name(&self) -> &'static str {
+        self.name()
+    }
+}
+
+impl AuditedError for HttpUploadError {
+    fn audit_name(&self) -> &'static str {
+        self.name()
+    }
+}
+
+impl AuditedError for SecretError {
+    fn audit_name(&self) -> &'static str {
+        self.name()
+    }
+}
+
+impl AuditedError for StorageErrorV2 {
+    fn audit_name(&self) -> &'static str {
+        self.name()
+    }
+}
+
+/// Origin and path of a fetched URL with query values redacted; credentials are dropped.
+fn audit_url_target(method: &str, raw: &str) -> String {
+    let Ok(url) = Url::parse(raw) else {
+        return format!("{} {}", method, redact_identifier(raw));
+    };
+    let mut target = format!(
+        "{} {}://{}{}",
+        method,
+        url.scheme(),
+        url.host_str().unwrap_or_default(),
+        url.port().map(|p| format!(":{p}")).unwrap_or_default()
+    );
+    target.push_str(url.path());
+    let query: Vec<String> = url
+        .query_pairs()
+        .map(|(k, v)| format!("{}={}", k, redact_identifier(&v)))
+        .collect();
+    if !query.is_empty() {
+        target.push('?');
+        target.push_str(&query.join("&"));
+    }
+    target
+}
+
+fn audit_storage_target(namespace: &str, key: Option<&str>) -> String {
+    match key {
+        Some(key) => format!("{}/{}", namespace, redact_identifier(key)),
+        None => namespace.to_string(),
+    }
+}
+
+/// Await `call` and record its outcome against `capability`.
+async fn audited<T, E: AuditedError>(
+    audit: &AuditLog,
+    ctx: &HostExecutionContext,
+    capability: &str,
+    target: String,
+    call: impl std::future::Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let result = call.await;
+    audit.record(ctx, capability, target, &result);
+    result
+}
+
 #[allow(clippy::too_many_arguments)]
 fn make_storage_entry(
     namespace: String,
@@ -371,7 +814,48 @@ fn make_storage_entry(
     }
 }
 
+/// The caller's context, `Denied` when `cap:context.read` is missing, or `Internal` when
+/// the runner started the execution without a tenant or extension id.
+fn resolve_context(
+    ctx: HostExecutionContext,
+    providers: &HashSet<String>,
+) -> Result<ContextData, ContextError> {
+    if !has_capability(providers, CAP_CONTEXT_READ) {
+        tracing::error!(
+            tenant = ?ctx.tenant_id,
+            extension = ?ctx.extension_id,
+            request_id = ?ctx.request_id,
+            providers = ?providers,
+            "context capability denied - cap:context.read not granted"
+        );
+        return Err(ContextError::Denied);
+    }
+    let (Some(tenant_id), Some(extension_id)) = (ctx.tenant_id, ctx.extension_id) else {
+        tracing::error!(
+            request_id = ?ctx.request_id,
+            "execution context is missing its tenant or extension id"
+        );
+        return Err(ContextError::Internal);
+    };
+    let data = ContextData {
+        request_id: ctx.request_id,
+        tenant_id,
+        extension_id,
+        install_id: ctx.install_id,
+        version_id: ctx.version_id,
+    };
+    tracing::debug!(
+        tenant = ?data.tenant_id,
+        extension = ?data.extension_id,
+        request_id = ?data.request_id,
+        "context capability granted; returning context"
+    );
+    Ok(data)
+}
+
 impl context::HostWithStore for HasSelf<HostState> {
+    /// Legacy signature without an error channel: a denied capability yields an empty
+    /// context rather than trapping the guest. `context-v2` reports `denied` instead.
     fn get_context<T>(
         accessor: &Accessor<T, Self>,
     ) -> impl std::future::Future<Output = ContextData> + Send {
@@ -384,76 +868,167 @@ impl context::HostWithStore for HasSelf<HostState> {
         });
 
         async move {
-            if !has_capability(&providers, CAP_CONTEXT_READ) {
-                tracing::error!(
-                    tenant = ?ctx.tenant_id,
-                    extension = ?ctx.extension_id,
-                    request_id = ?ctx.request_id,
-                    providers = ?providers,
-                    "context capability missing; guest will panic"
-                );
-                panic!("capability_not_granted: {CAP_CONTEXT_READ}");
-            }
-            let data = ContextData {
-                request_id: ctx.request_id,
-                tenant_id: ctx.tenant_id.unwrap_or_default(),
-                extension_id: ctx.extension_id.unwrap_or_default(),
-                install_id: ctx.install_id,
-                version_id: ctx.version_id,
-            };
-            tracing::debug!(
-                tenant = ?data.tenant_id,
-                extension = ?data.extension_id,
-                request_id = ?data.request_id,
-                "context capability granted; returning context"
-            );
-            data
+            resolve_context(ctx, &providers).unwrap_or_else(|_| ContextData {
+                request_id: None,
+                tenant_id: String::new(),
+                extension_id: String::new(),
+                install_id: None,
+                version_id: None,
+            })
         }
     }
 }
 
+impl context_v2::HostWithStore for HasSelf<HostState> {
+    fn get_context<T>(
+        accessor: &Accessor<T, Self>,
+    ) -> impl std::future::Future<Output = Result<ContextData, ContextError>> + Send {
+        let (ctx, providers) = accessor.with(|mut access| {
+            let state = access.get();
+            (
+                clone_context_for_host(state),
+                state.context.providers.clone(),
+            )
+        });
+
+        async move { resolve_context(ctx, &providers) }
+    }
+}
+
+/// `context-v2` plus the per-execution scratch map kept on `HostState`.
+impl context_v3::HostWithStore for HasSelf<HostState> {
+    fn get_context<T>(
+        accessor: &Accessor<T, Self>,
+    ) -> impl std::future::Future<Output = Result<ContextData, ContextError>> + Send {
+        let (ctx, providers) = accessor.with(|mut access| {
+            let state = access.get();
+            (
+                clone_context_for_host(state),
+                state.context.providers.clone(),
+            )
+        });
+
+        async move { resolve_context(ctx, &providers) }
+    }
+
+    fn scratch_get<T>(
+        accessor: &Accessor<T, Self>,
+        key: String,
+    ) -> impl std::future::Future<Output = Option<String>> + Send {
+        let value = accessor.with(|mut access| {
+            let state = access.get();
+            scratch_get(&state.context.providers, &state.scratch, &key)
+        });
+        async move { value }
+    }
+
+    fn scratch_put<T>(
+        accessor: &Accessor<T, Self>,
+        key: String,
+        value: String,
+    ) -> impl std::future::Future<Output = Result<(), StorageErrorV2>> + Send {
+        let result = accessor.with(|mut access| {
+            let state = access.get();
+            scratch_put(&state.context.providers, &mut state.scratch, key, value)
+        });
+        async move { result }
+    }
+}
+
+/// Caps on the per-execution scratch map so a guest cannot grow host memory unbounded.
+const SCRATCH_MAX_ENTRIES: usize = 256;
+const SCRATCH_MAX_KEY_BYTES: usize = 256;
+const SCRATCH_MAX_VALUE_BYTES: usize = 64 * 1024;
+
+fn scratch_get(
+    providers: &HashSet<String>,
+    scratch: &HashMap<String, String>,
+    key: &str,
+) -> Option<String> {
+    if !has_capability(providers, CAP_CONTEXT_READ) {
+        return None;
+    }
+    scratch.get(key).cloned()
+}
+
+fn scratch_put(
+    providers: &HashSet<String>,
+    scratch: &mut HashMap<String, String>,
+    key: String,
+    value: String,
+) -> Result<(), StorageErrorV2> {
+    if !has_capability(providers, CAP_CONTEXT_READ) {
+        return Err(StorageErrorV2::Denied);
+    }
+    if key.len() > SCRATCH_MAX_KEY_BYTES || value.len() > SCRATCH_MAX_VALUE_BYTES {
+        return Err(StorageErrorV2::TooLarge);
+    }
+    if scratch.len() >= SCRATCH_MAX_ENTRIES && !scratch.contains_key(&key) {
+        return Err(StorageErrorV2::TooLarge);
+    }
+    scratch.insert(key, value);
+    Ok(())
+}
+
+fn secrets_get(
+    audit: &AuditLog,
+    ctx: &HostExecutionContext,
+    material: Option<SecretMaterial>,
+    key: String,
+) -> Result<String, SecretError> {
+    let result = lookup_secret(ctx, material, &key);
+    audit.record(ctx, "secrets.get", redact_identifier(&key), &result);
+    result
+}
+
+fn lookup_secret(
+    ctx: &HostExecutionContext,
+    material: Option<SecretMaterial>,
+    key: &str,
+) -> Result<String, SecretError> {
+    if !has_capability(&ctx.providers, CAP_SECRETS_GET) {
+        tracing::error!(
+            tenant = ?ctx.tenant_id,
+            extension = ?ctx.extension_id,
+            request_id = ?ctx.request_id,
+            "secrets capability denied - cap:secrets.get not granted"
+        );
+        return Err(SecretError::Denied);
+    }
+    let Some(secrets) = material else {
+        return Err(SecretError::Missing);
+    };
+    let tenant = ctx.tenant_id.as_deref().unwrap_or_default();
+    let extension = ctx.extension_id.as_deref().unwrap_or_default();
+    let available_keys: Vec<String> = secrets.values.keys().cloned().collect();
+    tracing::info!(
+        tenant=%tenant,
+        extension=%extension,
+        key_redacted=%redact_identifier(key),
+        available_keys=?available_keys,
+        "secrets capability get attempt"
+    );
+    match secrets.values.get(key) {
+        Some(value) => Ok(value.clone()),
+        None => Err(SecretError::Missing),
+    }
+}
+
 impl secrets::HostWithStore for HasSelf<HostState> {
     fn get<T>(
         accessor: &Accessor<T, Self>,
         key: String,
     ) -> impl std::future::Future<Output = Result<String, SecretError>> + Send {
-        let (providers, material, ctx) = accessor.with(|mut access| {
+        let (audit, material, ctx) = accessor.with(|mut access| {
             let state = access.get();
             (
-                state.context.providers.clone(),
+                state.runtime.audit.clone(),
                 state.context.secrets.clone(),
                 state.context.clone(),
             )
         });
 
-        async move {
-            if !has_capability(&providers, CAP_SECRETS_GET) {
-                tracing::error!(
-                    tenant = ?ctx.tenant_id,
-                    extension = ?ctx.extension_id,
-                    request_id = ?ctx.request_id,
-                    "secrets capability denied - cap:secrets.get not granted"
-                );
-                return Err(SecretError::Denied);
-            }
-            let Some(secrets) = material else {
-                return Err(SecretError::Missing);
-            };
-            let tenant = ctx.tenant_id.unwrap_or_default();
-            let extension = ctx.extension_id.unwrap_or_default();
-            let available_keys: Vec<String> = secrets.values.keys().cloned().collect();
-            tracing::info!(
-                tenant=%tenant,
-                extension=%extension,
-                key_redacted=%redact_identifier(&key),
-                available_keys=?available_keys,
-                "secrets capability get attempt"
-            );
-            match secrets.values.get(&key) {
-                Some(value) => Ok(value.clone()),
-                None => Err(SecretError::Missing),
-            }
-        }
+        async move { secrets_get(&audit, &ctx, material, key) }
     }
 
     fn list_keys<T>(
@@ -488,9 +1063,204 @@ impl secrets::HostWithStore for HasSelf<HostState> {
     }
 }
 
-impl http::HostWithStore for HasSelf<HostState> {
-    fn fetch<T>(
-        accessor: &Accessor<T, Self>,
+/// Capability, egress policy and private-address checks shared by `fetch` and `open-upload`.
+async fn authorize_fetch(
+    config: &HostRuntimeConfig,
+    ctx: &HostExecutionContext,
+    providers: &HashSet<String>,
+    request: &HttpRequest,
+) -> Result<(Url, Method), HttpError> {
+    if !has_capability(providers, CAP_HTTP_FETCH) {
+        tracing::error!(
+            tenant = ?ctx.tenant_id,
+            extension = ?ctx.extension_id,
+            request_id = ?ctx.request_id,
+            "http capability denied - cap:http.fetch not granted"
+        );
+        return Err(HttpError::NotAllowed);
+    }
+
+    let tenant = ctx.tenant_id.clone().unwrap_or_default();
+    let extension = ctx.extension_id.clone().unwrap_or_default();
+    let route = &request.url;
+    let method = &request.method;
+
+    let url = Url::parse(route).map_err(|_| HttpError::InvalidUrl)?;
+    if let Err(denied_by) = check_egress(config, ctx.egress_allowlist.as_deref(), &url) {
+        tracing::error!(
+            tenant=%tenant,
+            extension=%extension,
+            method=%method,
+            url=%route,
+            denied_by=?denied_by,
+            egress_mode=?config.egress_mode,
+            egress_allowlist=?config.egress_allowlist,
+            tenant_egress_allowlist=?ctx.egress_allowlist,
+            tenant_overlay=?config.egress_tenant_overlay,
+            "http capability denied by egress policy"
+        );
+        return Err(HttpError::NotAllowed);
+    }
+    if config.egress_block_private_ips {
+        if let Err(err) = egress::check_target(&url).await {
+            tracing::error!(
+                tenant=%tenant,
+                extension=%extension,
+                method=%method,
+                url=%route,
+                reason=%err,
+                "http capability denied by private address guard"
+            );
+            return Err(HttpError::NotAllowed);
+        }
+    }
+
+    let method: Method = method.parse().map_err(|_| HttpError::InvalidUrl)?;
+    Ok((url, method))
+}
+
+/// Guest headers are sent as given; the execution's request id is added unless the guest set one.
+fn fetch_request_builder(
+    config: &HostRuntimeConfig,
+    method: Method,
+    url: Url,
+    headers: Vec<HttpHeader>,
+    request_id: Option<&str>,
+) -> reqwest::RequestBuilder {
+    let client = if config.egress_block_private_ips {
+        &*HTTP_FETCH_GUARDED_CLIENT
+    } else {
+        &*HTTP_FETCH_CLIENT
+    };
+    let mut builder = client.request(method, url);
+    let guest_request_id = headers.iter().any(|header| {
+        header
+            .name
+            .eq_ignore_ascii_case(request_id::REQUEST_ID_HEADER)
+    });
+    if let Some(request_id) = request_id.filter(|_| !guest_request_id) {
+        builder = builder.header(request_id::REQUEST_ID_HEADER, request_id);
+    }
+    for header in headers {
+        builder = builder.header(&header.name, &header.value);
+    }
+    builder
+}
+
+async fn into_http_response(
+    response: reqwest::Response,
+    tenant: &str,
+    extension: &str,
+    route: &str,
+    started: Instant,
+) -> Result<HttpResponse, HttpError> {
+    let status = response.status().as_u16();
+    let elapsed_ms = started.elapsed().as_millis();
+    tracing::info!(
+        tenant=%tenant,
+        extension=%extension,
+        status,
+        elapsed_ms,
+        url=%route,
+        "http capability fetch completed"
+    );
+    let mut headers = Vec::new();
+    for (name, value) in response.headers().iter() {
+        headers.push(HttpHeader {
+            name: name.to_string(),
+            value: value.to_str().unwrap_or_default().to_string(),
+        });
+    }
+    let body_bytes = response.bytes().await.map_err(|err| {
+        tracing::error!(error = %err, "http_fetch body read failed");
+        HttpError::Internal
+    })?;
+
+    Ok(HttpResponse {
+        status,
+        headers,
+        body: Some(body_bytes.to_vec()),
+    })
+}
+
+fn upload_error_to_http(err: UploadError) -> HttpUploadError {
+    match err {
+        UploadError::TooManyOpen => HttpUploadError::TooManyUploads,
+        UploadError::UnknownHandle => HttpUploadError::Internal,
+        UploadError::Closed => HttpUploadError::Transport,
+    }
+}
+
+/// Fetch failures surfaced through the upload calls keep their meaning.
+fn fetch_error_to_upload(err: HttpError) -> HttpUploadError {
+    match err {
+        HttpError::InvalidUrl => HttpUploadError::InvalidUrl,
+        HttpError::NotAllowed => HttpUploadError::NotAllowed,
+        HttpError::Transport => HttpUploadError::Transport,
+        HttpError::Internal => HttpUploadError::Internal,
+    }
+}
+
+/// `http.fetch` body shared by the host binding and tests; records one audit event.
+async fn http_fetch(
+    config: &HostRuntimeConfig,
+    ctx: HostExecutionContext,
+    providers: &HashSet<String>,
+    request: HttpRequest,
+) -> Result<HttpResponse, HttpError> {
+    let target = audit_url_target(&request.method, &request.url);
+    let audit_ctx = ctx.clone();
+    audited(
+        &config.audit,
+        &audit_ctx,
+        "http.fetch",
+        target,
+        fetch_unaudited(config, ctx, providers, request),
+    )
+    .await
+}
+
+async fn fetch_unaudited(
+    config: &HostRuntimeConfig,
+    ctx: HostExecutionContext,
+    providers: &HashSet<String>,
+    request: HttpRequest,
+) -> Result<HttpResponse, HttpError> {
+    let (url, method) = authorize_fetch(config, &ctx, providers, &request).await?;
+    let tenant = ctx.tenant_id.unwrap_or_default();
+    let extension = ctx.extension_id.unwrap_or_default();
+    let route = request.url;
+    let started = Instant::now();
+    tracing::info!(
+        tenant=%tenant,
+        extension=%extension,
+        method=%method,
+        url=%route,
+        "http capability fetch start"
+    );
+
+    let mut builder = fetch_request_builder(
+        config,
+        method,
+        url,
+        request.headers,
+        ctx.request_id.as_deref(),
+    );
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+
+    let response = builder.send().await.map_err(|err| {
+        tracing::error!(error = %err, "http_fetch transport error");
+        HttpError::Transport
+    })?;
+
+    into_http_response(response, &tenant, &extension, &route, started).await
+}
+
+impl http::HostWithStore for HasSelf<HostState> {
+    fn fetch<T>(
+        accessor: &Accessor<T, Self>,
         request: HttpRequest,
     ) -> impl std::future::Future<Output = Result<HttpResponse, HttpError>> + Send {
         let (providers, config, ctx) = accessor.with(|mut access| {
@@ -502,85 +1272,98 @@ impl http::HostWithStore for HasSelf<HostState> {
             )
         });
 
+        async move { http_fetch(&config, ctx, &providers, request).await }
+    }
+
+    fn open_upload<T>(
+        accessor: &Accessor<T, Self>,
+        request: HttpRequest,
+    ) -> impl std::future::Future<Output = Result<u32, HttpUploadError>> + Send {
+        let (providers, config, ctx) = accessor.with(|mut access| {
+            let state = access.get();
+            (
+                state.context.providers.clone(),
+                state.runtime.clone(),
+                state.context.clone(),
+            )
+        });
+
         async move {
-            if !has_capability(&providers, CAP_HTTP_FETCH) {
-                tracing::error!(
+            let target = audit_url_target(&request.method, &request.url);
+            audited(&config.audit, &ctx, "http.open-upload", target, async {
+                let (url, method) = authorize_fetch(&config, &ctx, &providers, &request)
+                    .await
+                    .map_err(fetch_error_to_upload)?;
+                tracing::info!(
                     tenant = ?ctx.tenant_id,
                     extension = ?ctx.extension_id,
-                    request_id = ?ctx.request_id,
-                    "http capability denied - cap:http.fetch not granted"
-                );
-                return Err(HttpError::NotAllowed);
-            }
-
-            let tenant = ctx.tenant_id.unwrap_or_default();
-            let extension = ctx.extension_id.unwrap_or_default();
-            let route = request.url.clone();
-            let method = request.method.clone();
-
-            let url = Url::parse(&route).map_err(|_| HttpError::InvalidUrl)?;
-            if !is_host_allowed(&config.egress_allowlist, &url) {
-                tracing::error!(
-                    tenant=%tenant,
-                    extension=%extension,
                     method=%method,
-                    url=%route,
-                    egress_allowlist=?config.egress_allowlist,
-                    "http capability denied by allowlist"
+                    url=%request.url,
+                    "http capability streaming upload start"
                 );
-                return Err(HttpError::NotAllowed);
-            }
-
-            let method: Method = method.parse().map_err(|_| HttpError::InvalidUrl)?;
-            let started = Instant::now();
-            tracing::info!(
-                tenant=%tenant,
-                extension=%extension,
-                method=%method,
-                url=%route,
-                "http capability fetch start"
-            );
+                let builder = fetch_request_builder(
+                    &config,
+                    method,
+                    url,
+                    request.headers,
+                    ctx.request_id.as_deref(),
+                );
+                let upload = HttpUpload::start(builder);
+                let writer = upload.writer();
+                let handle = accessor
+                    .with(|mut access| access.get().uploads.insert(upload))
+                    .map_err(upload_error_to_http)?;
+                if let Some(body) = request.body {
+                    writer.write(body).await.map_err(upload_error_to_http)?;
+                }
+                Ok(handle)
+            })
+            .await
+        }
+    }
 
-            let mut builder = HTTP_FETCH_CLIENT.request(method, url);
-            for header in request.headers {
-                builder = builder.header(&header.name, &header.value);
-            }
-            if let Some(body) = request.body {
-                builder = builder.body(body);
-            }
+    fn write_chunk<T>(
+        accessor: &Accessor<T, Self>,
+        upload: u32,
+        chunk: Vec<u8>,
+    ) -> impl std::future::Future<Output = Result<(), HttpUploadError>> + Send {
+        let writer = accessor.with(|mut access| access.get().uploads.writer(upload));
 
-            let response = builder.send().await.map_err(|err| {
-                tracing::error!(error = %err, "http_fetch transport error");
-                HttpError::Transport
-            })?;
+        async move {
+            let writer = writer.map_err(upload_error_to_http)?;
+            writer.write(chunk).await.map_err(|err| {
+                tracing::error!(upload, "http_fetch streaming upload closed before finish");
+                upload_error_to_http(err)
+            })
+        }
+    }
 
-            let status = response.status().as_u16();
-            let elapsed_ms = started.elapsed().as_millis();
-            tracing::info!(
-                tenant=%tenant,
-                extension=%extension,
-                status,
-                elapsed_ms,
-                url=%route,
-                "http capability fetch completed"
-            );
-            let mut headers = Vec::new();
-            for (name, value) in response.headers().iter() {
-                headers.push(HttpHeader {
-                    name: name.to_string(),
-                    value: value.to_str().unwrap_or_default().to_string(),
-                });
-            }
-            let body_bytes = response.bytes().await.map_err(|err| {
-                tracing::error!(error = %err, "http_fetch body read failed");
-                HttpError::Internal
-            })?;
+    fn finish_upload<T>(
+        accessor: &Accessor<T, Self>,
+        upload: u32,
+    ) -> impl std::future::Future<Output = Result<HttpResponse, HttpUploadError>> + Send {
+        let (pending, ctx) = accessor.with(|mut access| {
+            let state = access.get();
+            (state.uploads.remove(upload), state.context.clone())
+        });
 
-            Ok(HttpResponse {
-                status,
-                headers,
-                body: Some(body_bytes.to_vec()),
-            })
+        async move {
+            let pending = pending.map_err(upload_error_to_http)?;
+            let tenant = ctx.tenant_id.unwrap_or_default();
+            let extension = ctx.extension_id.unwrap_or_default();
+            let started = Instant::now();
+            let response = pending
+                .finish()
+                .await
+                .map_err(upload_error_to_http)?
+                .map_err(|err| {
+                    tracing::error!(error = %err, "http_fetch streaming upload transport error");
+                    HttpUploadError::Transport
+                })?;
+            let route = response.url().to_string();
+            into_http_response(response, &tenant, &extension, &route, started)
+                .await
+                .map_err(fetch_error_to_upload)
         }
     }
 }
@@ -659,53 +1442,13 @@ impl storage::HostWithStore for HasSelf<HostState> {
         namespace: String,
         key: String,
     ) -> impl std::future::Future<Output = Result<StorageEntry, StorageError>> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
-            let state = access.get();
-            (
-                state.context.providers.clone(),
-                state.context.install_id.clone(),
-                state.context.clone(),
-            )
-        });
-
+        let (ctx, audit, api) = storage_accessor_state(accessor);
         async move {
-            if !has_capability(&providers, CAP_STORAGE_KV) {
-                tracing::error!(
-                    tenant = ?ctx.tenant_id,
-                    extension = ?ctx.extension_id,
-                    request_id = ?ctx.request_id,
-                    "storage capability denied - cap:storage.kv not granted"
-                );
-                return Err(StorageError::Denied);
-            }
-            let install_id = install_id
-                .filter(|id| !id.is_empty())
-                .ok_or_else(|| {
-                    tracing::error!(
-                        tenant = ?ctx.tenant_id,
-                        extension = ?ctx.extension_id,
-                        request_id = ?ctx.request_id,
-                        "storage capability denied - install_id missing or empty"
-                    );
-                    StorageError::Denied
-                })?;
-            let tenant = ctx.tenant_id.unwrap_or_default();
-            let extension = ctx.extension_id.unwrap_or_default();
-            let namespace_log = namespace.clone();
-            let key_log = key.clone();
-            tracing::info!(
-                tenant=%tenant,
-                extension=%extension,
-                namespace=%namespace_log,
-                key_redacted=%redact_identifier(&key_log),
-                "storage capability get start"
-            );
-            let mut payload = Map::new();
-            payload.insert("namespace".into(), Value::String(namespace.clone()));
-            payload.insert("key".into(), Value::String(key.clone()));
-
-            let value = storage_request(&install_id, "get", payload).await?;
-            parse_storage_entry(value, Some(namespace), Some(key))
+            let target = audit_storage_target(&namespace, Some(&key));
+            Ok(audited(&audit, &ctx, "storage.get", target, async {
+                storage_get(api.storage_target(), &ctx, namespace, key).await
+            })
+            .await?)
         }
     }
 
@@ -713,72 +1456,78 @@ impl storage::HostWithStore for HasSelf<HostState> {
         accessor: &Accessor<T, Self>,
         entry: StorageEntry,
     ) -> impl std::future::Future<Output = Result<StorageEntry, StorageError>> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
-            let state = access.get();
-            (
-                state.context.providers.clone(),
-                state.context.install_id.clone(),
-                state.context.clone(),
-            )
-        });
+        let (ctx, audit, api) = storage_accessor_state(accessor);
+        let max_value_bytes =
+            accessor.with(|mut access| access.get().runtime.storage_max_value_bytes);
+        async move {
+            let target = audit_storage_target(&entry.namespace, Some(&entry.key));
+            Ok(audited(&audit, &ctx, "storage.put", target, async {
+                storage_put(api.storage_target(), &ctx, entry, max_value_bytes).await
+            })
+            .await?)
+        }
+    }
 
+    fn delete<T>(
+        accessor: &Accessor<T, Self>,
+        namespace: String,
+        key: String,
+    ) -> impl std::future::Future<Output = Result<(), StorageError>> + Send {
+        let (ctx, audit, api) = storage_accessor_state(accessor);
         async move {
-            if !has_capability(&providers, CAP_STORAGE_KV) {
-                tracing::error!(
-                    tenant = ?ctx.tenant_id,
-                    extension = ?ctx.extension_id,
-                    request_id = ?ctx.request_id,
-                    "storage capability denied - cap:storage.kv not granted"
-                );
-                return Err(StorageError::Denied);
-            }
-            let install_id = install_id
-                .filter(|id| !id.is_empty())
-                .ok_or_else(|| {
-                    tracing::error!(
-                        tenant = ?ctx.tenant_id,
-                        extension = ?ctx.extension_id,
-                        request_id = ?ctx.request_id,
-                        "storage capability denied - install_id missing or empty"
-                    );
-                    StorageError::Denied
-                })?;
-            let tenant = ctx.tenant_id.unwrap_or_default();
-            let extension = ctx.extension_id.unwrap_or_default();
-            let namespace_log = entry.namespace.clone();
-            let key_log = entry.key.clone();
-            tracing::info!(
-                tenant=%tenant,
-                extension=%extension,
-                namespace=%namespace_log,
-                key_redacted=%redact_identifier(&key_log),
-                "storage capability put start"
-            );
+            let target = audit_storage_target(&namespace, Some(&key));
+            Ok(audited(&audit, &ctx, "storage.delete", target, async {
+                storage_delete(api.storage_target(), &ctx, namespace, key).await
+            })
+            .await?)
+        }
+    }
 
-            let mut payload = Map::new();
-            payload.insert("namespace".into(), Value::String(entry.namespace.clone()));
-            payload.insert("key".into(), Value::String(entry.key.clone()));
-            payload.insert(
-                "value".into(),
-                Value::String(
-                    base64::engine::general_purpose::STANDARD.encode(entry.value.clone()),
-                ),
-            );
-            let mut metadata = Map::new();
-            metadata.insert("encoding".into(), Value::String("base64-bytes".to_string()));
-            payload.insert("metadata".into(), Value::Object(metadata));
-            if let Some(rev) = entry.revision {
-                payload.insert("ifRevision".into(), Value::Number(rev.into()));
-            }
+    fn list_entries<T>(
+        accessor: &Accessor<T, Self>,
+        namespace: String,
+        cursor: Option<String>,
+    ) -> impl std::future::Future<Output = Result<Vec<StorageEntry>, StorageError>> + Send {
+        let (ctx, audit, api) = storage_accessor_state(accessor);
+        async move {
+            let target = audit_storage_target(&namespace, None);
+            Ok(audited(&audit, &ctx, "storage.list", target, async {
+                storage_list(api.storage_target(), &ctx, namespace, cursor).await
+            })
+            .await?)
+        }
+    }
+}
 
-            let response = storage_request(&install_id, "put", payload).await?;
-            let revision = response.get("revision").and_then(|v| v.as_u64());
-            Ok(make_storage_entry(
-                entry.namespace,
-                entry.key,
-                entry.value,
-                revision,
-            ))
+impl storage_v2::HostWithStore for HasSelf<HostState> {
+    fn get<T>(
+        accessor: &Accessor<T, Self>,
+        namespace: String,
+        key: String,
+    ) -> impl std::future::Future<Output = Result<StorageEntry, StorageErrorV2>> + Send {
+        let (ctx, audit, api) = storage_accessor_state(accessor);
+        async move {
+            let target = audit_storage_target(&namespace, Some(&key));
+            audited(&audit, &ctx, "storage.get", target, async {
+                storage_get(api.storage_target(), &ctx, namespace, key).await
+            })
+            .await
+        }
+    }
+
+    fn put<T>(
+        accessor: &Accessor<T, Self>,
+        entry: StorageEntry,
+    ) -> impl std::future::Future<Output = Result<StorageEntry, StorageErrorV2>> + Send {
+        let (ctx, audit, api) = storage_accessor_state(accessor);
+        let max_value_bytes =
+            accessor.with(|mut access| access.get().runtime.storage_max_value_bytes);
+        async move {
+            let target = audit_storage_target(&entry.namespace, Some(&entry.key));
+            audited(&audit, &ctx, "storage.put", target, async {
+                storage_put(api.storage_target(), &ctx, entry, max_value_bytes).await
+            })
+            .await
         }
     }
 
@@ -786,140 +1535,281 @@ impl storage::HostWithStore for HasSelf<HostState> {
         accessor: &Accessor<T, Self>,
         namespace: String,
         key: String,
-    ) -> impl std::future::Future<Output = Result<(), StorageError>> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
-            let state = access.get();
-            (
-                state.context.providers.clone(),
-                state.context.install_id.clone(),
-                state.context.clone(),
-            )
-        });
+    ) -> impl std::future::Future<Output = Result<(), StorageErrorV2>> + Send {
+        let (ctx, audit, api) = storage_accessor_state(accessor);
+        async move {
+            let target = audit_storage_target(&namespace, Some(&key));
+            audited(&audit, &ctx, "storage.delete", target, async {
+                storage_delete(api.storage_target(), &ctx, namespace, key).await
+            })
+            .await
+        }
+    }
 
+    fn list_entries<T>(
+        accessor: &Accessor<T, Self>,
+        namespace: String,
+        cursor: Option<String>,
+    ) -> impl std::future::Future<Output = Result<Vec<StorageEntry>, StorageErrorV2>> + Send {
+        let (ctx, audit, api) = storage_accessor_state(accessor);
         async move {
-            if !has_capability(&providers, CAP_STORAGE_KV) {
-                tracing::error!(
-                    tenant = ?ctx.tenant_id,
-                    extension = ?ctx.extension_id,
-                    request_id = ?ctx.request_id,
-                    "storage capability denied - cap:storage.kv not granted"
-                );
-                return Err(StorageError::Denied);
-            }
-            let install_id = install_id
-                .filter(|id| !id.is_empty())
-                .ok_or_else(|| {
-                    tracing::error!(
-                        tenant = ?ctx.tenant_id,
-                        extension = ?ctx.extension_id,
-                        request_id = ?ctx.request_id,
-                        "storage capability denied - install_id missing or empty"
-                    );
-                    StorageError::Denied
-                })?;
-            let tenant = ctx.tenant_id.unwrap_or_default();
-            let extension = ctx.extension_id.unwrap_or_default();
-            let namespace_log = namespace.clone();
-            let key_log = key.clone();
-            tracing::info!(
-                tenant=%tenant,
-                extension=%extension,
-                namespace=%namespace_log,
-                key_redacted=%redact_identifier(&key_log),
-                "storage capability delete start"
+            let target = audit_storage_target(&namespace, None);
+            audited(&audit, &ctx, "storage.list", target, async {
+                storage_list(api.storage_target(), &ctx, namespace, cursor).await
+            })
+            .await
+        }
+    }
+}
+
+fn storage_accessor_state<T>(
+    accessor: &Accessor<T, HasSelf<HostState>>,
+) -> (HostExecutionContext, AuditLog, InternalApiConfig) {
+    accessor.with(|mut access| {
+        let state = access.get();
+        (
+            state.context.clone(),
+            state.runtime.audit.clone(),
+            state.runtime.internal_api.clone(),
+        )
+    })
+}
+
+/// The legacy `storage` interface predates `too-large`; guests built against it
+/// see oversized writes as `denied`, matching how backend quota rejections were
+/// reported before.
+impl From<StorageErrorV2> for StorageError {
+    fn from(err: StorageErrorV2) -> Self {
+        match err {
+            StorageErrorV2::Missing => StorageError::Missing,
+            StorageErrorV2::Conflict => StorageError::Conflict,
+            StorageErrorV2::Denied | StorageErrorV2::TooLarge => StorageError::Denied,
+            StorageErrorV2::Internal => StorageError::Internal,
+        }
+    }
+}
+
+/// Storage API base URL and runner token from [`InternalApiConfig`].
+#[derive(Clone, Copy)]
+struct StorageTarget<'a> {
+    base: &'a str,
+    token: &'a str,
+}
+
+impl InternalApiConfig {
+    fn storage_target(&self) -> Option<StorageTarget<'_>> {
+        Some(StorageTarget {
+            base: self.base_url.as_deref()?,
+            token: self.token.as_deref()?,
+        })
+    }
+}
+
+/// Resolved only after [`require_storage_access`], so an extension without
+/// `cap:storage.kv` is denied even when storage is not configured.
+fn require_storage_target<'a>(
+    target: Option<StorageTarget<'a>>,
+) -> Result<StorageTarget<'a>, StorageErrorV2> {
+    target.ok_or_else(|| {
+        tracing::error!(
+            "storage capability unavailable - storage API base URL or runner token not configured"
+        );
+        StorageErrorV2::Internal
+    })
+}
+
+/// Checks `cap:storage.kv` and returns the install id storage calls are scoped to.
+fn require_storage_access(ctx: &HostExecutionContext) -> Result<String, StorageErrorV2> {
+    if !has_capability(&ctx.providers, CAP_STORAGE_KV) {
+        tracing::error!(
+            tenant = ?ctx.tenant_id,
+            extension = ?ctx.extension_id,
+            request_id = ?ctx.request_id,
+            "storage capability denied - cap:storage.kv not granted"
+        );
+        return Err(StorageErrorV2::Denied);
+    }
+    ctx.install_id
+        .clone()
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| {
+            tracing::error!(
+                tenant = ?ctx.tenant_id,
+                extension = ?ctx.extension_id,
+                request_id = ?ctx.request_id,
+                "storage capability denied - install_id missing or empty"
             );
-            let mut payload = Map::new();
-            payload.insert("namespace".into(), Value::String(namespace));
-            payload.insert("key".into(), Value::String(key));
+            StorageErrorV2::Denied
+        })
+}
+
+/// Size of a value once base64-encoded for the storage API, which is what the
+/// backend measures against its per-value quota.
+fn encoded_storage_value_len(raw_len: usize) -> usize {
+    raw_len.div_ceil(3) * 4
+}
 
-            let response = storage_request(&install_id, "delete", payload).await?;
-            match response.get("success").and_then(|v| v.as_bool()) {
-                Some(true) | None => Ok(()),
-                Some(false) => Err(StorageError::Conflict),
-            }
-        }
+fn ensure_storage_value_within_limit(
+    raw_len: usize,
+    max_value_bytes: usize,
+) -> Result<(), StorageErrorV2> {
+    if encoded_storage_value_len(raw_len) > max_value_bytes {
+        return Err(StorageErrorV2::TooLarge);
     }
+    Ok(())
+}
 
-    fn list_entries<T>(
-        accessor: &Accessor<T, Self>,
-        namespace: String,
-        cursor: Option<String>,
-    ) -> impl std::future::Future<Output = Result<Vec<StorageEntry>, StorageError>> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
-            let state = access.get();
-            (
-                state.context.providers.clone(),
-                state.context.install_id.clone(),
-                state.context.clone(),
-            )
-        });
+async fn storage_get(
+    target: Option<StorageTarget<'_>>,
+    ctx: &HostExecutionContext,
+    namespace: String,
+    key: String,
+) -> Result<StorageEntry, StorageErrorV2> {
+    let install_id = require_storage_access(ctx)?;
+    let target = require_storage_target(target)?;
+    tracing::info!(
+        tenant=%ctx.tenant_id.as_deref().unwrap_or_default(),
+        extension=%ctx.extension_id.as_deref().unwrap_or_default(),
+        namespace=%namespace,
+        key_redacted=%redact_identifier(&key),
+        "storage capability get start"
+    );
+    let mut payload = Map::new();
+    payload.insert("namespace".into(), Value::String(namespace.clone()));
+    payload.insert("key".into(), Value::String(key.clone()));
 
-        async move {
-            if !has_capability(&providers, CAP_STORAGE_KV) {
-                tracing::error!(
-                    tenant = ?ctx.tenant_id,
-                    extension = ?ctx.extension_id,
-                    request_id = ?ctx.request_id,
-                    "storage capability denied - cap:storage.kv not granted"
-                );
-                return Err(StorageError::Denied);
-            }
-            let install_id = install_id
-                .filter(|id| !id.is_empty())
-                .ok_or_else(|| {
-                    tracing::error!(
-                        tenant = ?ctx.tenant_id,
-                        extension = ?ctx.extension_id,
-                        request_id = ?ctx.request_id,
-                        "storage capability denied - install_id missing or empty"
-                    );
-                    StorageError::Denied
-                })?;
-            let tenant = ctx.tenant_id.unwrap_or_default();
-            let extension = ctx.extension_id.unwrap_or_default();
-            let namespace_log = namespace.clone();
-            let cursor_log = cursor.clone();
-            tracing::info!(
-                tenant=%tenant,
-                extension=%extension,
-                namespace=%namespace_log,
-                cursor=?cursor_log,
-                "storage capability list start"
-            );
-            let mut payload = Map::new();
-            payload.insert("namespace".into(), Value::String(namespace.clone()));
-            payload.insert("includeValues".into(), Value::Bool(true));
-            payload.insert("includeMetadata".into(), Value::Bool(true));
-            if let Some(c) = cursor {
-                payload.insert("cursor".into(), Value::String(c));
-            }
+    let value = storage_request(target, &install_id, "get", payload).await?;
+    parse_storage_entry(value, Some(namespace), Some(key))
+}
 
-            let response = storage_request(&install_id, "list", payload).await?;
-            let items = response
-                .get("items")
-                .and_then(|v| v.as_array())
-                .cloned()
-                .unwrap_or_default();
+async fn storage_put(
+    target: Option<StorageTarget<'_>>,
+    ctx: &HostExecutionContext,
+    entry: StorageEntry,
+    max_value_bytes: usize,
+) -> Result<StorageEntry, StorageErrorV2> {
+    let install_id = require_storage_access(ctx)?;
+    let target = require_storage_target(target)?;
+    let tenant = ctx.tenant_id.as_deref().unwrap_or_default();
+    let extension = ctx.extension_id.as_deref().unwrap_or_default();
+    if let Err(err) = ensure_storage_value_within_limit(entry.value.len(), max_value_bytes) {
+        tracing::warn!(
+            tenant=%tenant,
+            extension=%extension,
+            namespace=%entry.namespace,
+            key_redacted=%redact_identifier(&entry.key),
+            value_bytes = entry.value.len(),
+            encoded_bytes = encoded_storage_value_len(entry.value.len()),
+            max_value_bytes,
+            "storage capability put rejected - value exceeds size limit"
+        );
+        return Err(err);
+    }
+    tracing::info!(
+        tenant=%tenant,
+        extension=%extension,
+        namespace=%entry.namespace,
+        key_redacted=%redact_identifier(&entry.key),
+        "storage capability put start"
+    );
 
-            let mut entries = Vec::with_capacity(items.len());
-            for item in items {
-                let entry = parse_storage_entry(item, None, None)?;
-                entries.push(entry);
-            }
+    let mut payload = Map::new();
+    payload.insert("namespace".into(), Value::String(entry.namespace.clone()));
+    payload.insert("key".into(), Value::String(entry.key.clone()));
+    payload.insert(
+        "value".into(),
+        Value::String(base64::engine::general_purpose::STANDARD.encode(&entry.value)),
+    );
+    let mut metadata = Map::new();
+    metadata.insert("encoding".into(), Value::String("base64-bytes".to_string()));
+    payload.insert("metadata".into(), Value::Object(metadata));
+    if let Some(rev) = entry.revision {
+        payload.insert("ifRevision".into(), Value::Number(rev.into()));
+    }
 
-            if let Some(cursor) = response.get("nextCursor").and_then(|v| v.as_str()) {
-                if !cursor.is_empty() {
-                    tracing::debug!(
-                        namespace,
-                        next_cursor = cursor,
-                        "storage list has more results, cursor ignored by host contract"
-                    );
-                }
-            }
+    let response = storage_request(target, &install_id, "put", payload).await?;
+    let revision = response.get("revision").and_then(|v| v.as_u64());
+    Ok(make_storage_entry(
+        entry.namespace,
+        entry.key,
+        entry.value,
+        revision,
+    ))
+}
+
+async fn storage_delete(
+    target: Option<StorageTarget<'_>>,
+    ctx: &HostExecutionContext,
+    namespace: String,
+    key: String,
+) -> Result<(), StorageErrorV2> {
+    let install_id = require_storage_access(ctx)?;
+    let target = require_storage_target(target)?;
+    tracing::info!(
+        tenant=%ctx.tenant_id.as_deref().unwrap_or_default(),
+        extension=%ctx.extension_id.as_deref().unwrap_or_default(),
+        namespace=%namespace,
+        key_redacted=%redact_identifier(&key),
+        "storage capability delete start"
+    );
+    let mut payload = Map::new();
+    payload.insert("namespace".into(), Value::String(namespace));
+    payload.insert("key".into(), Value::String(key));
+
+    let response = storage_request(target, &install_id, "delete", payload).await?;
+    match response.get("success").and_then(|v| v.as_bool()) {
+        Some(true) | None => Ok(()),
+        Some(false) => Err(StorageErrorV2::Conflict),
+    }
+}
+
+async fn storage_list(
+    target: Option<StorageTarget<'_>>,
+    ctx: &HostExecutionContext,
+    namespace: String,
+    cursor: Option<String>,
+) -> Result<Vec<StorageEntry>, StorageErrorV2> {
+    let install_id = require_storage_access(ctx)?;
+    let target = require_storage_target(target)?;
+    tracing::info!(
+        tenant=%ctx.tenant_id.as_deref().unwrap_or_default(),
+        extension=%ctx.extension_id.as_deref().unwrap_or_default(),
+        namespace=%namespace,
+        cursor=?cursor,
+        "storage capability list start"
+    );
+    let mut payload = Map::new();
+    payload.insert("namespace".into(), Value::String(namespace.clone()));
+    payload.insert("includeValues".into(), Value::Bool(true));
+    payload.insert("includeMetadata".into(), Value::Bool(true));
+    if let Some(c) = cursor {
+        payload.insert("cursor".into(), Value::String(c));
+    }
+
+    let response = storage_request(target, &install_id, "list", payload).await?;
+    let items = response
+        .get("items")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut entries = Vec::with_capacity(items.len());
+    for item in items {
+        let entry = parse_storage_entry(item, None, None)?;
+        entries.push(entry);
+    }
 
-            Ok(entries)
+    if let Some(cursor) = response.get("nextCursor").and_then(|v| v.as_str()) {
+        if !cursor.is_empty() {
+            tracing::debug!(
+                namespace,
+                next_cursor = cursor,
+                "storage list has more results, cursor ignored by host contract"
+            );
         }
     }
+
+    Ok(entries)
 }
 
 impl ui_proxy::HostWithStore for HasSelf<HostState> {
@@ -936,216 +1826,223 @@ impl ui_proxy::HostWithStore for HasSelf<HostState> {
                 state.runtime.clone(),
             )
         });
-        async move {
-            if !has_capability(&providers, CAP_UI_PROXY) {
-                tracing::error!(
-                    tenant = ?ctx.tenant_id,
-                    extension = ?ctx.extension_id,
-                    request_id = ?ctx.request_id,
-                    "ui_proxy capability denied - cap:ui.proxy not granted"
-                );
-                return Err(ProxyError::Denied);
-            }
-            let Some(base_url) = runtime.ui_proxy_base.clone() else {
-                tracing::warn!(
-                    tenant=?ctx.tenant_id,
-                    extension=?ctx.extension_id,
-                    "ui_proxy capability invoked but UI_PROXY_BASE_URL is not configured"
-                );
-                return Err(ProxyError::RouteNotFound);
-            };
+        async move { ui_proxy_call(&runtime, ctx, &providers, route, payload).await }
+    }
+}
 
-            let tenant = ctx.tenant_id.clone().ok_or_else(|| {
-                tracing::error!("ui_proxy call missing tenant id in host context");
-                ProxyError::Internal
-            })?;
+/// `ui_proxy.call-route` body shared by the host binding and tests.
+async fn ui_proxy_call(
+    runtime: &HostRuntimeConfig,
+    ctx: HostExecutionContext,
+    providers: &HashSet<String>,
+    route: String,
+    payload: Option<Vec<u8>>,
+) -> Result<Vec<u8>, ProxyError> {
+    if !has_capability(providers, CAP_UI_PROXY) {
+        tracing::error!(
+            tenant = ?ctx.tenant_id,
+            extension = ?ctx.extension_id,
+            request_id = ?ctx.request_id,
+            "ui_proxy capability denied - cap:ui.proxy not granted"
+        );
+        return Err(ProxyError::Denied);
+    }
+    let Some(base_url) = runtime.ui_proxy_base.clone() else {
+        tracing::warn!(
+            tenant=?ctx.tenant_id,
+            extension=?ctx.extension_id,
+            "ui_proxy capability invoked but UI_PROXY_BASE_URL is not configured"
+        );
+        return Err(ProxyError::RouteNotFound);
+    };
 
-            let extension = ctx.extension_id.clone().ok_or_else(|| {
-                tracing::error!("ui_proxy call missing extension id in host context");
-                ProxyError::Internal
-            })?;
+    let tenant = ctx.tenant_id.clone().ok_or_else(|| {
+        tracing::error!("ui_proxy call missing tenant id in host context");
+        ProxyError::Internal
+    })?;
+
+    let extension = ctx.extension_id.clone().ok_or_else(|| {
+        tracing::error!("ui_proxy call missing extension id in host context");
+        ProxyError::Internal
+    })?;
+
+    let request_id = ctx.request_id.clone().unwrap_or_else(request_id::generate);
 
-            let request_id = ctx
-                .request_id
-                .clone()
-                .unwrap_or_else(|| "ui-proxy-call".to_string());
+    let trimmed_route = route.trim();
+    if trimmed_route.is_empty() {
+        tracing::warn!(
+            tenant=%tenant,
+            extension=%extension,
+            "ui_proxy route was empty"
+        );
+        return Err(ProxyError::BadRequest);
+    }
+
+    let (path_part, query_part) = match trimmed_route.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (trimmed_route, None),
+    };
 
-            let trimmed_route = route.trim();
-            if trimmed_route.is_empty() {
+    // Build URL based on route type:
+    // - Routes starting with /api/ go directly to platform APIs (no extension prefix)
+    // - Other routes are prefixed with extension ID (backward compatible for self-proxying)
+    // Authentication is via api_key from secret envelope
+    let is_platform_api = path_part.starts_with("/api/") || path_part.starts_with("api/");
+    let mut url = base_url.clone();
+    {
+        let mut segments = url.path_segments_mut().map_err(|_| ProxyError::Internal)?;
+        segments.pop_if_empty();
+        // Only prefix with extension ID for non-platform-api routes
+        if !is_platform_api {
+            segments.push(&extension);
+        }
+        for segment in path_part.trim_start_matches('/').split('/') {
+            if segment.is_empty() {
+                continue;
+            }
+            if matches!(segment, "." | "..") {
                 tracing::warn!(
                     tenant=%tenant,
                     extension=%extension,
-                    "ui_proxy route was empty"
+                    segment=%segment,
+                    "ui_proxy route contains invalid segment"
                 );
                 return Err(ProxyError::BadRequest);
             }
+            segments.push(segment);
+        }
+    }
+    if let Some(query) = query_part {
+        if query.is_empty() {
+            url.set_query(None);
+        } else {
+            url.set_query(Some(query));
+        }
+    } else {
+        url.set_query(None);
+    }
 
-            let (path_part, query_part) = match trimmed_route.split_once('?') {
-                Some((path, query)) => (path, Some(query)),
-                None => (trimmed_route, None),
-            };
-
-            // Build URL based on route type:
-            // - Routes starting with /api/ go directly to platform APIs (no extension prefix)
-            // - Other routes are prefixed with extension ID (backward compatible for self-proxying)
-            // Authentication is via api_key from secret envelope
-            let is_platform_api = path_part.starts_with("/api/") || path_part.starts_with("api/");
-            let mut url = base_url.clone();
-            {
-                let mut segments = url.path_segments_mut().map_err(|_| ProxyError::Internal)?;
-                segments.pop_if_empty();
-                // Only prefix with extension ID for non-platform-api routes
-                if !is_platform_api {
-                    segments.push(&extension);
-                }
-                for segment in path_part.trim_start_matches('/').split('/') {
-                    if segment.is_empty() {
-                        continue;
-                    }
-                    if matches!(segment, "." | "..") {
-                        tracing::warn!(
-                            tenant=%tenant,
-                            extension=%extension,
-                            segment=%segment,
-                            "ui_proxy route contains invalid segment"
-                        );
-                        return Err(ProxyError::BadRequest);
-                    }
-                    segments.push(segment);
-                }
-            }
-            if let Some(query) = query_part {
-                if query.is_empty() {
-                    url.set_query(None);
-                } else {
-                    url.set_query(Some(query));
-                }
-            } else {
-                url.set_query(None);
-            }
+    let client: &Client = &HTTP_CLIENT;
+    // Use GET when no payload, POST when payload is present
+    let mut request = if payload.is_some() {
+        client.post(url.clone())
+    } else {
+        client.get(url.clone())
+    }
+    .timeout(runtime.ui_proxy_timeout);
+    request = request
+        .header(request_id::REQUEST_ID_HEADER, &request_id)
+        .header("x-alga-tenant", &tenant)
+        .header("x-alga-extension", &extension);
+    if let Some(install) = ctx.install_id.clone() {
+        request = request.header("x-ext-install-id", install);
+    }
+    if let Some(version) = ctx.version_id.clone() {
+        request = request.header("x-ext-version-id", version);
+    }
+    // Get API key from extension's secret envelope for authentication
+    if let Some(ref secrets) = ctx.secrets {
+        if let Some(api_key) = secrets.values.get("api_key") {
+            request = request.header("x-api-key", api_key);
+        }
+    }
 
-            let client: &Client = &HTTP_CLIENT;
-            // Use GET when no payload, POST when payload is present
-            let mut request = if payload.is_some() {
-                client.post(url.clone())
-            } else {
-                client.get(url.clone())
-            }.timeout(runtime.ui_proxy_timeout);
-            request = request
-                .header("x-request-id", &request_id)
-                .header("x-alga-tenant", &tenant)
-                .header("x-alga-extension", &extension);
-            if let Some(install) = ctx.install_id.clone() {
-                request = request.header("x-ext-install-id", install);
-            }
-            if let Some(version) = ctx.version_id.clone() {
-                request = request.header("x-ext-version-id", version);
-            }
-            // Get API key from extension's secret envelope for authentication
-            if let Some(ref secrets) = ctx.secrets {
-                if let Some(api_key) = secrets.values.get("api_key") {
-                    request = request.header("x-api-key", api_key);
-                }
-            }
+    // Forward user info for activity logging
+    if let Some(ref user) = ctx.user {
+        request = request.header("x-user-id", &user.user_id);
+        request = request.header("x-user-email", &user.user_email);
+    }
 
-            // Forward user info for activity logging
-            if let Some(ref user) = ctx.user {
-                request = request.header("x-user-id", &user.user_id);
-                request = request.header("x-user-email", &user.user_email);
-            }
+    let has_body = payload.is_some();
+    if let Some(body) = payload {
+        request = request
+            .header("content-type", "application/json")
+            .body(body);
+    }
 
-            let has_body = payload.is_some();
-            if let Some(body) = payload {
-                request = request
-                    .header("content-type", "application/json")
-                    .body(body);
-            }
+    let started = Instant::now();
+    tracing::info!(
+        tenant=%tenant,
+        extension=%extension,
+        route=%path_part,
+        url=%url,
+        has_body,
+        is_platform_api,
+        "ui proxy dispatch start"
+    );
 
-            let started = Instant::now();
-            tracing::info!(
+    let response = match request.send().await {
+        Ok(resp) => resp,
+        Err(err) => {
+            tracing::error!(
                 tenant=%tenant,
                 extension=%extension,
                 route=%path_part,
-                url=%url,
-                has_body,
-                is_platform_api,
-                "ui proxy dispatch start"
+                error=%err,
+                "ui proxy request failed during transport"
             );
+            return Err(ProxyError::Internal);
+        }
+    };
 
-            let response = match request.send().await {
-                Ok(resp) => resp,
-                Err(err) => {
-                    tracing::error!(
-                        tenant=%tenant,
-                        extension=%extension,
-                        route=%path_part,
-                        error=%err,
-                        "ui proxy request failed during transport"
-                    );
-                    return Err(ProxyError::Internal);
-                }
-            };
-
-            let status = response.status();
-            let duration_ms = started.elapsed().as_millis();
-
-            if !status.is_success() {
-                tracing::warn!(
-                    tenant=%tenant,
-                    extension=%extension,
-                    route=%path_part,
-                    status=status.as_u16(),
-                    duration_ms,
-                    "ui proxy backend returned non-success status"
-                );
-                return Err(map_proxy_status(status));
-            }
+    let status = response.status();
+    let duration_ms = started.elapsed().as_millis();
 
-            let bytes = response.bytes().await.map_err(|err| {
-                tracing::error!(
-                    tenant=%tenant,
-                    extension=%extension,
-                    route=%path_part,
-                    error=%err,
-                    "failed to read ui proxy response body"
-                );
-                ProxyError::Internal
-            })?;
+    if !status.is_success() {
+        tracing::warn!(
+            tenant=%tenant,
+            extension=%extension,
+            route=%path_part,
+            status=status.as_u16(),
+            duration_ms,
+            "ui proxy backend returned non-success status"
+        );
+        return Err(map_proxy_status(status));
+    }
 
-            tracing::info!(
-                tenant=%tenant,
-                extension=%extension,
-                route=%path_part,
-                duration_ms,
-                response_bytes = bytes.len(),
-                "ui proxy dispatch completed"
-            );
+    let bytes = response.bytes().await.map_err(|err| {
+        tracing::error!(
+            tenant=%tenant,
+            extension=%extension,
+            route=%path_part,
+            error=%err,
+            "failed to read ui proxy response body"
+        );
+        ProxyError::Internal
+    })?;
+
+    tracing::info!(
+        tenant=%tenant,
+        extension=%extension,
+        route=%path_part,
+        duration_ms,
+        response_bytes = bytes.len(),
+        "ui proxy dispatch completed"
+    );
 
-            Ok(bytes.to_vec())
-        }
-    }
+    Ok(bytes.to_vec())
 }
 
 fn parse_storage_entry(
     value: Value,
     default_namespace: Option<String>,
     default_key: Option<String>,
-) -> std::result::Result<StorageEntry, StorageError> {
-    let obj = value.as_object().cloned().ok_or(StorageError::Internal)?;
+) -> std::result::Result<StorageEntry, StorageErrorV2> {
+    let obj = value.as_object().cloned().ok_or(StorageErrorV2::Internal)?;
 
     let namespace = obj
         .get("namespace")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string())
         .or(default_namespace)
-        .ok_or(StorageError::Internal)?;
+        .ok_or(StorageErrorV2::Internal)?;
 
     let key = obj
         .get("key")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string())
         .or(default_key)
-        .ok_or(StorageError::Internal)?;
+        .ok_or(StorageErrorV2::Internal)?;
 
     let revision = obj.get("revision").and_then(|v| v.as_u64());
 
@@ -1153,7 +2050,7 @@ fn parse_storage_entry(
 
     let value_field = obj.get("value").cloned().unwrap_or(Value::Null);
     let bytes = decode_storage_value(&value_field, metadata.as_ref())
-        .map_err(|_| StorageError::Internal)?;
+        .map_err(|_| StorageErrorV2::Internal)?;
 
     Ok(make_storage_entry(namespace, key, bytes, revision))
 }
@@ -1195,20 +2092,16 @@ fn decode_storage_value(
 }
 
 async fn storage_request(
+    target: StorageTarget<'_>,
     install_id: &str,
     operation: &str,
     mut payload: Map<String, Value>,
-) -> std::result::Result<Value, StorageError> {
-    let base = STORAGE_BASE_URL.as_ref().ok_or(StorageError::Internal)?;
-    let token = RUNNER_STORAGE_API_TOKEN
-        .as_ref()
-        .ok_or(StorageError::Internal)?;
-
+) -> std::result::Result<Value, StorageErrorV2> {
     payload.insert("operation".into(), Value::String(operation.to_string()));
 
     let url = format!(
         "{}/api/internal/ext-storage/install/{}",
-        base.trim_end_matches('/'),
+        target.base.trim_end_matches('/'),
         install_id
     );
     tracing::info!(url = %url, operation = %operation, "storage request dispatch");
@@ -1233,13 +2126,13 @@ async fn storage_request(
     let response = HTTP_CLIENT
         .post(url.as_str())
         .header("content-type", "application/json")
-        .header("x-runner-auth", token)
+        .header("x-runner-auth", target.token)
         .json(&payload)
         .send()
         .await
         .map_err(|err| {
             tracing::error!(error = %err, "storage_request transport failure");
-            StorageError::Internal
+            StorageErrorV2::Internal
         })?;
 
     let status = response.status();
@@ -1248,12 +2141,12 @@ async fn storage_request(
     if !status.is_success() {
         tracing::warn!(
             status = status.as_u16(),
-            body = %text,
+            body = %redact::body_sample(&text),
             operation,
             install_id = %install_id,
             "storage_request error"
         );
-        return Err(map_storage_status(status));
+        return Err(map_storage_status(status, &text));
     }
 
     if text.trim().is_empty() {
@@ -1261,18 +2154,28 @@ async fn storage_request(
     } else {
         serde_json::from_str(&text).map_err(|err| {
             tracing::error!(error = %err, "storage_request invalid JSON response");
-            StorageError::Internal
+            StorageErrorV2::Internal
         })
     }
 }
 
-fn map_storage_status(status: StatusCode) -> StorageError {
+/// Maps a storage API failure to a guest-facing error. Per-value limits
+/// (`LIMIT_EXCEEDED`) and namespace/total quotas (`QUOTA_EXCEEDED`) surface as
+/// `too-large`; other rate limiting stays `denied`.
+fn map_storage_status(status: StatusCode, body: &str) -> StorageErrorV2 {
+    let code = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v.get("code").and_then(|c| c.as_str()).map(str::to_string));
+    if matches!(code.as_deref(), Some("LIMIT_EXCEEDED" | "QUOTA_EXCEEDED")) {
+        return StorageErrorV2::TooLarge;
+    }
     match status {
-        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => StorageError::Denied,
-        StatusCode::NOT_FOUND => StorageError::Missing,
-        StatusCode::CONFLICT => StorageError::Conflict,
-        StatusCode::TOO_MANY_REQUESTS => StorageError::Denied,
-        _ => StorageError::Internal,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => StorageErrorV2::Denied,
+        StatusCode::NOT_FOUND => StorageErrorV2::Missing,
+        StatusCode::CONFLICT => StorageErrorV2::Conflict,
+        StatusCode::PAYLOAD_TOO_LARGE => StorageErrorV2::TooLarge,
+        StatusCode::TOO_MANY_REQUESTS => StorageErrorV2::Denied,
+        _ => StorageErrorV2::Internal,
     }
 }
 
@@ -1288,14 +2191,13 @@ fn map_proxy_status(status: StatusCode) -> ProxyError {
 }
 
 async fn scheduler_request(
+    api: &InternalApiConfig,
     install_id: &str,
     operation: &str,
     mut payload: Map<String, Value>,
 ) -> std::result::Result<Value, SchedulerError> {
-    let base = SCHEDULER_BASE_URL.as_ref().ok_or(SchedulerError::Internal)?;
-    let token = RUNNER_STORAGE_API_TOKEN
-        .as_ref()
-        .ok_or(SchedulerError::Internal)?;
+    let base = api.base_url.as_ref().ok_or(SchedulerError::Internal)?;
+    let token = api.token.as_ref().ok_or(SchedulerError::Internal)?;
 
     payload.insert("operation".into(), Value::String(operation.to_string()));
 
@@ -1327,7 +2229,7 @@ async fn scheduler_request(
     let text = response.text().await.unwrap_or_default();
 
     if !status.is_success() {
-        tracing::warn!(status = status.as_u16(), body = %text, operation, "scheduler_request error");
+        tracing::warn!(status = status.as_u16(), body = %redact::body_sample(&text), operation, "scheduler_request error");
         return Err(map_scheduler_status(status));
     }
 
@@ -1352,14 +2254,17 @@ fn map_scheduler_status(status: StatusCode) -> SchedulerError {
 }
 
 async fn invoicing_request(
+    api: &InternalApiConfig,
     install_id: &str,
     operation: &str,
     mut payload: Map<String, Value>,
 ) -> std::result::Result<Value, String> {
-    let base = INVOICING_BASE_URL
+    let base = api
+        .base_url
         .as_ref()
         .ok_or_else(|| "invoicing base URL not configured".to_string())?;
-    let token = RUNNER_STORAGE_API_TOKEN
+    let token = api
+        .token
         .as_ref()
         .ok_or_else(|| "runner auth token not configured".to_string())?;
 
@@ -1393,7 +2298,7 @@ async fn invoicing_request(
     let text = response.text().await.unwrap_or_default();
 
     if !status.is_success() {
-        tracing::warn!(status = status.as_u16(), body = %text, operation, "invoicing_request error");
+        tracing::warn!(status = status.as_u16(), body = %redact::body_sample(&text), operation, "invoicing_request error");
         return Err(format!("http {}: {}", status.as_u16(), text));
     }
 
@@ -1408,14 +2313,13 @@ async fn invoicing_request(
 }
 
 async fn clients_request(
+    api: &InternalApiConfig,
     install_id: &str,
     operation: &str,
     mut payload: Map<String, Value>,
 ) -> std::result::Result<Value, ClientReadError> {
-    let base = CLIENTS_BASE_URL.as_ref().ok_or(ClientReadError::Internal)?;
-    let token = RUNNER_STORAGE_API_TOKEN
-        .as_ref()
-        .ok_or(ClientReadError::Internal)?;
+    let base = api.base_url.as_ref().ok_or(ClientReadError::Internal)?;
+    let token = api.token.as_ref().ok_or(ClientReadError::Internal)?;
 
     payload.insert("operation".into(), Value::String(operation.to_string()));
 
@@ -1441,7 +2345,7 @@ async fn clients_request(
     let text = response.text().await.unwrap_or_default();
 
     if !status.is_success() {
-        tracing::warn!(status = status.as_u16(), body = %text, operation, "clients_request error");
+        tracing::warn!(status = status.as_u16(), body = %redact::body_sample(&text), operation, "clients_request error");
         return Err(map_client_read_status(status));
     }
 
@@ -1464,16 +2368,13 @@ fn map_client_read_status(status: StatusCode) -> ClientReadError {
 }
 
 async fn services_request(
+    api: &InternalApiConfig,
     install_id: &str,
     operation: &str,
     mut payload: Map<String, Value>,
 ) -> std::result::Result<Value, ServiceReadError> {
-    let base = SERVICES_BASE_URL
-        .as_ref()
-        .ok_or(ServiceReadError::Internal)?;
-    let token = RUNNER_STORAGE_API_TOKEN
-        .as_ref()
-        .ok_or(ServiceReadError::Internal)?;
+    let base = api.base_url.as_ref().ok_or(ServiceReadError::Internal)?;
+    let token = api.token.as_ref().ok_or(ServiceReadError::Internal)?;
 
     payload.insert("operation".into(), Value::String(operation.to_string()));
 
@@ -1499,7 +2400,7 @@ async fn services_request(
     let text = response.text().await.unwrap_or_default();
 
     if !status.is_success() {
-        tracing::warn!(status = status.as_u16(), body = %text, operation, "services_request error");
+        tracing::warn!(status = status.as_u16(), body = %redact::body_sample(&text), operation, "services_request error");
         return Err(map_service_read_status(status));
     }
 
@@ -1525,7 +2426,10 @@ fn build_runner_user_payload(ctx: &HostExecutionContext) -> Option<Value> {
     ctx.user.as_ref().map(|user| {
         Value::Object(Map::from_iter([
             ("userId".to_string(), Value::String(user.user_id.clone())),
-            ("userType".to_string(), Value::String(user.user_type.clone())),
+            (
+                "userType".to_string(),
+                Value::String(user.user_type.clone()),
+            ),
         ]))
     })
 }
@@ -1607,7 +2511,10 @@ fn parse_create_manual_invoice_result(value: &Value) -> CreateManualInvoiceResul
         });
 
     CreateManualInvoiceResult {
-        success: obj.get("success").and_then(|v| v.as_bool()).unwrap_or(false),
+        success: obj
+            .get("success")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
         invoice_id: get_str(&["invoiceId", "invoice_id"]),
         invoice_number: get_str(&["invoiceNumber", "invoice_number"]),
         status: get_str(&["status"]),
@@ -1623,9 +2530,20 @@ fn parse_schedule_info(value: &Value) -> Option<ScheduleInfo> {
     let obj = value.as_object()?;
     Some(ScheduleInfo {
         id: obj.get("id")?.as_str()?.to_string(),
-        endpoint_path: obj.get("endpointPath").or(obj.get("endpoint_path"))?.as_str()?.to_string(),
-        endpoint_method: obj.get("endpointMethod").or(obj.get("endpoint_method"))?.as_str()?.to_string(),
-        name: obj.get("name").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        endpoint_path: obj
+            .get("endpointPath")
+            .or(obj.get("endpoint_path"))?
+            .as_str()?
+            .to_string(),
+        endpoint_method: obj
+            .get("endpointMethod")
+            .or(obj.get("endpoint_method"))?
+            .as_str()?
+            .to_string(),
+        name: obj
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
         cron: obj.get("cron")?.as_str()?.to_string(),
         timezone: obj.get("timezone")?.as_str()?.to_string(),
         enabled: obj.get("enabled")?.as_bool()?,
@@ -1638,10 +2556,26 @@ fn parse_schedule_info(value: &Value) -> Option<ScheduleInfo> {
                 serde_json::to_string(v).ok()
             }
         }),
-        last_run_at: obj.get("lastRunAt").or(obj.get("last_run_at")).and_then(|v| v.as_str()).map(|s| s.to_string()),
-        last_run_status: obj.get("lastRunStatus").or(obj.get("last_run_status")).and_then(|v| v.as_str()).map(|s| s.to_string()),
-        last_error: obj.get("lastError").or(obj.get("last_error")).and_then(|v| v.as_str()).map(|s| s.to_string()),
-        created_at: obj.get("createdAt").or(obj.get("created_at")).and_then(|v| v.as_str()).map(|s| s.to_string()),
+        last_run_at: obj
+            .get("lastRunAt")
+            .or(obj.get("last_run_at"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        last_run_status: obj
+            .get("lastRunStatus")
+            .or(obj.get("last_run_status"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        last_error: obj
+            .get("lastError")
+            .or(obj.get("last_error"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        created_at: obj
+            .get("createdAt")
+            .or(obj.get("created_at"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
     })
 }
 
@@ -1659,8 +2593,16 @@ fn parse_endpoint_info(value: &Value) -> Option<EndpointInfo> {
 fn parse_client_summary(value: &Value) -> Option<ClientSummary> {
     let obj = value.as_object()?;
     Some(ClientSummary {
-        client_id: obj.get("clientId").or(obj.get("client_id"))?.as_str()?.to_string(),
-        client_name: obj.get("clientName").or(obj.get("client_name"))?.as_str()?.to_string(),
+        client_id: obj
+            .get("clientId")
+            .or(obj.get("client_id"))?
+            .as_str()?
+            .to_string(),
+        client_name: obj
+            .get("clientName")
+            .or(obj.get("client_name"))?
+            .as_str()?
+            .to_string(),
         client_type: obj
             .get("clientType")
             .or(obj.get("client_type"))
@@ -1778,7 +2720,9 @@ fn parse_service_summary(value: &Value) -> Option<ServiceSummary> {
             .or(obj.get("is_active"))
             .and_then(|v| v.as_bool())
             .unwrap_or(true),
-        sku: obj.get("sku").and_then(|v| v.as_str().map(|s| s.to_string())),
+        sku: obj
+            .get("sku")
+            .and_then(|v| v.as_str().map(|s| s.to_string())),
     })
 }
 
@@ -1817,12 +2761,13 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
     fn list_schedules<T>(
         accessor: &Accessor<T, Self>,
     ) -> impl std::future::Future<Output = Result<Vec<ScheduleInfo>, SchedulerError>> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -1855,7 +2800,7 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
             );
 
             let payload = Map::new();
-            let response = scheduler_request(&install_id, "list", payload).await?;
+            let response = scheduler_request(&api, &install_id, "list", payload).await?;
 
             let schedules_arr = response
                 .get("schedules")
@@ -1884,12 +2829,13 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
         schedule_id: String,
     ) -> impl std::future::Future<Output = Result<Option<ScheduleInfo>, SchedulerError>> + Send
     {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -1925,7 +2871,7 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
             let mut payload = Map::new();
             payload.insert("scheduleId".into(), Value::String(schedule_id.clone()));
 
-            let response = scheduler_request(&install_id, "get", payload).await?;
+            let response = scheduler_request(&api, &install_id, "get", payload).await?;
             let schedule = response.get("schedule").and_then(parse_schedule_info);
 
             tracing::info!(
@@ -1944,12 +2890,13 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
         accessor: &Accessor<T, Self>,
         input: CreateScheduleInput,
     ) -> impl std::future::Future<Output = CreateScheduleResult> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2025,13 +2972,16 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
                             success: false,
                             schedule_id: None,
                             error: Some("Payload must be valid JSON object or array".to_string()),
-                            field_errors: Some("{\"payload\":\"Payload must be valid JSON object or array\"}".to_string()),
+                            field_errors: Some(
+                                "{\"payload\":\"Payload must be valid JSON object or array\"}"
+                                    .to_string(),
+                            ),
                         };
                     }
                 }
             }
 
-            let response = match scheduler_request(&install_id, "create", payload).await {
+            let response = match scheduler_request(&api, &install_id, "create", payload).await {
                 Ok(r) => r,
                 Err(e) => {
                     let error_msg = format!("{:?}", e);
@@ -2050,9 +3000,19 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
                 }
             };
 
-            let success = response.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
-            let schedule_id = response.get("scheduleId").or(response.get("schedule_id")).and_then(|v| v.as_str()).map(|s| s.to_string());
-            let error = response.get("error").and_then(|v| v.as_str()).map(|s| s.to_string());
+            let success = response
+                .get("success")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let schedule_id = response
+                .get("scheduleId")
+                .or(response.get("schedule_id"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            let error = response
+                .get("error")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
             let field_errors = response
                 .get("fieldErrors")
                 .or(response.get("field_errors"))
@@ -2080,12 +3040,13 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
         schedule_id: String,
         input: UpdateScheduleInput,
     ) -> impl std::future::Future<Output = UpdateScheduleResult> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2162,13 +3123,16 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
                         return UpdateScheduleResult {
                             success: false,
                             error: Some("Payload must be valid JSON object or array".to_string()),
-                            field_errors: Some("{\"payload\":\"Payload must be valid JSON object or array\"}".to_string()),
+                            field_errors: Some(
+                                "{\"payload\":\"Payload must be valid JSON object or array\"}"
+                                    .to_string(),
+                            ),
                         };
                     }
                 }
             }
 
-            let response = match scheduler_request(&install_id, "update", payload).await {
+            let response = match scheduler_request(&api, &install_id, "update", payload).await {
                 Ok(r) => r,
                 Err(e) => {
                     let error_msg = format!("{:?}", e);
@@ -2187,8 +3151,14 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
                 }
             };
 
-            let success = response.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
-            let error = response.get("error").and_then(|v| v.as_str()).map(|s| s.to_string());
+            let success = response
+                .get("success")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let error = response
+                .get("error")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
             let field_errors = response
                 .get("fieldErrors")
                 .or(response.get("field_errors"))
@@ -2214,12 +3184,13 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
         accessor: &Accessor<T, Self>,
         schedule_id: String,
     ) -> impl std::future::Future<Output = DeleteScheduleResult> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2264,7 +3235,7 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
             let mut payload = Map::new();
             payload.insert("scheduleId".into(), Value::String(schedule_id.clone()));
 
-            let response = match scheduler_request(&install_id, "delete", payload).await {
+            let response = match scheduler_request(&api, &install_id, "delete", payload).await {
                 Ok(r) => r,
                 Err(e) => {
                     let error_msg = format!("{:?}", e);
@@ -2282,8 +3253,14 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
                 }
             };
 
-            let success = response.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
-            let error = response.get("error").and_then(|v| v.as_str()).map(|s| s.to_string());
+            let success = response
+                .get("success")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let error = response
+                .get("error")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
 
             tracing::info!(
                 tenant=%tenant,
@@ -2300,12 +3277,13 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
     fn get_endpoints<T>(
         accessor: &Accessor<T, Self>,
     ) -> impl std::future::Future<Output = Result<Vec<EndpointInfo>, SchedulerError>> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2338,7 +3316,7 @@ impl scheduler::HostWithStore for HasSelf<HostState> {
             );
 
             let payload = Map::new();
-            let response = scheduler_request(&install_id, "getEndpoints", payload).await?;
+            let response = scheduler_request(&api, &install_id, "getEndpoints", payload).await?;
 
             let endpoints_arr = response
                 .get("endpoints")
@@ -2368,12 +3346,13 @@ impl invoicing::HostWithStore for HasSelf<HostState> {
         accessor: &Accessor<T, Self>,
         input: CreateManualInvoiceInput,
     ) -> impl std::future::Future<Output = CreateManualInvoiceResult> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2413,7 +3392,10 @@ impl invoicing::HostWithStore for HasSelf<HostState> {
             );
 
             let mut payload = Map::new();
-            payload.insert("clientId".into(), Value::String(input.client_id.to_string()));
+            payload.insert(
+                "clientId".into(),
+                Value::String(input.client_id.to_string()),
+            );
 
             let items: Vec<Value> = input
                 .items
@@ -2423,10 +3405,28 @@ impl invoicing::HostWithStore for HasSelf<HostState> {
                     // Older server-side validators treat `null` as "present", which can turn a normal
                     // line item into a validation error. Using explicit inserts avoids emitting nulls.
                     let mut m = Map::new();
-                    m.insert("serviceId".into(), Value::String(item.service_id.to_string()));
-                    m.insert("quantity".into(), Value::Number(serde_json::Number::from_f64(item.quantity).unwrap_or_else(|| serde_json::Number::from(0))));
-                    m.insert("description".into(), Value::String(item.description.to_string()));
-                    m.insert("rate".into(), Value::Number(serde_json::Number::from_f64(item.rate).unwrap_or_else(|| serde_json::Number::from(0))));
+                    m.insert(
+                        "serviceId".into(),
+                        Value::String(item.service_id.to_string()),
+                    );
+                    m.insert(
+                        "quantity".into(),
+                        Value::Number(
+                            serde_json::Number::from_f64(item.quantity)
+                                .unwrap_or_else(|| serde_json::Number::from(0)),
+                        ),
+                    );
+                    m.insert(
+                        "description".into(),
+                        Value::String(item.description.to_string()),
+                    );
+                    m.insert(
+                        "rate".into(),
+                        Value::Number(
+                            serde_json::Number::from_f64(item.rate)
+                                .unwrap_or_else(|| serde_json::Number::from(0)),
+                        ),
+                    );
 
                     if let Some(is_discount) = item.is_discount {
                         m.insert("isDiscount".into(), Value::Bool(is_discount));
@@ -2439,10 +3439,16 @@ impl invoicing::HostWithStore for HasSelf<HostState> {
                         m.insert("discountType".into(), Value::String(v.to_string()));
                     }
                     if let Some(applies_to_item_id) = item.applies_to_item_id.as_ref() {
-                        m.insert("appliesToItemId".into(), Value::String(applies_to_item_id.to_string()));
+                        m.insert(
+                            "appliesToItemId".into(),
+                            Value::String(applies_to_item_id.to_string()),
+                        );
                     }
                     if let Some(applies_to_service_id) = item.applies_to_service_id.as_ref() {
-                        m.insert("appliesToServiceId".into(), Value::String(applies_to_service_id.to_string()));
+                        m.insert(
+                            "appliesToServiceId".into(),
+                            Value::String(applies_to_service_id.to_string()),
+                        );
                     }
 
                     Value::Object(m)
@@ -2451,7 +3457,10 @@ impl invoicing::HostWithStore for HasSelf<HostState> {
             payload.insert("items".into(), Value::Array(items));
 
             if let Some(invoice_date) = input.invoice_date {
-                payload.insert("invoiceDate".into(), Value::String(invoice_date.to_string()));
+                payload.insert(
+                    "invoiceDate".into(),
+                    Value::String(invoice_date.to_string()),
+                );
             }
             if let Some(due_date) = input.due_date {
                 payload.insert("dueDate".into(), Value::String(due_date.to_string()));
@@ -2460,28 +3469,29 @@ impl invoicing::HostWithStore for HasSelf<HostState> {
                 payload.insert("poNumber".into(), Value::String(po_number.to_string()));
             }
 
-            let response = match invoicing_request(&install_id, "createManualInvoice", payload).await {
-                Ok(v) => v,
-                Err(err) => {
-                    tracing::error!(
-                        tenant=%tenant,
-                        extension=%extension,
-                        error=%err,
-                        "invoicing capability create_manual_invoice request failed"
-                    );
-                    return CreateManualInvoiceResult {
-                        success: false,
-                        invoice_id: None,
-                        invoice_number: None,
-                        status: None,
-                        subtotal: None,
-                        tax: None,
-                        total: None,
-                        error: Some(err),
-                        field_errors: None,
-                    };
-                }
-            };
+            let response =
+                match invoicing_request(&api, &install_id, "createManualInvoice", payload).await {
+                    Ok(v) => v,
+                    Err(err) => {
+                        tracing::error!(
+                            tenant=%tenant,
+                            extension=%extension,
+                            error=%err,
+                            "invoicing capability create_manual_invoice request failed"
+                        );
+                        return CreateManualInvoiceResult {
+                            success: false,
+                            invoice_id: None,
+                            invoice_number: None,
+                            status: None,
+                            subtotal: None,
+                            tax: None,
+                            total: None,
+                            error: Some(err),
+                            field_errors: None,
+                        };
+                    }
+                };
 
             let result = parse_create_manual_invoice_result(&response);
 
@@ -2502,12 +3512,13 @@ impl clients::HostWithStore for HasSelf<HostState> {
         accessor: &Accessor<T, Self>,
         input: ClientsListInput,
     ) -> impl std::future::Future<Output = Result<ClientsListResult, ClientReadError>> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2532,7 +3543,10 @@ impl clients::HostWithStore for HasSelf<HostState> {
             if let Some(include_inactive) = input.include_inactive {
                 filter.insert("includeInactive".to_string(), Value::Bool(include_inactive));
             }
-            filter.insert("page".to_string(), Value::Number(serde_json::Number::from(page)));
+            filter.insert(
+                "page".to_string(),
+                Value::Number(serde_json::Number::from(page)),
+            );
             filter.insert(
                 "pageSize".to_string(),
                 Value::Number(serde_json::Number::from(page_size)),
@@ -2550,7 +3564,7 @@ impl clients::HostWithStore for HasSelf<HostState> {
                 "clients capability list_clients start"
             );
 
-            let response = clients_request(&install_id, "list", payload).await?;
+            let response = clients_request(&api, &install_id, "list", payload).await?;
             let parsed = parse_clients_list_result(&response).ok_or(ClientReadError::Internal)?;
 
             tracing::info!(
@@ -2569,12 +3583,13 @@ impl clients::HostWithStore for HasSelf<HostState> {
         client_id: String,
     ) -> impl std::future::Future<Output = Result<Option<ClientSummary>, ClientReadError>> + Send
     {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2604,7 +3619,7 @@ impl clients::HostWithStore for HasSelf<HostState> {
                 payload.insert("user".into(), user);
             }
 
-            let response = clients_request(&install_id, "get", payload).await?;
+            let response = clients_request(&api, &install_id, "get", payload).await?;
             let item = match response.get("item") {
                 Some(value) if value.is_null() => None,
                 Some(value) => Some(parse_client_summary(value).ok_or(ClientReadError::Internal)?),
@@ -2628,13 +3643,15 @@ impl services::HostWithStore for HasSelf<HostState> {
     fn list_services<T>(
         accessor: &Accessor<T, Self>,
         input: ServicesListInput,
-    ) -> impl std::future::Future<Output = Result<ServicesListResult, ServiceReadError>> + Send {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+    ) -> impl std::future::Future<Output = Result<ServicesListResult, ServiceReadError>> + Send
+    {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2672,9 +3689,15 @@ impl services::HostWithStore for HasSelf<HostState> {
                     ServiceBillingMethod::Hourly => "hourly",
                     ServiceBillingMethod::Usage => "usage",
                 };
-                filter.insert("billingMethod".to_string(), Value::String(value.to_string()));
+                filter.insert(
+                    "billingMethod".to_string(),
+                    Value::String(value.to_string()),
+                );
             }
-            filter.insert("page".to_string(), Value::Number(serde_json::Number::from(page)));
+            filter.insert(
+                "page".to_string(),
+                Value::Number(serde_json::Number::from(page)),
+            );
             filter.insert(
                 "pageSize".to_string(),
                 Value::Number(serde_json::Number::from(page_size)),
@@ -2692,7 +3715,7 @@ impl services::HostWithStore for HasSelf<HostState> {
                 "services capability list_services start"
             );
 
-            let response = services_request(&install_id, "list", payload).await?;
+            let response = services_request(&api, &install_id, "list", payload).await?;
             let parsed = parse_services_list_result(&response).ok_or(ServiceReadError::Internal)?;
 
             tracing::info!(
@@ -2711,12 +3734,13 @@ impl services::HostWithStore for HasSelf<HostState> {
         service_id: String,
     ) -> impl std::future::Future<Output = Result<Option<ServiceSummary>, ServiceReadError>> + Send
     {
-        let (providers, install_id, ctx) = accessor.with(|mut access| {
+        let (providers, install_id, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
             (
                 state.context.providers.clone(),
                 state.context.install_id.clone(),
                 state.context.clone(),
+                state.runtime.internal_api.clone(),
             )
         });
 
@@ -2746,10 +3770,12 @@ impl services::HostWithStore for HasSelf<HostState> {
                 payload.insert("user".into(), user);
             }
 
-            let response = services_request(&install_id, "get", payload).await?;
+            let response = services_request(&api, &install_id, "get", payload).await?;
             let item = match response.get("item") {
                 Some(value) if value.is_null() => None,
-                Some(value) => Some(parse_service_summary(value).ok_or(ServiceReadError::Internal)?),
+                Some(value) => {
+                    Some(parse_service_summary(value).ok_or(ServiceReadError::Internal)?)
+                }
                 None => return Err(ServiceReadError::Internal),
             };
 
@@ -2761,18 +3787,114 @@ impl services::HostWithStore for HasSelf<HostState> {
                 "services capability get_service completed"
             );
 
-            Ok(item)
+            Ok(item)
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct UserServiceTarget<'a> {
+    base: &'a str,
+    token: &'a str,
+}
+
+/// The caller's user: the gateway-supplied details when present, otherwise a user-service
+/// lookup by the forwarded user id. None when neither is available.
+async fn resolve_user(api: &InternalApiConfig, ctx: &HostExecutionContext) -> Option<UserInfo> {
+    if let Some(user) = &ctx.user {
+        return Some(user.clone());
+    }
+    let target = UserServiceTarget {
+        base: api.user_service_base_url.as_deref()?,
+        token: api.token.as_deref()?,
+    };
+    lookup_user(target, &USER_LOOKUP_CACHE, ctx).await
+}
+
+/// Error code the ext-users route returns when the user does not exist in the tenant.
+const USER_NOT_FOUND_CODE: &str = "USER_NOT_FOUND";
+
+async fn lookup_user(
+    target: UserServiceTarget<'_>,
+    cache: &UserLookupCache,
+    ctx: &HostExecutionContext,
+) -> Option<UserInfo> {
+    let user_id = ctx.user_id.as_deref()?;
+    let install_id = ctx.install_id.as_deref()?;
+    let key = (
+        ctx.tenant_id.clone().unwrap_or_default(),
+        user_id.to_string(),
+    );
+    if let Some(cached) = cache.get(&key).await {
+        return cached;
+    }
+
+    let url = format!(
+        "{}/api/internal/ext-users/install/{}",
+        target.base.trim_end_matches('/'),
+        install_id
+    );
+    let response = match HTTP_CLIENT
+        .post(url)
+        .header("content-type", "application/json")
+        .header("x-runner-auth", target.token)
+        .json(&serde_json::json!({ "operation": "get", "userId": user_id }))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(err) => {
+            tracing::warn!(error = %err, install_id = %install_id, "user service transport failure");
+            return None;
         }
-    }
+    };
+
+    let status = response.status();
+    let user = if status == StatusCode::NOT_FOUND {
+        // Only the route's own "no such user" answer is cached; any other 404 means the route
+        // itself is missing (e.g. an older server) and the next call should retry.
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if body.get("code").and_then(Value::as_str) != Some(USER_NOT_FOUND_CODE) {
+            tracing::warn!(install_id = %install_id, "user service route not found");
+            return None;
+        }
+        None
+    } else if status.is_success() {
+        let body: Value = response.json().await.ok()?;
+        let user = body.get("user").cloned().unwrap_or(body);
+        match serde_json::from_value::<UserInfo>(user) {
+            Ok(user) => Some(user),
+            Err(err) => {
+                tracing::warn!(error = %err, install_id = %install_id, "user service returned an unexpected payload");
+                return None;
+            }
+        }
+    } else {
+        tracing::warn!(status = status.as_u16(), install_id = %install_id, "user service lookup failed");
+        return None;
+    };
+
+    tracing::debug!(
+        install_id = %install_id,
+        user_id = %redact_identifier(user_id),
+        found = user.is_some(),
+        "user service lookup complete"
+    );
+    cache.insert(key, user.clone()).await;
+    user
 }
 
 impl user::HostWithStore for HasSelf<HostState> {
     fn get_user<T>(
         accessor: &Accessor<T, Self>,
     ) -> impl std::future::Future<Output = Result<UserData, UserError>> + Send {
-        let (providers, ctx) = accessor.with(|mut access| {
+        let (providers, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
-            (state.context.providers.clone(), state.context.clone())
+            (
+                state.context.providers.clone(),
+                state.context.clone(),
+                state.runtime.internal_api.clone(),
+            )
         });
 
         async move {
@@ -2789,7 +3911,7 @@ impl user::HostWithStore for HasSelf<HostState> {
             let tenant = ctx.tenant_id.clone().unwrap_or_default();
             let extension = ctx.extension_id.clone().unwrap_or_default();
 
-            match &ctx.user {
+            match resolve_user(&api, &ctx).await {
                 Some(user_info) => {
                     tracing::info!(
                         tenant=%tenant,
@@ -2823,9 +3945,13 @@ impl user_v2::HostWithStore for HasSelf<HostState> {
     fn get_user<T>(
         accessor: &Accessor<T, Self>,
     ) -> impl std::future::Future<Output = Result<UserDataV2, UserError>> + Send {
-        let (providers, ctx) = accessor.with(|mut access| {
+        let (providers, ctx, api) = accessor.with(|mut access| {
             let state = access.get();
-            (state.context.providers.clone(), state.context.clone())
+            (
+                state.context.providers.clone(),
+                state.context.clone(),
+                state.runtime.internal_api.clone(),
+            )
         });
 
         async move {
@@ -2842,7 +3968,7 @@ impl user_v2::HostWithStore for HasSelf<HostState> {
             let tenant = ctx.tenant_id.clone().unwrap_or_default();
             let extension = ctx.extension_id.clone().unwrap_or_default();
 
-            match &ctx.user {
+            match resolve_user(&api, &ctx).await {
                 Some(user_info) => {
                     let mut additional_fields: Vec<(String, String)> = user_info
                         .additional_fields
@@ -2881,12 +4007,75 @@ impl user_v2::HostWithStore for HasSelf<HostState> {
     }
 }
 
+/// Record a guest metric under the caller's tenant/extension.
+fn emit_metric(
+    registry: &MetricsRegistry,
+    ctx: &HostExecutionContext,
+    kind: MetricKind,
+    name: &str,
+    value: u64,
+    tags: Vec<MetricTag>,
+) -> Result<(), MetricsError> {
+    if !has_capability(&ctx.providers, CAP_METRICS_EMIT) {
+        return Err(MetricsError::NotAllowed);
+    }
+    let tags: Vec<(String, String)> = tags.into_iter().map(|t| (t.key, t.value)).collect();
+    registry
+        .record(
+            kind,
+            ctx.tenant_id.as_deref().unwrap_or_default(),
+            ctx.extension_id.as_deref().unwrap_or_default(),
+            name,
+            value,
+            &tags,
+        )
+        .map_err(|rejection| {
+            tracing::debug!(
+                tenant = ?ctx.tenant_id,
+                extension = ?ctx.extension_id,
+                metric = %name,
+                ?rejection,
+                "guest metric dropped"
+            );
+            match rejection {
+                MetricRejection::InvalidMetric => MetricsError::InvalidMetric,
+                MetricRejection::LimitExceeded => MetricsError::LimitExceeded,
+            }
+        })
+}
+
+impl metrics::HostWithStore for HasSelf<HostState> {
+    fn counter<T>(
+        accessor: &Accessor<T, Self>,
+        name: String,
+        value: u64,
+        tags: Vec<MetricTag>,
+    ) -> impl std::future::Future<Output = Result<(), MetricsError>> + Send {
+        let ctx = accessor.with(|mut access| access.get().context.clone());
+        async move { emit_metric(&EXT_METRICS, &ctx, MetricKind::Counter, &name, value, tags) }
+    }
+
+    fn timing<T>(
+        accessor: &Accessor<T, Self>,
+        name: String,
+        ms: u64,
+        tags: Vec<MetricTag>,
+    ) -> impl std::future::Future<Output = Result<(), MetricsError>> + Send {
+        let ctx = accessor.with(|mut access| access.get().context.clone());
+        async move { emit_metric(&EXT_METRICS, &ctx, MetricKind::Timing, &name, ms, tags) }
+    }
+}
+
 impl types::Host for HostState {}
 impl context::Host for HostState {}
+impl context_v2::Host for HostState {}
+impl context_v3::Host for HostState {}
 impl secrets::Host for HostState {}
 impl http::Host for HostState {}
 impl storage::Host for HostState {}
+impl storage_v2::Host for HostState {}
 impl logging::Host for HostState {}
+impl metrics::Host for HostState {}
 impl ui_proxy::Host for HostState {}
 impl user::Host for HostState {}
 impl user_v2::Host for HostState {}
@@ -2898,10 +4087,100 @@ impl services::Host for HostState {}
 #[cfg(test)]
 mod tests {
     use super::*;
-    use axum::{routing::get, Router};
+    use axum::{routing::get, routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
     use tokio::net::TcpListener;
     use url::Url;
 
+    #[tokio::test]
+    async fn tenant_egress_allowlist_applies_only_to_that_tenant() {
+        let config = HostRuntimeConfig {
+            egress_mode: EgressMode::Allowlist,
+            egress_allowlist: vec!["api.global.com".to_string()],
+            egress_tenant_overlay: TenantEgressOverlay::Union,
+            egress_block_private_ips: false,
+            ..HostRuntimeConfig::default()
+        };
+        let tenant_a = HostExecutionContext {
+            tenant_id: Some("tenant-a".into()),
+            egress_allowlist: Some(normalize_allowlist([" Tenant-A.example.com "])),
+            ..Default::default()
+        };
+        let tenant_b = HostExecutionContext {
+            tenant_id: Some("tenant-b".into()),
+            ..Default::default()
+        };
+        let providers = HashSet::from([CAP_HTTP_FETCH.to_string()]);
+        let fetch = |url: &str| HttpRequest {
+            method: "GET".into(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+        };
+
+        let tenant_host = fetch("https://tenant-a.example.com/hook");
+        assert!(
+            authorize_fetch(&config, &tenant_a, &providers, &tenant_host)
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            authorize_fetch(&config, &tenant_b, &providers, &tenant_host).await,
+            Err(HttpError::NotAllowed)
+        ));
+        let global_host = fetch("https://api.global.com/v1");
+        assert!(
+            authorize_fetch(&config, &tenant_b, &providers, &global_host)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn tenant_egress_overlay_reports_denying_list() {
+        let tenant = vec!["tenant.example.com".to_string()];
+        let global_host = Url::parse("https://api.global.com/").unwrap();
+        let tenant_host = Url::parse("https://tenant.example.com/").unwrap();
+        let other = Url::parse("https://other.example.org/").unwrap();
+        let mut config = HostRuntimeConfig {
+            egress_mode: EgressMode::Allowlist,
+            egress_allowlist: vec!["api.global.com".to_string()],
+            ..HostRuntimeConfig::default()
+        };
+        assert_eq!(config.egress_tenant_overlay, TenantEgressOverlay::Intersect);
+
+        config.egress_tenant_overlay = TenantEgressOverlay::Union;
+        assert_eq!(check_egress(&config, Some(&tenant), &global_host), Ok(()));
+        assert_eq!(check_egress(&config, Some(&tenant), &tenant_host), Ok(()));
+        assert_eq!(
+            check_egress(&config, Some(&tenant), &other),
+            Err(EgressDenial::GlobalAndTenant)
+        );
+        assert_eq!(
+            check_egress(&config, None, &tenant_host),
+            Err(EgressDenial::Global)
+        );
+
+        config.egress_tenant_overlay = TenantEgressOverlay::Intersect;
+        assert_eq!(
+            check_egress(&config, Some(&tenant), &global_host),
+            Err(EgressDenial::Tenant)
+        );
+        assert_eq!(
+            check_egress(&config, Some(&tenant), &tenant_host),
+            Err(EgressDenial::Global)
+        );
+        assert_eq!(check_egress(&config, None, &global_host), Ok(()));
+
+        config.egress_mode = EgressMode::DenyAll;
+        config.egress_tenant_overlay = TenantEgressOverlay::Union;
+        assert_eq!(
+            check_egress(&config, Some(&tenant), &tenant_host),
+            Err(EgressDenial::Global)
+        );
+    }
+
     #[test]
     fn allowlist_checks_exact_and_subdomain() {
         let allow = vec!["example.com".to_string()];
@@ -2909,9 +4188,321 @@ mod tests {
         let sub = Url::parse("https://api.example.com/path").unwrap();
         let other = Url::parse("https://example.org/path").unwrap();
 
-        assert!(is_host_allowed(&allow, &exact));
-        assert!(is_host_allowed(&allow, &sub));
-        assert!(!is_host_allowed(&allow, &other));
+        assert!(is_host_allowed(EgressMode::Allowlist, &allow, &exact));
+        assert!(is_host_allowed(EgressMode::Allowlist, &allow, &sub));
+        assert!(!is_host_allowed(EgressMode::Allowlist, &allow, &other));
+    }
+
+    #[test]
+    fn egress_modes_with_empty_and_populated_allowlists() {
+        let empty: Vec<String> = Vec::new();
+        let allow = vec!["example.com".to_string()];
+        let listed = Url::parse("https://api.example.com/path").unwrap();
+        let other = Url::parse("https://example.org/path").unwrap();
+
+        assert!(is_host_allowed(EgressMode::AllowAll, &empty, &other));
+        assert!(is_host_allowed(EgressMode::AllowAll, &allow, &listed));
+        assert!(!is_host_allowed(EgressMode::AllowAll, &allow, &other));
+
+        assert!(!is_host_allowed(EgressMode::DenyAll, &empty, &other));
+        assert!(!is_host_allowed(EgressMode::DenyAll, &allow, &listed));
+
+        assert!(!is_host_allowed(EgressMode::Allowlist, &empty, &listed));
+        assert!(is_host_allowed(EgressMode::Allowlist, &allow, &listed));
+        assert!(!is_host_allowed(EgressMode::Allowlist, &allow, &other));
+    }
+
+    #[test]
+    fn runtime_config_rejects_invalid_values() {
+        assert_eq!(EgressMode::parse("allow-all"), Some(EgressMode::AllowAll));
+        assert_eq!(EgressMode::parse(" Deny-All "), Some(EgressMode::DenyAll));
+        assert_eq!(EgressMode::parse("allowlist"), Some(EgressMode::Allowlist));
+        assert_eq!(EgressMode::parse("open"), None);
+
+        let vars = HashMap::from([
+            ("EXT_EGRESS_MODE", "open"),
+            ("EXT_EGRESS_TENANT_OVERLAY", "widen"),
+            ("EXT_EGRESS_BLOCK_PRIVATE_IPS", "sometimes"),
+            ("UI_PROXY_TIMEOUT_MS", "0"),
+        ]);
+        let mut problems = Vec::new();
+        HostRuntimeConfig::from_lookup(|key| vars.get(key).map(|v| v.to_string()), &mut problems);
+        assert_eq!(problems.len(), 4, "{problems:?}");
+
+        let vars = HashMap::from([
+            ("EXT_EGRESS_MODE", "allowlist"),
+            ("EXT_EGRESS_ALLOWLIST", "API.example.com, "),
+            ("EXT_EGRESS_BLOCK_PRIVATE_IPS", "yes"),
+            ("EXT_STORAGE_MAX_VALUE_BYTES", "1024"),
+        ]);
+        let mut problems = Vec::new();
+        let cfg = HostRuntimeConfig::from_lookup(
+            |key| vars.get(key).map(|v| v.to_string()),
+            &mut problems,
+        );
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(cfg.egress_mode, EgressMode::Allowlist);
+        assert_eq!(cfg.egress_allowlist, vec!["api.example.com".to_string()]);
+        assert!(cfg.egress_block_private_ips);
+        assert_eq!(cfg.storage_max_value_bytes, 1024);
+    }
+
+    #[test]
+    fn permissive_egress_warns_only_at_startup() {
+        let logs = crate::util::test_logs::CapturedLogs::default();
+        let _guard = logs.install();
+        // The private address guard is off unless EXT_EGRESS_BLOCK_PRIVATE_IPS turns it on.
+        let cfg = HostRuntimeConfig::from_lookup(|_| None, &mut Vec::new());
+        assert!(!cfg.egress_block_private_ips);
+        assert!(!logs.text().contains("WARN"), "{}", logs.text());
+
+        cfg.log_startup_warnings();
+        let output = logs.text();
+        assert!(output.contains("may fetch ANY host"), "{output}");
+        assert!(
+            output.contains("EXT_EGRESS_BLOCK_PRIVATE_IPS is off"),
+            "{output}"
+        );
+    }
+
+    #[tokio::test]
+    async fn user_lookup_falls_back_to_user_service_and_caches() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/api/internal/ext-users/install/:install_id",
+            post(move |axum::Json(body): axum::Json<Value>| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    assert_eq!(body["userId"], "user-7");
+                    axum::Json(serde_json::json!({
+                        "user": {
+                            "user_id": "user-7",
+                            "user_email": "u7@example.com",
+                            "user_name": "User Seven",
+                            "user_type": "internal",
+                        }
+                    }))
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        let base = format!("http://{addr}");
+        let target = UserServiceTarget {
+            base: &base,
+            token: "runner-token",
+        };
+        let cache: UserLookupCache = moka::future::Cache::new(16);
+        let ctx = HostExecutionContext {
+            tenant_id: Some("tenant-1".into()),
+            install_id: Some("install-1".into()),
+            user_id: Some("user-7".into()),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            let user = lookup_user(target, &cache, &ctx)
+                .await
+                .expect("user resolved");
+            assert_eq!(user.user_email, "u7@example.com");
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let anonymous = HostExecutionContext {
+            user_id: None,
+            ..ctx.clone()
+        };
+        assert!(lookup_user(target, &cache, &anonymous).await.is_none());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn user_lookup_caches_unknown_users_but_not_a_missing_route() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new()
+            .route(
+                "/api/internal/ext-users/install/:install_id",
+                post(move || {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        (
+                            StatusCode::NOT_FOUND,
+                            axum::Json(serde_json::json!({
+                                "error": "User not found",
+                                "code": "USER_NOT_FOUND",
+                            })),
+                        )
+                    }
+                }),
+            )
+            .fallback(|| async { StatusCode::NOT_FOUND });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        let base = format!("http://{addr}");
+        let target = UserServiceTarget {
+            base: &base,
+            token: "runner-token",
+        };
+        let cache: UserLookupCache = moka::future::Cache::new(16);
+        let ctx = HostExecutionContext {
+            tenant_id: Some("tenant-1".into()),
+            install_id: Some("install-1".into()),
+            user_id: Some("user-7".into()),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            assert!(lookup_user(target, &cache, &ctx).await.is_none());
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let missing_route = format!("http://{addr}/older-server");
+        let target = UserServiceTarget {
+            base: &missing_route,
+            token: "runner-token",
+        };
+        let other_user = HostExecutionContext {
+            user_id: Some("user-8".into()),
+            ..ctx.clone()
+        };
+        assert!(lookup_user(target, &cache, &other_user).await.is_none());
+        assert!(!cache.contains_key(&("tenant-1".to_string(), "user-8".to_string())));
+    }
+
+    #[test]
+    fn missing_context_capability_is_a_classified_error() {
+        let ctx = HostExecutionContext {
+            tenant_id: Some("tenant-1".into()),
+            extension_id: Some("ext-1".into()),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            resolve_context(ctx.clone(), &HashSet::new()),
+            Err(ContextError::Denied)
+        ));
+
+        let granted = HashSet::from([CAP_CONTEXT_READ.to_string()]);
+        let data = resolve_context(ctx.clone(), &granted).expect("context granted");
+        assert_eq!(data.tenant_id, "tenant-1");
+        assert_eq!(data.extension_id, "ext-1");
+
+        let unscoped = HostExecutionContext {
+            tenant_id: None,
+            ..ctx
+        };
+        assert!(matches!(
+            resolve_context(unscoped, &granted),
+            Err(ContextError::Internal)
+        ));
+    }
+
+    #[test]
+    fn upload_limit_has_its_own_error() {
+        assert_eq!(
+            upload_error_to_http(UploadError::TooManyOpen),
+            HttpUploadError::TooManyUploads
+        );
+        assert_eq!(
+            upload_error_to_http(UploadError::UnknownHandle),
+            HttpUploadError::Internal
+        );
+        assert_eq!(
+            fetch_error_to_upload(HttpError::NotAllowed),
+            HttpUploadError::NotAllowed
+        );
+    }
+
+    #[test]
+    fn scratch_values_live_for_one_execution() {
+        let providers = HashSet::from([CAP_CONTEXT_READ.to_string()]);
+
+        let mut first = HashMap::new();
+        scratch_put(&providers, &mut first, "corr".into(), "abc-123".into()).unwrap();
+        assert_eq!(
+            scratch_get(&providers, &first, "corr").as_deref(),
+            Some("abc-123")
+        );
+        scratch_put(&providers, &mut first, "corr".into(), "abc-456".into()).unwrap();
+        assert_eq!(
+            scratch_get(&providers, &first, "corr").as_deref(),
+            Some("abc-456")
+        );
+
+        // The next execution gets a fresh HostState and so an empty map.
+        let second = HashMap::new();
+        assert_eq!(scratch_get(&providers, &second, "corr"), None);
+    }
+
+    #[test]
+    fn scratch_is_bounded_and_capability_gated() {
+        let mut providers = HashSet::new();
+        let mut scratch = HashMap::new();
+        assert_eq!(
+            scratch_put(&providers, &mut scratch, "k".into(), "v".into()),
+            Err(StorageErrorV2::Denied)
+        );
+
+        providers.insert(CAP_CONTEXT_READ.to_string());
+        assert_eq!(
+            scratch_put(
+                &providers,
+                &mut scratch,
+                "k".into(),
+                "v".repeat(SCRATCH_MAX_VALUE_BYTES + 1)
+            ),
+            Err(StorageErrorV2::TooLarge)
+        );
+        for i in 0..SCRATCH_MAX_ENTRIES {
+            scratch_put(&providers, &mut scratch, format!("k{i}"), "v".into()).unwrap();
+        }
+        assert_eq!(
+            scratch_put(&providers, &mut scratch, "overflow".into(), "v".into()),
+            Err(StorageErrorV2::TooLarge)
+        );
+        scratch_put(&providers, &mut scratch, "k0".into(), "updated".into()).unwrap();
+    }
+
+    #[test]
+    fn metrics_require_capability() {
+        let registry = MetricsRegistry::new(10, 10);
+        let mut ctx = HostExecutionContext {
+            tenant_id: Some("tenant-1".into()),
+            extension_id: Some("ext-1".into()),
+            ..Default::default()
+        };
+        let tags = || {
+            vec![MetricTag {
+                key: "kind".into(),
+                value: "sync".into(),
+            }]
+        };
+
+        assert_eq!(
+            emit_metric(&registry, &ctx, MetricKind::Counter, "jobs", 1, tags()),
+            Err(MetricsError::NotAllowed)
+        );
+        assert!(registry.render().is_empty());
+
+        ctx.providers.insert(CAP_METRICS_EMIT.to_string());
+        emit_metric(&registry, &ctx, MetricKind::Counter, "jobs", 4, tags()).unwrap();
+        assert!(registry.render().contains(
+            "alga_ext_jobs_total{tenant=\"tenant-1\",extension=\"ext-1\",kind=\"sync\"} 4"
+        ));
+        assert_eq!(
+            emit_metric(&registry, &ctx, MetricKind::Timing, "bad name", 1, vec![]),
+            Err(MetricsError::InvalidMetric)
+        );
     }
 
     #[test]
@@ -3044,4 +4635,317 @@ mod tests {
         server.abort();
         let _ = server.await;
     }
+
+    #[tokio::test]
+    async fn audit_records_fetch_and_secret_access_with_redaction() {
+        let app = Router::new().route("/hook", get(|| async { "ok" }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = HostRuntimeConfig {
+            egress_block_private_ips: false,
+            audit: AuditLog(Some(AuditQueue::Memory(events.clone()))),
+            ..HostRuntimeConfig::default()
+        };
+        let ctx = HostExecutionContext {
+            request_id: Some("req-1".into()),
+            tenant_id: Some("tenant-1".into()),
+            extension_id: Some("ext-1".into()),
+            providers: HashSet::from([CAP_HTTP_FETCH.to_string(), CAP_SECRETS_GET.to_string()]),
+            ..Default::default()
+        };
+
+        let response = http_fetch(
+            &config,
+            ctx.clone(),
+            &ctx.providers,
+            HttpRequest {
+                method: "GET".into(),
+                url: format!("http://{addr}/hook?token=supersecret"),
+                headers: Vec::new(),
+                body: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, 200);
+
+        let material = SecretMaterial {
+            values: HashMap::from([("api_token".to_string(), "s3cr3t-value".to_string())]),
+            version: None,
+        };
+        assert_eq!(
+            secrets_get(&config.audit, &ctx, Some(material), "api_token".into()).unwrap(),
+            "s3cr3t-value"
+        );
+        assert!(matches!(
+            secrets_get(&config.audit, &ctx, None, "missing_key".into()),
+            Err(SecretError::Missing)
+        ));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+
+        let fetch = &events[0];
+        assert_eq!(fetch["capability"], "http.fetch");
+        assert_eq!(fetch["tenant"], "tenant-1");
+        assert_eq!(fetch["extension"], "ext-1");
+        assert_eq!(fetch["request_id"], "req-1");
+        assert_eq!(fetch["outcome"], "ok");
+        assert_eq!(
+            fetch["target"],
+            format!("GET http://127.0.0.1:{}/hook?token=su…et", addr.port())
+        );
+        assert!(fetch["ts"].as_str().is_some());
+
+        let secret = &events[1];
+        assert_eq!(secret["capability"], "secrets.get");
+        assert_eq!(secret["target"], "ap…en");
+        assert_eq!(secret["outcome"], "ok");
+        assert!(secret.get("error").is_none());
+        assert!(!secret.to_string().contains("s3cr3t-value"));
+
+        assert_eq!(events[2]["outcome"], "error");
+        assert_eq!(events[2]["error"], "missing");
+
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[tokio::test]
+    async fn audit_endpoint_gets_its_own_token_from_the_background_writer() {
+        let (tx, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/audit",
+            post(move |headers: axum::http::HeaderMap, body: String| {
+                let tx = tx.clone();
+                async move {
+                    let auth = headers
+                        .get("authorization")
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string);
+                    let _ = tx.send((auth, headers.contains_key("x-runner-auth"), body));
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let audit = AuditLog::spawn(AuditSink::Endpoint {
+            url: Url::parse(&format!("http://{addr}/audit")).unwrap(),
+            token: Some("audit-token".into()),
+        });
+        let ctx = storage_ctx();
+        let ok: Result<(), StorageErrorV2> = Ok(());
+        for _ in 0..2 {
+            audit.record(&ctx, "storage.get", "ns".into(), &ok);
+        }
+
+        for _ in 0..2 {
+            let (auth, runner_auth, body) =
+                tokio::time::timeout(Duration::from_secs(5), received.recv())
+                    .await
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(auth.as_deref(), Some("Bearer audit-token"));
+            assert!(!runner_auth);
+            let event: Value = serde_json::from_str(body.trim()).unwrap();
+            assert_eq!(event["capability"], "storage.get");
+        }
+
+        server.abort();
+        let _ = server.await;
+    }
+
+    fn storage_ctx() -> HostExecutionContext {
+        HostExecutionContext {
+            tenant_id: Some("tenant-1".into()),
+            extension_id: Some("ext-1".into()),
+            install_id: Some("install-1".into()),
+            providers: HashSet::from([CAP_STORAGE_KV.to_string()]),
+            ..Default::default()
+        }
+    }
+
+    fn storage_value(len: usize) -> StorageEntry {
+        make_storage_entry("ns".into(), "key".into(), vec![7u8; len], None)
+    }
+
+    async fn start_storage_backend(
+        hits: Arc<AtomicUsize>,
+    ) -> (String, tokio::task::JoinHandle<()>) {
+        let app = Router::new().route(
+            "/api/internal/ext-storage/install/:install_id",
+            post(move || {
+                let hits = hits.clone();
+                async move {
+                    hits.fetch_add(1, Ordering::SeqCst);
+                    axum::Json(serde_json::json!({ "revision": 3 }))
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (format!("http://{addr}"), server)
+    }
+
+    #[tokio::test]
+    async fn storage_put_rejects_oversized_value_without_backend_call() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let (base, server) = start_storage_backend(hits.clone()).await;
+        let target = StorageTarget {
+            base: &base,
+            token: "token",
+        };
+
+        // 48 raw bytes encode to 64 base64 bytes.
+        let err = storage_put(Some(target), &storage_ctx(), storage_value(49), 64)
+            .await
+            .unwrap_err();
+        assert_eq!(err, StorageErrorV2::TooLarge);
+        assert_eq!(StorageError::from(err), StorageError::Denied);
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[tokio::test]
+    async fn storage_put_forwards_value_within_limit() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let (base, server) = start_storage_backend(hits.clone()).await;
+        let target = StorageTarget {
+            base: &base,
+            token: "token",
+        };
+
+        let stored = storage_put(Some(target), &storage_ctx(), storage_value(48), 64)
+            .await
+            .unwrap();
+        assert_eq!(stored.revision, Some(3));
+        assert_eq!(stored.value.len(), 48);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[tokio::test]
+    async fn storage_capability_is_checked_before_configuration() {
+        let mut ctx = storage_ctx();
+        ctx.providers.clear();
+        let err = storage_get(None, &ctx, "ns".into(), "key".into())
+            .await
+            .unwrap_err();
+        assert_eq!(err, StorageErrorV2::Denied);
+
+        let err = storage_get(None, &storage_ctx(), "ns".into(), "key".into())
+            .await
+            .unwrap_err();
+        assert_eq!(err, StorageErrorV2::Internal);
+    }
+
+    #[test]
+    fn storage_status_maps_backend_limits_to_too_large() {
+        assert_eq!(
+            map_storage_status(StatusCode::PAYLOAD_TOO_LARGE, ""),
+            StorageErrorV2::TooLarge
+        );
+        assert_eq!(
+            map_storage_status(StatusCode::BAD_REQUEST, r#"{"code":"LIMIT_EXCEEDED"}"#),
+            StorageErrorV2::TooLarge
+        );
+        assert_eq!(
+            map_storage_status(
+                StatusCode::TOO_MANY_REQUESTS,
+                r#"{"code":"QUOTA_EXCEEDED"}"#
+            ),
+            StorageErrorV2::TooLarge
+        );
+        assert_eq!(
+            map_storage_status(StatusCode::TOO_MANY_REQUESTS, r#"{"code":"RATE_LIMITED"}"#),
+            StorageErrorV2::Denied
+        );
+    }
+
+    #[tokio::test]
+    async fn outbound_calls_carry_the_execution_request_id() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = |seen: Arc<std::sync::Mutex<Vec<String>>>| {
+            move |headers: axum::http::HeaderMap| async move {
+                let id = headers
+                    .get(request_id::REQUEST_ID_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default();
+                seen.lock().unwrap().push(id.to_string());
+                "{}"
+            }
+        };
+        let app = Router::new()
+            .route("/hook", get(record(seen.clone())))
+            .route("/ext-1/ping", get(record(seen.clone())));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let config = HostRuntimeConfig {
+            egress_block_private_ips: false,
+            ui_proxy_base: Some(Url::parse(&format!("http://{addr}/")).unwrap()),
+            ..HostRuntimeConfig::default()
+        };
+        let id = request_id::generate();
+        let ctx = HostExecutionContext {
+            request_id: Some(id.clone()),
+            tenant_id: Some("tenant-1".into()),
+            extension_id: Some("ext-1".into()),
+            providers: HashSet::from([CAP_HTTP_FETCH.to_string(), CAP_UI_PROXY.to_string()]),
+            ..Default::default()
+        };
+        let fetch = |headers: Vec<HttpHeader>| HttpRequest {
+            method: "GET".into(),
+            url: format!("http://{addr}/hook"),
+            headers,
+            body: None,
+        };
+
+        http_fetch(&config, ctx.clone(), &ctx.providers, fetch(Vec::new()))
+            .await
+            .unwrap();
+        ui_proxy_call(&config, ctx.clone(), &ctx.providers, "/ping".into(), None)
+            .await
+            .unwrap();
+        // A guest-supplied id is forwarded instead of the execution's.
+        let guest_header = HttpHeader {
+            name: "X-Request-Id".into(),
+            value: "guest-id".into(),
+        };
+        http_fetch(
+            &config,
+            ctx.clone(),
+            &ctx.providers,
+            fetch(vec![guest_header]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![id.clone(), id, "guest-id".to_string()]
+        );
+
+        server.abort();
+        let _ = server.await;
+    }
 }
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(test)]
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::stream;
+use reqwest::{Body, RequestBuilder, Response};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Chunks queued between the guest and the outbound request; writers wait once it is full.
+pub const UPLOAD_CHANNEL_DEPTH: usize = 4;
+/// Streaming uploads a single execution may hold open at once.
+pub const MAX_OPEN_UPLOADS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadError {
+    /// No open upload with that handle.
+    UnknownHandle,
+    /// The execution already holds `MAX_OPEN_UPLOADS` uploads.
+    TooManyOpen,
+    /// The outbound request ended before the body was complete.
+    Closed,
+}
+
+/// Queued-but-unsent body bytes and their high-water mark, tracked in tests only.
+#[cfg(test)]
+#[derive(Default)]
+struct BufferGauge {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+#[cfg(test)]
+impl BufferGauge {
+    fn add(&self, bytes: usize) {
+        let now = self.current.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+    }
+
+    fn sub(&self, bytes: usize) {
+        self.current.fetch_sub(bytes, Ordering::SeqCst);
+    }
+}
+
+/// An in-progress `http.fetch` whose body the guest supplies chunk by chunk.
+pub struct HttpUpload {
+    writer: UploadWriter,
+    response: Option<JoinHandle<reqwest::Result<Response>>>,
+}
+
+/// Cloneable handle used to push chunks without holding the host state borrow.
+#[derive(Clone)]
+pub struct UploadWriter {
+    sender: mpsc::Sender<Bytes>,
+    #[cfg(test)]
+    gauge: Arc<BufferGauge>,
+}
+
+impl UploadWriter {
+    pub async fn write(&self, chunk: Vec<u8>) -> Result<(), UploadError> {
+        #[cfg(test)]
+        let len = chunk.len();
+        #[cfg(test)]
+        self.gauge.add(len);
+        if self.sender.send(Bytes::from(chunk)).await.is_err() {
+            #[cfg(test)]
+            self.gauge.sub(len);
+            return Err(UploadError::Closed);
+        }
+        Ok(())
+    }
+}
+
+impl HttpUpload {
+    /// Start sending `builder` with a streaming body; the request runs until `finish`.
+    pub fn start(builder: RequestBuilder) -> Self {
+        let (sender, receiver) = mpsc::channel::<Bytes>(UPLOAD_CHANNEL_DEPTH);
+        #[cfg(test)]
+        let gauge = Arc::new(BufferGauge::default());
+        #[cfg(test)]
+        let drain = gauge.clone();
+        let body = stream::unfold(receiver, move |mut receiver| {
+            #[cfg(test)]
+            let drain = drain.clone();
+            async move {
+                let chunk = receiver.recv().await?;
+                #[cfg(test)]
+                drain.sub(chunk.len());
+                Some((Ok::<_, std::io::Error>(chunk), receiver))
+            }
+        });
+        let response = tokio::spawn(builder.body(Body::wrap_stream(body)).send());
+        Self {
+            writer: UploadWriter {
+                sender,
+                #[cfg(test)]
+                gauge,
+            },
+            response: Some(response),
+        }
+    }
+
+    pub fn writer(&self) -> UploadWriter {
+        self.writer.clone()
+    }
+
+    /// Most body bytes held by the host at any one time.
+    #[cfg(test)]
+    fn peak_buffered_bytes(&self) -> usize {
+        self.writer.gauge.peak.load(Ordering::SeqCst)
+    }
+
+    /// Close the body and wait for the upstream response.
+    pub async fn finish(mut self) -> Result<reqwest::Result<Response>, UploadError> {
+        let response = self.response.take().ok_or(UploadError::Closed)?;
+        // Dropping the last sender ends the body stream.
+        drop(self);
+        response.await.map_err(|_| UploadError::Closed)
+    }
+}
+
+impl Drop for HttpUpload {
+    fn drop(&mut self) {
+        // Abandoned uploads must not outlive the execution.
+        if let Some(response) = self.response.take() {
+            response.abort();
+        }
+    }
+}
+
+/// Open streaming uploads for one execution, keyed by the handle returned to the guest.
+#[derive(Default)]
+pub struct HttpUploads {
+    next_handle: u32,
+    open: HashMap<u32, HttpUpload>,
+}
+
+impl HttpUploads {
+    pub fn insert(&mut self, upload: HttpUpload) -> Result<u32, UploadError> {
+        if self.open.len() >= MAX_OPEN_UPLOADS {
+            return Err(UploadError::TooManyOpen);
+        }
+        self.next_handle = self.next_handle.wrapping_add(1);
+        self.open.insert(self.next_handle, upload);
+        Ok(self.next_handle)
+    }
+
+    pub fn writer(&self, handle: u32) -> Result<UploadWriter, UploadError> {
+        self.open
+            .get(&handle)
+            .map(HttpUpload::writer)
+            .ok_or(UploadError::UnknownHandle)
+    }
+
+    pub fn remove(&mut self, handle: u32) -> Result<HttpUpload, UploadError> {
+        self.open.remove(&handle).ok_or(UploadError::UnknownHandle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body as AxumBody, routing::post, Router};
+    use futures_util::StreamExt;
+    use tokio::net::TcpListener;
+
+    const CHUNK_BYTES: usize = 64 * 1024;
+    const CHUNKS: usize = 256;
+
+    #[tokio::test]
+    async fn streams_large_body_with_bounded_buffering() {
+        let app = Router::new().route(
+            "/upload",
+            post(|body: AxumBody| async move {
+                let mut stream = body.into_data_stream();
+                let mut len = 0u64;
+                let mut sum = 0u64;
+                while let Some(frame) = stream.next().await {
+                    let frame = frame.unwrap();
+                    len += frame.len() as u64;
+                    sum = frame
+                        .iter()
+                        .fold(sum, |acc, byte| acc.wrapping_add(*byte as u64));
+                }
+                format!("{len}:{sum}")
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        let client = reqwest::Client::new();
+        let upload = HttpUpload::start(client.post(format!("http://{addr}/upload")));
+        let writer = upload.writer();
+        let mut expected_sum = 0u64;
+        for i in 0..CHUNKS {
+            let chunk = vec![(i % 251) as u8; CHUNK_BYTES];
+            expected_sum = expected_sum.wrapping_add((i % 251) as u64 * CHUNK_BYTES as u64);
+            writer.write(chunk).await.unwrap();
+        }
+        drop(writer);
+
+        let peak = upload.peak_buffered_bytes();
+        let response = upload.finish().await.unwrap().unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(
+            response.text().await.unwrap(),
+            format!("{}:{expected_sum}", CHUNKS * CHUNK_BYTES)
+        );
+        assert!(
+            peak <= (UPLOAD_CHANNEL_DEPTH + 1) * CHUNK_BYTES,
+            "host buffered {peak} bytes"
+        );
+
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[test]
+    fn handles_are_bounded_and_single_use() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let client = reqwest::Client::new();
+        let mut uploads = HttpUploads::default();
+
+        let mut handles = Vec::new();
+        for _ in 0..MAX_OPEN_UPLOADS {
+            let upload = HttpUpload::start(client.post("http://127.0.0.1:9/"));
+            handles.push(uploads.insert(upload).unwrap());
+        }
+        let extra = HttpUpload::start(client.post("http://127.0.0.1:9/"));
+        assert_eq!(uploads.insert(extra).err(), Some(UploadError::TooManyOpen));
+
+        assert!(uploads.remove(handles[0]).is_ok());
+        assert_eq!(
+            uploads.remove(handles[0]).err(),
+            Some(UploadError::UnknownHandle)
+        );
+        assert_eq!(
+            uploads.writer(handles[0]).err(),
+            Some(UploadError::UnknownHandle)
+        );
+    }
+}
@@ -7,20 +7,17 @@ use anyhow::Context;
 use once_cell::sync::Lazy;
 use reqwest::Client;
 use std::collections::{HashMap, HashSet};
-use std::env;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 use tar::Archive;
-use tokio::{
-    fs,
-    io::AsyncReadExt,
-    sync::{Mutex as TokioMutex, RwLock},
-};
+use tokio::{fs, io::AsyncReadExt, sync::Mutex as TokioMutex};
 use wasmtime::{
     component::{Component, Linker, ResourceTable},
     Config, Engine, InstanceAllocationStrategy, PoolingAllocationConfig, ResourceLimiter, Store,
+    UpdateDeadline, WasmBacktrace,
 };
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
 use wasmtime_wasi_http::{
@@ -33,7 +30,13 @@ use super::component;
 use super::host_api::{
     add_component_host, to_component_execute_request, to_model_execute_response, HostRuntimeConfig,
 };
-use crate::{cache::fs as cache_fs, util::errors::IntegrityError};
+use super::http_upload::HttpUploads;
+use crate::{
+    cache::{fs as cache_fs, space::CacheSpaceGuard},
+    config::RunnerConfig,
+    signing::TrustedKeys,
+    util::errors::{BundleFetchError, InstancePoolError, IntegrityError, SigningError},
+};
 use aws_credential_types::Credentials as AwsCredentials;
 use aws_sdk_s3::{config as s3config, Client as S3Client};
 use url::Url;
@@ -45,15 +48,235 @@ const DEFAULT_POOL_TOTAL_TABLES: u32 = 256;
 const DEFAULT_POOL_TOTAL_STACKS: u32 = 512;
 const DEFAULT_MAX_CORE_INSTANCE_SIZE: usize = 1 << 20;
 const DEFAULT_MAX_COMPONENT_INSTANCE_SIZE: usize = 1 << 20;
-const EPOCH_TICK_MS: u64 = 10;
+const DEFAULT_EPOCH_TICK_MS: u64 = 10;
+const DEFAULT_SLOW_EXECUTION_WARN_PCT: u64 = 80;
+
+/// Wasmtime engine sizing and execution defaults. `Default` is the built-in profile;
+/// embedders set fields directly and `RunnerConfig` layers `WASM_*`/`EXT_DEFAULT_*` on top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Guest memory ceiling when a request sets no `limits.memory_mb`.
+    pub default_memory_mb: u64,
+    /// Execution timeout when a request sets no `limits.timeout_ms`; `None` leaves it unbounded.
+    pub default_timeout_ms: Option<u64>,
+    pub pool_total_components: u32,
+    pub pool_total_memories: u32,
+    pub pool_total_tables: u32,
+    pub pool_total_stacks: u32,
+    pub max_core_instance_size: usize,
+    pub max_component_instance_size: usize,
+    /// Interval between engine epoch bumps; timeouts are rounded down to whole ticks.
+    pub epoch_tick_ms: u64,
+    /// Share of the timeout (percent) after which a still-running execution is logged as slow;
+    /// `0` disables the warning.
+    pub slow_execution_warn_pct: u64,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            default_memory_mb: DEFAULT_MAX_MEMORY_MB,
+            default_timeout_ms: None,
+            pool_total_components: DEFAULT_POOL_TOTAL_COMPONENTS,
+            pool_total_memories: DEFAULT_POOL_TOTAL_MEMORIES,
+            pool_total_tables: DEFAULT_POOL_TOTAL_TABLES,
+            pool_total_stacks: DEFAULT_POOL_TOTAL_STACKS,
+            max_core_instance_size: DEFAULT_MAX_CORE_INSTANCE_SIZE,
+            max_component_instance_size: DEFAULT_MAX_COMPONENT_INSTANCE_SIZE,
+            epoch_tick_ms: DEFAULT_EPOCH_TICK_MS,
+            slow_execution_warn_pct: DEFAULT_SLOW_EXECUTION_WARN_PCT,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Replace each field whose variable `lookup` returns, recording unparsable values in
+    /// `problems`.
+    pub(crate) fn apply_overrides(
+        mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+        problems: &mut Vec<String>,
+    ) -> Self {
+        fn read<T: std::str::FromStr>(
+            lookup: &impl Fn(&str) -> Option<String>,
+            problems: &mut Vec<String>,
+            key: &str,
+            field: &mut T,
+        ) {
+            if let Some(raw) = lookup(key) {
+                match raw.trim().parse::<T>() {
+                    Ok(value) => *field = value,
+                    Err(_) => problems.push(format!("{key} is not a valid number: {raw:?}")),
+                }
+            }
+        }
 
+        read(
+            &lookup,
+            problems,
+            "EXT_DEFAULT_MEMORY_MB",
+            &mut self.default_memory_mb,
+        );
+        let mut timeout = self.default_timeout_ms.unwrap_or(0);
+        read(&lookup, problems, "EXT_DEFAULT_TIMEOUT_MS", &mut timeout);
+        self.default_timeout_ms = (timeout > 0).then_some(timeout);
+        read(
+            &lookup,
+            problems,
+            "WASM_POOL_TOTAL_COMPONENTS",
+            &mut self.pool_total_components,
+        );
+        read(
+            &lookup,
+            problems,
+            "WASM_POOL_TOTAL_MEMORIES",
+            &mut self.pool_total_memories,
+        );
+        read(
+            &lookup,
+            problems,
+            "WASM_POOL_TOTAL_TABLES",
+            &mut self.pool_total_tables,
+        );
+        read(
+            &lookup,
+            problems,
+            "WASM_POOL_TOTAL_STACKS",
+            &mut self.pool_total_stacks,
+        );
+        read(
+            &lookup,
+            problems,
+            "WASM_POOL_MAX_CORE_INSTANCE_SIZE",
+            &mut self.max_core_instance_size,
+        );
+        read(
+            &lookup,
+            problems,
+            "WASM_POOL_MAX_COMPONENT_INSTANCE_SIZE",
+            &mut self.max_component_instance_size,
+        );
+        read(
+            &lookup,
+            problems,
+            "WASM_EPOCH_TICK_MS",
+            &mut self.epoch_tick_ms,
+        );
+        read(
+            &lookup,
+            problems,
+            "EXT_SLOW_EXECUTION_WARN_PCT",
+            &mut self.slow_execution_warn_pct,
+        );
+        self
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let positive = [
+            ("default_memory_mb", self.default_memory_mb),
+            ("pool_total_components", self.pool_total_components.into()),
+            ("pool_total_memories", self.pool_total_memories.into()),
+            ("pool_total_tables", self.pool_total_tables.into()),
+            ("pool_total_stacks", self.pool_total_stacks.into()),
+            ("max_core_instance_size", self.max_core_instance_size as u64),
+            (
+                "max_component_instance_size",
+                self.max_component_instance_size as u64,
+            ),
+            ("epoch_tick_ms", self.epoch_tick_ms),
+        ];
+        for (field, value) in positive {
+            if value == 0 {
+                anyhow::bail!("engine config {field} must be greater than zero");
+            }
+        }
+        if self.slow_execution_warn_pct >= 100 {
+            anyhow::bail!("engine config slow_execution_warn_pct must be below 100");
+        }
+        Ok(())
+    }
+
+    fn build_engine(&self) -> anyhow::Result<Engine> {
+        self.validate()?;
+
+        let mut cfg = Config::default();
+        // Enable async + epoch interruption for cooperative timeslicing
+        cfg.async_support(true)
+            .epoch_interruption(true)
+            // Fuel is optional; disabled by default for lower overhead
+            .consume_fuel(false)
+            .cranelift_debug_verifier(false)
+            .parallel_compilation(true);
+
+        let mut pool = PoolingAllocationConfig::default();
+        pool.total_core_instances(self.pool_total_components)
+            .total_memories(self.pool_total_memories)
+            .total_tables(self.pool_total_tables)
+            .total_stacks(self.pool_total_stacks)
+            .max_core_instance_size(self.max_core_instance_size)
+            .max_component_instance_size(self.max_component_instance_size)
+            // Keep small resident regions to avoid page thrash without bloating RSS
+            .linear_memory_keep_resident(0)
+            .table_keep_resident(0);
+        cfg.allocation_strategy(InstanceAllocationStrategy::Pooling(pool));
+
+        tracing::info!("Wasmtime Configuration:");
+        tracing::info!("  - Max Components: {}", self.pool_total_components);
+        tracing::info!("  - Max Memories: {}", self.pool_total_memories);
+        tracing::info!("  - Max Tables: {}", self.pool_total_tables);
+        tracing::info!("  - Max Stacks: {}", self.pool_total_stacks);
+        tracing::info!("  - Epoch Tick: {}ms", self.epoch_tick_ms);
+        tracing::info!("  - Async Support: ENABLED");
+        tracing::info!("  - Epoch Interruption: ENABLED");
+
+        Ok(Engine::new(&cfg)?)
+    }
+}
+
+/// Bumps an engine's epoch every tick until dropped. One per engine: every store's deadline
+/// counts ticks of the same clock, so concurrent executions do not speed each other up.
+struct EpochTicker {
+    stop: Arc<AtomicBool>,
+}
+
+impl EpochTicker {
+    fn start(engine: &Engine, tick_ms: u64) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let engine = engine.clone();
+        let stopped = stop.clone();
+        std::thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(tick_ms));
+                engine.increment_epoch();
+            }
+        });
+        Self { stop }
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Total component bytes kept in memory; least recently used entries are re-read from the
+/// bundle cache on disk once this is exceeded.
+const MODULE_CACHE_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Shared by every execution: one engine, instance pool and epoch clock per process, so
+/// `WASM_POOL_*` limits apply across concurrent requests.
 pub struct ModuleLoader {
     pub engine: Engine,
+    _epoch_ticker: EpochTicker,
+    engine_cfg: EngineConfig,
     http: Client,
-    cache: Arc<RwLock<HashMap<String, Arc<Vec<u8>>>>>,
+    /// Component bytes by tenant, extension, content hash and entry path.
+    cache: moka::future::Cache<String, Arc<Vec<u8>>>,
     runtime_cfg: HostRuntimeConfig,
     bundle_store_base: Url,
     cache_root: PathBuf,
+    cache_policy: Arc<BundleCachePolicy>,
 }
 
 #[derive(Clone, Default)]
@@ -67,6 +290,11 @@ pub struct HostExecutionContext {
     pub providers: HashSet<String>,
     pub secrets: Option<SecretMaterial>,
     pub user: Option<UserInfo>,
+    /// Caller's user id when the gateway forwarded only the id (`x-alga-user-id`);
+    /// `user.get-user` resolves the details from the user service.
+    pub user_id: Option<String>,
+    /// Tenant-specific egress hosts from the execute context; `None` applies only the global policy.
+    pub egress_allowlist: Option<Vec<String>>,
 }
 
 #[derive(Clone, Default)]
@@ -79,6 +307,10 @@ pub(crate) struct HostState {
     max_memory: usize,
     pub runtime: HostRuntimeConfig,
     pub context: HostExecutionContext,
+    /// Per-execution values set via `context-v3.scratch-put`; dropped with the store.
+    pub scratch: HashMap<String, String>,
+    /// Streaming `http.fetch` uploads opened by the guest during this execution.
+    pub uploads: HttpUploads,
     wasi: WasiCtx,
     table: ResourceTable,
     http: WasiHttpCtx,
@@ -123,66 +355,43 @@ impl WasiHttpView for HostState {
 }
 
 impl ModuleLoader {
-    pub fn new() -> anyhow::Result<Self> {
+    /// Build the engine and bundle access from settings parsed once at startup.
+    pub fn with_config(config: &RunnerConfig) -> anyhow::Result<Self> {
+        let engine_cfg = config.engine.clone();
         tracing::info!("Initializing Wasmtime ModuleLoader...");
         tracing::info!(
             "Configuring Wasmtime engine with pooling allocator and epoch-based interruption"
         );
 
-        let mut cfg = Config::default();
-        // Enable async + epoch interruption for cooperative timeslicing
-        cfg.async_support(true)
-            .epoch_interruption(true)
-            // Fuel is optional; disabled by default for lower overhead
-            .consume_fuel(false)
-            .cranelift_debug_verifier(false)
-            .parallel_compilation(true);
-
-        // Configure pooling allocator with conservative defaults
-        let mut pool = PoolingAllocationConfig::default();
-        pool.total_core_instances(DEFAULT_POOL_TOTAL_COMPONENTS)
-            .total_memories(DEFAULT_POOL_TOTAL_MEMORIES)
-            .total_tables(DEFAULT_POOL_TOTAL_TABLES)
-            .total_stacks(DEFAULT_POOL_TOTAL_STACKS)
-            .max_core_instance_size(DEFAULT_MAX_CORE_INSTANCE_SIZE)
-            .max_component_instance_size(DEFAULT_MAX_COMPONENT_INSTANCE_SIZE)
-            // Keep small resident regions to avoid page thrash without bloating RSS
-            .linear_memory_keep_resident(0)
-            .table_keep_resident(0);
-        cfg.allocation_strategy(InstanceAllocationStrategy::Pooling(pool));
-
-        tracing::info!("Wasmtime Configuration:");
-        tracing::info!("  - Max Components: {}", DEFAULT_POOL_TOTAL_COMPONENTS);
-        tracing::info!("  - Max Memories: {}", DEFAULT_POOL_TOTAL_MEMORIES);
-        tracing::info!("  - Max Tables: {}", DEFAULT_POOL_TOTAL_TABLES);
-        tracing::info!("  - Max Stacks: {}", DEFAULT_POOL_TOTAL_STACKS);
-        tracing::info!("  - Async Support: ENABLED");
-        tracing::info!("  - Epoch Interruption: ENABLED");
-
-        let engine = Engine::new(&cfg)?;
+        let engine = engine_cfg.build_engine()?;
+        let epoch_ticker = EpochTicker::start(&engine, engine_cfg.epoch_tick_ms);
         tracing::info!("✓ Wasmtime Engine created successfully");
 
         let http = Client::builder().build()?;
         tracing::info!("✓ HTTP client initialized for MinIO/bundle store communication");
 
-        let runtime_cfg = HostRuntimeConfig::from_env();
-        tracing::info!("✓ Host runtime configuration loaded from environment");
-
-        let bundle_store_raw = env::var("BUNDLE_STORE_BASE")
-            .map_err(|_| anyhow::anyhow!("BUNDLE_STORE_BASE not configured"))?;
-        let bundle_store_base = Url::parse(&bundle_store_raw)?;
-        tracing::info!(base=%bundle_store_base.as_str(), "✓ Bundle store base URL parsed");
+        let runtime_cfg = config.runtime.clone();
+        let bundle_store_base = config.bundle_store_url();
+        tracing::info!(base=%bundle_store_base.as_str(), "✓ Bundle store base URL resolved");
 
-        let cache_root = cache_fs::ext_cache_root_from_env();
+        let cache_root = config.cache_root.clone();
         tracing::info!(cache_root=%cache_root.to_string_lossy(), "✓ Extension cache root resolved");
 
         let loader = Self {
             engine,
+            _epoch_ticker: epoch_ticker,
+            engine_cfg,
             http,
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            cache: moka::future::Cache::builder()
+                .max_capacity(MODULE_CACHE_MAX_BYTES)
+                .weigher(|_key: &String, bytes: &Arc<Vec<u8>>| {
+                    u32::try_from(bytes.len()).unwrap_or(u32::MAX)
+                })
+                .build(),
             runtime_cfg,
             bundle_store_base,
             cache_root,
+            cache_policy: Arc::new(BundleCachePolicy::from_config(config)),
         };
         tracing::info!("✓ ModuleLoader fully initialized and ready");
         tracing::info!("  - In-memory object cache ready");
@@ -196,6 +405,7 @@ impl ModuleLoader {
         wasm: &[u8],
         timeout_ms: Option<u64>,
         memory_mb: Option<u64>,
+        env: &[(String, String)],
     ) -> anyhow::Result<(Store<HostState>, Component, Linker<HostState>)> {
         let wasm_size = wasm.len();
         tracing::info!(wasm_size=%wasm_size, timeout_ms=?timeout_ms, memory_mb=?memory_mb, "Wasmtime component instantiation starting");
@@ -209,52 +419,31 @@ impl ModuleLoader {
         let table = ResourceTable::new();
         tracing::info!("✓ Resource table initialized");
 
-        // For now we keep WASI stdio defaulted; stdout/stderr mapping is handled at the
-        // logging layer by using the HostExecutionContext + DebugHub. If in the future we
-        // attach explicit pipes here, they must forward lines to `emit_stdout_line` /
-        // `emit_stderr_line` with the current context.
-        // Use a dedicated stderr sink that mirrors guest stderr into the debug hub when enabled.
-        // We intentionally keep stdout as-is for now and treat stderr as the primary signal for
-        // extension authors; this avoids surprising noise while still surfacing failures.
         tracing::info!("Configuring WASI runtime context with stderr capture");
-        let stderr = StderrPipe::new(move |bytes: Vec<u8>| {
-            if let Ok(line) = std::str::from_utf8(&bytes) {
-                let line = line.trim_end_matches(&['\r', '\n'][..]).to_string();
-                if !line.is_empty() {
-                    // Best-effort: we don't have the context here yet; the debug hub will
-                    // attach request/tenant metadata once the HostExecutionContext is set
-                    // on the store before the handler is invoked.
-                    let ctx = HostExecutionContext::default();
-                    tokio::spawn(async move {
-                        crate::engine::debug::emit_stderr_line(&ctx, &line).await;
-                    });
-                }
-            }
-        });
-
-        let wasi = WasiCtxBuilder::new()
-            .inherit_args() // preserves existing behavior for args
-            .inherit_stdin()
-            .inherit_stdout()
-            .stderr(stderr)
-            .build();
-        tracing::info!("✓ WASI context configured with stdio handlers");
+        let wasi = guest_wasi_ctx(env);
+        tracing::info!(
+            env_vars = env.len(),
+            "✓ WASI context configured with stdio handlers"
+        );
 
         let http = WasiHttpCtx::new();
         tracing::info!("✓ WASI HTTP context initialized");
 
         // Calculate memory limit
-        let memory_limit = (memory_mb.unwrap_or(DEFAULT_MAX_MEMORY_MB) as usize) * 1024 * 1024;
+        let memory_mb = memory_mb.unwrap_or(self.engine_cfg.default_memory_mb);
+        let memory_limit = (memory_mb as usize) * 1024 * 1024;
         tracing::info!(
             "Memory limit configured: {} bytes ({} MB)",
             memory_limit,
-            memory_mb.unwrap_or(DEFAULT_MAX_MEMORY_MB)
+            memory_mb
         );
 
         let host_state = HostState {
             max_memory: memory_limit,
             runtime: self.runtime_cfg.clone(),
             context: HostExecutionContext::default(),
+            scratch: HashMap::new(),
+            uploads: HttpUploads::default(),
             wasi,
             table,
             http,
@@ -266,7 +455,7 @@ impl ModuleLoader {
         store.limiter(|s| s);
         tracing::info!("✓ Resource limiter installed on store");
 
-        if let Some(ms) = timeout_ms {
+        if let Some(ms) = timeout_ms.or(self.engine_cfg.default_timeout_ms) {
             tracing::info!(timeout_ms=%ms, "Configuring epoch-based timeout ({}ms)", ms);
             self.apply_timeout(&mut store, ms);
             tracing::info!("✓ Timeout configuration applied");
@@ -289,21 +478,12 @@ impl ModuleLoader {
     }
 
     fn apply_timeout(&self, store: &mut Store<HostState>, ms: u64) {
-        // Use epoch-based interruption. Map ms to ticks by incrementing the engine epoch every 10ms.
-        let ticks = deadline_ticks_for_timeout(ms);
-        store.set_epoch_deadline(ticks);
-        // For async configs, yield and update to continue if host wants to resume (not used yet)
-        let _ = store.epoch_deadline_trap();
+        arm_epoch_deadline(store, ms, &self.engine_cfg);
+    }
 
-        // Spawn a background task to bump the engine epoch periodically until the deadline is likely reached.
-        let engine = self.engine.clone();
-        std::thread::spawn(move || {
-            let steps = ticks + 2;
-            for _ in 0..steps {
-                std::thread::sleep(Duration::from_millis(EPOCH_TICK_MS));
-                engine.increment_epoch();
-            }
-        });
+    /// The bundle admission checks this loader applies, for other bundle consumers to share.
+    pub fn cache_policy(&self) -> Arc<BundleCachePolicy> {
+        self.cache_policy.clone()
     }
 
     pub async fn load_wasm_module(
@@ -312,15 +492,15 @@ impl ModuleLoader {
         extension: &str,
         content_hash: &str,
         entry_path: &str,
-    ) -> anyhow::Result<Vec<u8>> {
+    ) -> anyhow::Result<Arc<Vec<u8>>> {
         let hash_hex = content_hash
             .strip_prefix("sha256:")
             .unwrap_or(content_hash)
             .to_ascii_lowercase();
         let cache_key = format!("{}::{}::{}::{}", tenant, extension, hash_hex, entry_path);
-        if let Some(bytes) = self.cache.read().await.get(&cache_key).cloned() {
+        if let Some(bytes) = self.cache.get(&cache_key).await {
             tracing::info!(tenant=%tenant, extension=%extension, hash=%hash_hex, entry=%entry_path, "Component bytes served from in-memory cache");
-            return Ok((*bytes).clone());
+            return Ok(bytes);
         }
 
         let object_key = format!(
@@ -329,6 +509,7 @@ impl ModuleLoader {
         );
         tracing::info!(tenant=%tenant, extension=%extension, hash=%hash_hex, entry=%entry_path, object_key=%object_key, "Ensuring bundle cached locally");
         let paths = ensure_bundle_cached(
+            &self.cache_policy,
             &self.bundle_store_base,
             &self.cache_root,
             &object_key,
@@ -338,14 +519,30 @@ impl ModuleLoader {
 
         let wasm_path = paths.bundle_root.join(entry_path);
         tracing::info!(tenant=%tenant, extension=%extension, path=%wasm_path.to_string_lossy(), "Reading WASM component from cache");
-        let bytes = fs::read(&wasm_path).await.map_err(|err| {
-            tracing::error!(error=%err.to_string(), path=%wasm_path.to_string_lossy(), "Failed to read WASM component from cache");
-            err
-        })?;
+        let bytes = match fs::read(&wasm_path).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                // The cache may have been pruned since it was verified; re-check once.
+                tracing::warn!(error=%err.to_string(), path=%wasm_path.to_string_lossy(), "Cached WASM component unreadable; re-checking bundle cache");
+                forget_verified_bundle(&self.cache_root.join(&hash_hex));
+                ensure_bundle_cached(
+                    &self.cache_policy,
+                    &self.bundle_store_base,
+                    &self.cache_root,
+                    &object_key,
+                    &hash_hex,
+                )
+                .await?;
+                fs::read(&wasm_path).await.map_err(|err| {
+                    tracing::error!(error=%err.to_string(), path=%wasm_path.to_string_lossy(), "Failed to read WASM component from cache");
+                    err
+                })?
+            }
+        };
         tracing::info!(tenant=%tenant, extension=%extension, bytes=%bytes.len(), "WASM component loaded from cache");
 
-        let arc = Arc::new(bytes.clone());
-        self.cache.write().await.insert(cache_key, arc);
+        let bytes = Arc::new(bytes);
+        self.cache.insert(cache_key, bytes.clone()).await;
         Ok(bytes)
     }
 
@@ -396,7 +593,8 @@ impl ModuleLoader {
 
         // Instantiate WASM component
         tracing::info!(request_id=%request_id, "Instantiating WASM component in Wasmtime");
-        let (mut store, component, linker) = self.instantiate(wasm, timeout_ms, memory_mb)?;
+        let env = guest_env(&context.config, &self.runtime_cfg.wasi_env_allowlist);
+        let (mut store, component, linker) = self.instantiate(wasm, timeout_ms, memory_mb, &env)?;
         tracing::info!(request_id=%request_id, "WASM component instantiated successfully");
 
         // Set execution context
@@ -410,7 +608,10 @@ impl ModuleLoader {
 
         // Instantiate the component
         tracing::info!(request_id=%request_id, "Instantiating component instance");
-        let instance = instance_pre.instantiate_async(&mut store).await?;
+        let instance = instance_pre
+            .instantiate_async(&mut store)
+            .await
+            .map_err(|e| classify_pool_exhaustion(e.into()))?;
         tracing::info!(request_id=%request_id, "Component instance created");
 
         // Get the handler function
@@ -438,7 +639,7 @@ impl ModuleLoader {
             Err(e) => {
                 tracing::error!(request_id=%request_id, error_debug=?e, "Extension handler execution failed");
                 tracing::error!(request_id=%request_id, error_display=%e.to_string(), "Handler error details");
-                return Err(e.into());
+                return Err(classify_pool_exhaustion(e.into()));
             }
         };
 
@@ -451,6 +652,21 @@ impl ModuleLoader {
     }
 }
 
+/// Re-tag wasmtime pooling allocator exhaustion as [`InstancePoolError`] so the server can
+/// answer 503 instead of a generic failure.
+fn classify_pool_exhaustion(err: anyhow::Error) -> anyhow::Error {
+    let exhausted = err
+        .chain()
+        .any(|cause| cause.is::<wasmtime::PoolConcurrencyLimitError>());
+    if exhausted {
+        anyhow::Error::new(InstancePoolError::Exhausted {
+            detail: format!("{err:#}"),
+        })
+    } else {
+        err
+    }
+}
+
 /// Fetch a URL (bundle/object) and write atomically to a destination file.
 /// Currently buffers response fully; can be optimized later to chunked write.
 pub async fn fetch_to_file(url: &str, dest_tmp: &Path) -> anyhow::Result<()> {
@@ -464,8 +680,101 @@ pub async fn fetch_to_file(url: &str, dest_tmp: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn deadline_ticks_for_timeout(timeout_ms: u64) -> u64 {
-    (timeout_ms / EPOCH_TICK_MS).max(1)
+/// WASI context for one execution: inherited args/stdin/stdout, stderr mirrored to the debug
+/// hub, and `env` as the complete environment.
+fn guest_wasi_ctx(env: &[(String, String)]) -> WasiCtx {
+    // For now we keep WASI stdio defaulted; stdout/stderr mapping is handled at the
+    // logging layer by using the HostExecutionContext + DebugHub. If in the future we
+    // attach explicit pipes here, they must forward lines to `emit_stdout_line` /
+    // `emit_stderr_line` with the current context.
+    // Use a dedicated stderr sink that mirrors guest stderr into the debug hub when enabled.
+    // We intentionally keep stdout as-is for now and treat stderr as the primary signal for
+    // extension authors; this avoids surprising noise while still surfacing failures.
+    let stderr = StderrPipe::new(move |bytes: Vec<u8>| {
+        if let Ok(line) = std::str::from_utf8(&bytes) {
+            let line = line.trim_end_matches(&['\r', '\n'][..]).to_string();
+            if !line.is_empty() {
+                // Best-effort: we don't have the context here yet; the debug hub will
+                // attach request/tenant metadata once the HostExecutionContext is set
+                // on the store before the handler is invoked.
+                let ctx = HostExecutionContext::default();
+                tokio::spawn(async move {
+                    crate::engine::debug::emit_stderr_line(&ctx, &line).await;
+                });
+            }
+        }
+    });
+
+    // The host environment is never inherited; only allowlisted install config is visible.
+    WasiCtxBuilder::new()
+        .inherit_args() // preserves existing behavior for args
+        .inherit_stdin()
+        .inherit_stdout()
+        .stderr(stderr)
+        .envs(env)
+        .build()
+}
+
+/// Install config entries exposed to the guest as WASI environment variables. Keys must be
+/// allowlisted (exact, or by a `PREFIX*` entry) and be valid variable names.
+fn guest_env(config: &HashMap<String, String>, allowlist: &[String]) -> Vec<(String, String)> {
+    let allowed = |key: &str| {
+        allowlist.iter().any(|entry| match entry.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == entry,
+        })
+    };
+    let mut env: Vec<(String, String)> = config
+        .iter()
+        .filter(|(key, value)| {
+            !key.is_empty() && !key.contains(['=', '\0']) && !value.contains('\0') && allowed(key)
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    env.sort();
+    env
+}
+
+fn deadline_ticks_for_timeout(timeout_ms: u64, tick_ms: u64) -> u64 {
+    (timeout_ms / tick_ms).max(1)
+}
+
+/// Trap the store after `ms`, logging a slow-execution warning with the guest backtrace once
+/// `slow_execution_warn_pct` of the timeout has passed.
+fn arm_epoch_deadline(store: &mut Store<HostState>, ms: u64, cfg: &EngineConfig) {
+    // Use epoch-based interruption. Map ms to ticks by incrementing the engine epoch every tick.
+    let tick_ms = cfg.epoch_tick_ms;
+    let ticks = deadline_ticks_for_timeout(ms, tick_ms);
+    let warn_after_ms = ms * cfg.slow_execution_warn_pct / 100;
+    let warn_ticks = (warn_after_ms / tick_ms).max(1);
+    if cfg.slow_execution_warn_pct == 0 || warn_ticks >= ticks {
+        store.set_epoch_deadline(ticks);
+        store.epoch_deadline_trap();
+    } else {
+        // The first deadline is the warning point; the callback then extends to the timeout.
+        store.set_epoch_deadline(warn_ticks);
+        let started = Instant::now();
+        let mut warned = false;
+        store.epoch_deadline_callback(move |store| {
+            if warned {
+                return Ok(UpdateDeadline::Interrupt);
+            }
+            warned = true;
+            let ctx = &store.data().context;
+            let backtrace = WasmBacktrace::capture(&store);
+            tracing::warn!(
+                request_id = ctx.request_id.as_deref().unwrap_or("unknown"),
+                tenant = ctx.tenant_id.as_deref().unwrap_or("unknown"),
+                extension = ctx.extension_id.as_deref().unwrap_or("unknown"),
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                warn_after_ms,
+                timeout_ms = ms,
+                backtrace = %backtrace,
+                "Extension execution is approaching its timeout"
+            );
+            Ok(UpdateDeadline::Continue(ticks - warn_ticks))
+        });
+    }
 }
 
 /// Build the bundle URL from BUNDLE_STORE_BASE and a content hash "sha256:<hex>" or "<hex>".
@@ -489,11 +798,18 @@ pub fn bundle_url_for_key(bundle_store_base: &Url, key: &str) -> anyhow::Result<
     Ok(Url::parse(&full)?)
 }
 
-/// Stream a bundle archive to a temp file while computing sha256, verifying against expected hex.
+/// Default end-to-end budget for downloading a bundle archive.
+pub const DEFAULT_BUNDLE_FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Stream a bundle archive to a temp file under `cache_root` while computing sha256, verifying against expected hex.
 /// On success returns the path to the temp file. On mismatch deletes the temp and returns IntegrityError::ArchiveHashMismatch.
+/// The download is presigned when `presign` is set; exceeding `fetch_timeout` yields BundleFetchError::Timeout.
 pub async fn verify_archive_sha256(
     url: &Url,
     expected_hex: &str,
+    cache_root: &Path,
+    fetch_timeout: Duration,
+    presign: Option<&S3PresignConfig>,
 ) -> anyhow::Result<std::path::PathBuf> {
     use rand::{distributions::Alphanumeric, Rng};
     use sha2::{Digest, Sha256};
@@ -504,7 +820,6 @@ pub async fn verify_archive_sha256(
     tracing::info!(expected_hash=%expected_lower, bundle_url=%url.to_string(), "Bundle archive download and hash verification started");
     tracing::info!(expected_hash=%expected_lower, "Archive will be verified against SHA256 hash and extracted to cache");
 
-    let cache_root = cache_fs::ext_cache_root_from_env();
     let tmp_dir = cache_root.join("tmp");
     cache_fs::ensure_dir(&tmp_dir).await?;
     let rand_suffix: String = rand::thread_rng()
@@ -514,94 +829,26 @@ pub async fn verify_archive_sha256(
         .collect();
     let tmp_path = tmp_dir.join(format!("{}.{}.tar.zst", expected_lower, rand_suffix));
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .build()?;
-
-    // Prefer presigned S3 GET if credentials are available; fallback to direct URL
-    let mut fetch_url = url.clone();
-    let mut using_presigned = false;
-
-    if let (Ok(base), Some(access), Some(secret)) = (
-        std::env::var("BUNDLE_STORE_BASE"),
-        std::env::var("S3_ACCESS_KEY")
-            .ok()
-            .or_else(|| std::env::var("MINIO_ACCESS_KEY").ok()),
-        std::env::var("S3_SECRET_KEY")
-            .ok()
-            .or_else(|| std::env::var("MINIO_SECRET_KEY").ok()),
-    ) {
-        tracing::info!(expected_hash=%expected_lower, "S3/MinIO credentials detected - attempting presigned URL generation");
-        if let Ok(base_url) = Url::parse(&base) {
-            let bucket = base_url
-                .path()
-                .trim_matches('/')
-                .split('/')
-                .next()
-                .unwrap_or("");
-            if !bucket.is_empty() {
-                let endpoint = match (base_url.scheme(), base_url.host_str(), base_url.port()) {
-                    (scheme, Some(host), Some(port)) => format!("{}://{}:{}", scheme, host, port),
-                    (scheme, Some(host), None) => format!("{}://{}", scheme, host),
-                    _ => String::new(),
-                };
-                if !endpoint.is_empty() {
-                    let region =
-                        std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
-                    let creds = AwsCredentials::new(
-                        access.clone(),
-                        secret.clone(),
-                        None,
-                        None,
-                        "alga-ext-runner",
-                    );
-                    let conf = s3config::Builder::new()
-                        .region(s3config::Region::new(region))
-                        .endpoint_url(endpoint)
-                        .credentials_provider(creds)
-                        .force_path_style(true)
-                        .build();
-                    let s3 = S3Client::from_conf(conf);
-                    if let Some((bucket_name, object_key)) =
-                        presign_target_from_urls(&base_url, &url)
-                    {
-                        if let Ok(cfg) = aws_sdk_s3::presigning::PresigningConfig::expires_in(
-                            Duration::from_secs(60),
-                        ) {
-                            match s3
-                                .get_object()
-                                .bucket(&bucket_name)
-                                .key(&object_key)
-                                .presigned(cfg)
-                                .await
-                            {
-                                Ok(ps) => {
-                                    if let Ok(u) = Url::parse(&ps.uri().to_string()) {
-                                        using_presigned = true;
-                                        fetch_url = u;
-                                        tracing::info!(expected_hash=%expected_lower, bucket=%bucket_name, key=%object_key, "Using presigned S3 GET URL for secure download");
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::warn!(expected_hash=%expected_lower, err=%e.to_string(), bucket=%bucket_name, key=%object_key, "Presigned URL generation failed; falling back to direct URL");
-                                }
-                            }
-                        }
-                    } else {
-                        tracing::debug!(expected_hash=%expected_lower, bundle_url=%url.to_string(), "Presigned URL skipped; unable to derive bucket/key from URLs");
-                    }
-                }
-            }
+    let client = reqwest::Client::builder().timeout(fetch_timeout).build()?;
+    let timeout_ms = fetch_timeout.as_millis() as u64;
+    let fetch_err = |e: reqwest::Error| -> anyhow::Error {
+        if e.is_timeout() {
+            tracing::error!(expected_hash=%expected_lower, timeout_ms=%timeout_ms, "Bundle download timed out");
+            BundleFetchError::Timeout { timeout_ms }.into()
+        } else {
+            e.into()
         }
-    }
+    };
 
-    if !using_presigned {
-        tracing::info!(expected_hash=%expected_lower, "Using direct URL download (no presigned URL)");
-    }
+    let fetch_url = bundle_download_url(url, &expected_lower, presign).await;
 
     tracing::info!(expected_hash=%expected_lower, download_url=%fetch_url.to_string(), "Starting bundle download");
 
-    let mut resp = client.get(fetch_url.clone()).send().await?;
+    let mut resp = client
+        .get(fetch_url.clone())
+        .send()
+        .await
+        .map_err(fetch_err)?;
     if !resp.status().is_success() {
         tracing::error!(expected_hash=%expected_lower, status=%resp.status().as_u16(), download_url=%fetch_url.to_string(), "Bundle download failed with non-success HTTP status");
         tracing::error!(
@@ -620,7 +867,16 @@ pub async fn verify_archive_sha256(
     // Stream using reqwest Response::chunk to avoid extra deps
     let mut total: u64 = 0;
     let mut chunks: u64 = 0;
-    while let Some(bytes) = resp.chunk().await? {
+    loop {
+        let bytes = match resp.chunk().await {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => break,
+            Err(e) => {
+                drop(file);
+                let _ = tfs::remove_file(&tmp_path).await;
+                return Err(fetch_err(e));
+            }
+        };
         hasher.update(&bytes);
         file.write_all(&bytes).await?;
         total += bytes.len() as u64;
@@ -665,15 +921,190 @@ pub async fn verify_archive_sha256(
     Ok(tmp_path)
 }
 
+/// Presigned S3 GET for `url` when store credentials are configured; the plain URL otherwise.
+async fn bundle_download_url(
+    url: &Url,
+    expected_lower: &str,
+    presign: Option<&S3PresignConfig>,
+) -> Url {
+    let Some(presign) = presign else {
+        tracing::info!(expected_hash=%expected_lower, "Using direct URL download (no presigned URL)");
+        return url.clone();
+    };
+
+    tracing::info!(expected_hash=%expected_lower, "S3/MinIO credentials detected - attempting presigned URL generation");
+    let style = presign.style;
+    let Some(target) = s3_presign_target(style, &presign.base, url, presign.endpoint.as_deref())
+    else {
+        tracing::debug!(expected_hash=%expected_lower, bundle_url=%url.to_string(), style=?style, "Presigned URL skipped; unable to derive bucket/key from URLs");
+        tracing::info!(expected_hash=%expected_lower, "Using direct URL download (no presigned URL)");
+        return url.clone();
+    };
+
+    let creds = AwsCredentials::new(
+        presign.access_key.clone(),
+        presign.secret_key.clone(),
+        None,
+        None,
+        "alga-ext-runner",
+    );
+    let mut builder = s3config::Builder::new()
+        .region(s3config::Region::new(presign.region.clone()))
+        .credentials_provider(creds)
+        .force_path_style(style == S3AddressingStyle::Path);
+    if let Some(endpoint) = &target.endpoint {
+        builder = builder.endpoint_url(endpoint);
+    }
+    let s3 = S3Client::from_conf(builder.build());
+    if let Ok(cfg) = aws_sdk_s3::presigning::PresigningConfig::expires_in(Duration::from_secs(60)) {
+        match s3
+            .get_object()
+            .bucket(&target.bucket)
+            .key(&target.key)
+            .presigned(cfg)
+            .await
+        {
+            Ok(ps) => {
+                if let Ok(u) = Url::parse(ps.uri()) {
+                    tracing::info!(expected_hash=%expected_lower, bucket=%target.bucket, key=%target.key, style=?style, "Using presigned S3 GET URL for secure download");
+                    return u;
+                }
+            }
+            Err(e) => {
+                tracing::warn!(expected_hash=%expected_lower, err=%e.to_string(), bucket=%target.bucket, key=%target.key, "Presigned URL generation failed; falling back to direct URL");
+            }
+        }
+    }
+
+    tracing::info!(expected_hash=%expected_lower, "Using direct URL download (no presigned URL)");
+    url.clone()
+}
+
+/// Detached signature object stored next to each `bundle.tar.zst`.
+const BUNDLE_SIGNATURE_OBJECT: &str = "bundle.sig";
+/// Signatures are 64 bytes (base64 text is under 100); anything larger is not one.
+const MAX_SIGNATURE_BYTES: usize = 1024;
+
+/// Check the detached Ed25519 signature beside `archive_url`. The signature covers the
+/// content hash string `sha256:<hex>`, so it can be checked before the archive is downloaded;
+/// the archive itself is then bound to that hash by [`verify_archive_sha256`].
+async fn verify_bundle_signature(
+    trusted: &TrustedKeys,
+    archive_url: &Url,
+    expected_lower: &str,
+    fetch_timeout: Duration,
+    presign: Option<&S3PresignConfig>,
+) -> anyhow::Result<()> {
+    let sig_url = archive_url.join(BUNDLE_SIGNATURE_OBJECT)?;
+    let fetch_url = bundle_download_url(&sig_url, expected_lower, presign).await;
+    let timeout_ms = fetch_timeout.as_millis() as u64;
+    let fetch_err = |e: reqwest::Error| -> anyhow::Error {
+        if e.is_timeout() {
+            BundleFetchError::Timeout { timeout_ms }.into()
+        } else {
+            e.into()
+        }
+    };
+
+    let client = reqwest::Client::builder().timeout(fetch_timeout).build()?;
+    let resp = client.get(fetch_url).send().await.map_err(fetch_err)?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        tracing::error!(hash=%expected_lower, signature_url=%sig_url, "Bundle has no detached signature");
+        return Err(SigningError::Missing.into());
+    }
+    if !resp.status().is_success() {
+        anyhow::bail!("bundle signature fetch failed: {}", resp.status());
+    }
+    let signature = resp.bytes().await.map_err(fetch_err)?;
+    if signature.len() > MAX_SIGNATURE_BYTES {
+        return Err(SigningError::MalformedSignature {
+            reason: format!("signature object is {} bytes", signature.len()),
+        }
+        .into());
+    }
+
+    let signed = format!("sha256:{}", expected_lower);
+    if let Err(err) = trusted.verify(signed.as_bytes(), &signature) {
+        tracing::error!(hash=%expected_lower, signature_url=%sig_url, err=%err, "Bundle signature verification failed");
+        return Err(err.into());
+    }
+    tracing::info!(hash=%expected_lower, "Bundle signature verified");
+    Ok(())
+}
+
 #[derive(Clone, Debug)]
 pub struct BundleCachePaths {
     pub bundle_root: PathBuf,
     pub ui_root: PathBuf,
 }
 
-static BUNDLE_EXTRACT_LOCK: Lazy<TokioMutex<()>> = Lazy::new(|| TokioMutex::new(()));
+/// Per-hash extraction locks. Concurrent requests for the same bundle wait for a single
+/// fetch+extract; different bundles proceed independently. Entries are weak so the map
+/// only holds hashes with an extraction in flight.
+static BUNDLE_EXTRACT_LOCKS: Lazy<std::sync::Mutex<HashMap<String, Weak<TokioMutex<()>>>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+fn bundle_extract_lock(hash: &str) -> Arc<TokioMutex<()>> {
+    let mut locks = BUNDLE_EXTRACT_LOCKS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(lock) = locks.get(hash).and_then(Weak::upgrade) {
+        return lock;
+    }
+    locks.retain(|_, lock| lock.strong_count() > 0);
+    let lock = Arc::new(TokioMutex::new(()));
+    locks.insert(hash.to_string(), Arc::downgrade(&lock));
+    lock
+}
+
+/// Checks a bundle must pass before it is admitted to the local cache.
+pub struct BundleCachePolicy {
+    space: CacheSpaceGuard,
+    /// When set, bundles need a signature from one of these keys.
+    trusted_keys: Option<TrustedKeys>,
+    /// When set, downloads use presigned S3 URLs instead of the plain bundle store URL.
+    presign: Option<S3PresignConfig>,
+    /// Budget for each archive or signature download.
+    fetch_timeout: Duration,
+}
+
+impl BundleCachePolicy {
+    pub fn new(space: CacheSpaceGuard, trusted_keys: Option<TrustedKeys>) -> Self {
+        Self {
+            space,
+            trusted_keys,
+            presign: None,
+            fetch_timeout: DEFAULT_BUNDLE_FETCH_TIMEOUT,
+        }
+    }
+
+    pub fn with_fetch_timeout(mut self, fetch_timeout: Duration) -> Self {
+        self.fetch_timeout = fetch_timeout;
+        self
+    }
+
+    /// Free-space floor, trusted keys, presign credentials and the fetch budget come from the
+    /// startup-validated configuration.
+    pub fn from_config(config: &RunnerConfig) -> Self {
+        if let Some(keys) = &config.trusted_keys {
+            tracing::info!(
+                keys = keys.keys().len(),
+                "Bundle signature verification enabled"
+            );
+        }
+        Self {
+            presign: config.s3_presign.clone(),
+            fetch_timeout: config.bundle_fetch_timeout,
+            ..Self::new(
+                CacheSpaceGuard::statvfs(config.cache_min_free_bytes),
+                config.trusted_keys.clone(),
+            )
+        }
+    }
+}
 
 pub async fn ensure_bundle_cached(
+    policy: &BundleCachePolicy,
     bundle_store_base: &Url,
     cache_root: &Path,
     object_key: &str,
@@ -682,35 +1113,70 @@ pub async fn ensure_bundle_cached(
     let normalized_hash = hash_hex.to_ascii_lowercase();
     let bundle_root = cache_root.join(&normalized_hash).join("bundle");
     let ui_root = cache_root.join(&normalized_hash).join("ui");
+    let hash_root = cache_root.join(&normalized_hash);
     let marker = bundle_root.join(".ready");
 
-    if fs::metadata(&marker).await.is_ok() {
+    if bundle_cache_ready(&hash_root, &marker).await {
         return Ok(BundleCachePaths {
             bundle_root,
             ui_root,
         });
     }
 
-    let _guard = BUNDLE_EXTRACT_LOCK.lock().await;
-    if fs::metadata(&marker).await.is_ok() {
+    let lock = bundle_extract_lock(&normalized_hash);
+    let _guard = lock.lock().await;
+    if bundle_cache_ready(&hash_root, &marker).await {
         return Ok(BundleCachePaths {
             bundle_root,
             ui_root,
         });
     }
+    if fs::metadata(&marker).await.is_ok() {
+        tracing::warn!(hash=%normalized_hash, "Bundle cache marker present but files missing; re-extracting");
+        let _ = fs::remove_file(&marker).await;
+    }
 
+    policy.space.check(cache_root)?;
     let url = bundle_url_for_key(bundle_store_base, object_key)?;
+    if let Some(trusted) = &policy.trusted_keys {
+        verify_bundle_signature(
+            trusted,
+            &url,
+            &normalized_hash,
+            policy.fetch_timeout,
+            policy.presign.as_ref(),
+        )
+        .await?;
+    }
     tracing::info!(hash=%normalized_hash, object_key=%object_key, url=%url.to_string(), "Bundle archive fetch start");
-    let tmp_archive = verify_archive_sha256(&url, &normalized_hash).await?;
+    let tmp_archive = verify_archive_sha256(
+        &url,
+        &normalized_hash,
+        cache_root,
+        policy.fetch_timeout,
+        policy.presign.as_ref(),
+    )
+    .await?;
+
+    let written = match extract_bundle_archive(&tmp_archive, &bundle_root, &ui_root).await {
+        Ok(written) => written,
+        Err(err) => {
+            let _ = fs::remove_dir_all(&bundle_root).await;
+            let _ = fs::remove_dir_all(&ui_root).await;
+            let _ = fs::remove_file(&tmp_archive).await;
+            return Err(err);
+        }
+    };
 
-    if let Err(err) = extract_bundle_archive(&tmp_archive, &bundle_root, &ui_root).await {
-        let _ = fs::remove_dir_all(&bundle_root).await;
-        let _ = fs::remove_dir_all(&ui_root).await;
-        let _ = fs::remove_file(&tmp_archive).await;
-        return Err(err);
+    let mut manifest = String::from(READY_MARKER_HEADER);
+    for path in &written {
+        if let Ok(rel) = path.strip_prefix(&hash_root) {
+            manifest.push('\n');
+            manifest.push_str(&rel.to_string_lossy());
+        }
     }
-
-    cache_fs::write_atomic(&marker, b"ok").await?;
+    cache_fs::write_atomic(&marker, manifest.into_bytes()).await?;
+    mark_bundle_verified(&hash_root);
     let _ = fs::remove_file(&tmp_archive).await;
     tracing::info!(hash=%normalized_hash, bundle_root=%bundle_root.to_string_lossy(), "Bundle archive cached locally");
 
@@ -720,11 +1186,81 @@ pub async fn ensure_bundle_cached(
     })
 }
 
+/// First line of the `.ready` marker; the remaining lines list every extracted file
+/// relative to the hash directory. Older runners wrote a bare `ok`, which carries no
+/// file list and is treated as stale.
+const READY_MARKER_HEADER: &str = "files-v1";
+
+/// Hash directories checked against their `.ready` marker by this process. Later cache hits
+/// trust the directory without re-statting every recorded file; callers that fail to open a
+/// cached file call [`forget_verified_bundle`] so the next request checks the disk again.
+static VERIFIED_BUNDLES: Lazy<std::sync::Mutex<HashSet<PathBuf>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashSet::new()));
+
+fn mark_bundle_verified(hash_root: &Path) {
+    VERIFIED_BUNDLES
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .insert(hash_root.to_path_buf());
+}
+
+/// Drop `hash_root` from the verified set so the next request re-checks its files.
+pub fn forget_verified_bundle(hash_root: &Path) {
+    VERIFIED_BUNDLES
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .remove(hash_root);
+}
+
+/// Verified once per process; the full file check runs only on the first hit.
+async fn bundle_cache_ready(hash_root: &Path, marker: &Path) -> bool {
+    let verified = VERIFIED_BUNDLES
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .contains(hash_root);
+    if verified {
+        return true;
+    }
+    if bundle_cache_intact(hash_root, marker).await {
+        mark_bundle_verified(hash_root);
+        return true;
+    }
+    false
+}
+
+/// True when the `.ready` marker exists and every file it records is still on disk.
+async fn bundle_cache_intact(hash_root: &Path, marker: &Path) -> bool {
+    let Ok(contents) = fs::read_to_string(marker).await else {
+        return false;
+    };
+    let mut lines = contents.lines();
+    if lines.next() != Some(READY_MARKER_HEADER) {
+        return false;
+    }
+    for rel in lines.filter(|l| !l.is_empty()) {
+        if fs::metadata(hash_root.join(rel)).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// True when the `.ready` marker under `hash_root` records `rel` (relative to the hash directory)
+/// as one of the bundle's files.
+pub async fn bundle_records_file(hash_root: &Path, rel: &Path) -> bool {
+    let Ok(contents) = fs::read_to_string(hash_root.join("bundle").join(".ready")).await else {
+        return false;
+    };
+    let mut lines = contents.lines();
+    lines.next() == Some(READY_MARKER_HEADER) && lines.any(|line| Path::new(line) == rel)
+}
+
+/// Extract the archive into `bundle_root`/`ui_root`, returning the paths of the files written.
 async fn extract_bundle_archive(
     archive_path: &Path,
     bundle_root: &Path,
     ui_root: &Path,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Vec<PathBuf>> {
     tracing::info!(archive=%archive_path.to_string_lossy(), bundle_root=%bundle_root.to_string_lossy(), ui_root=%ui_root.to_string_lossy(), "Extracting bundle archive to cache");
 
     let mut file = fs::File::open(archive_path).await?;
@@ -792,15 +1328,139 @@ async fn extract_bundle_archive(
         }
     }
 
+    let mut written = Vec::new();
     for op in ops {
         match op {
             Op::Mkdir(path) => cache_fs::ensure_dir(&path).await?,
-            Op::Write { path, contents } => cache_fs::write_atomic(&path, contents).await?,
+            Op::Write { path, contents } => {
+                cache_fs::write_atomic(&path, contents).await?;
+                written.push(path);
+            }
         }
     }
 
-    tracing::info!(bundle_root=%bundle_root.to_string_lossy(), ui_root=%ui_root.to_string_lossy(), "Bundle archive extraction complete");
-    Ok(())
+    tracing::info!(bundle_root=%bundle_root.to_string_lossy(), ui_root=%ui_root.to_string_lossy(), files=%written.len(), "Bundle archive extraction complete");
+    Ok(written)
+}
+
+/// How bucket names are encoded in bundle store URLs, selected by `S3_ADDRESSING_STYLE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum S3AddressingStyle {
+    /// `http://endpoint/<bucket>/<key>` (MinIO; default).
+    Path,
+    /// `https://<bucket>.<endpoint>/<key>` (AWS S3 and compatible).
+    Virtual,
+}
+
+impl S3AddressingStyle {
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "virtual" | "virtual-hosted" => Some(S3AddressingStyle::Virtual),
+            "path" => Some(S3AddressingStyle::Path),
+            _ => None,
+        }
+    }
+}
+
+/// Credentials and addressing for presigned bundle store downloads (`S3_*`, or `MINIO_*` keys).
+#[derive(Clone, PartialEq, Eq)]
+pub struct S3PresignConfig {
+    /// Bundle store base the bucket (and key prefix) are derived from.
+    pub base: Url,
+    pub access_key: String,
+    pub secret_key: String,
+    pub style: S3AddressingStyle,
+    /// Endpoint to sign against (`S3_ENDPOINT`); derived from `base` when unset.
+    pub endpoint: Option<String>,
+    /// Signing region (`S3_REGION`, default `us-east-1`).
+    pub region: String,
+}
+
+impl std::fmt::Debug for S3PresignConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3PresignConfig")
+            .field("base", &self.base.as_str())
+            .field("access_key", &self.access_key)
+            .field("secret_key", &"<redacted>")
+            .field("style", &self.style)
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .finish()
+    }
+}
+
+/// Where and what to presign. `endpoint` is None when the SDK should resolve the
+/// regional AWS endpoint itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct S3PresignTarget {
+    endpoint: Option<String>,
+    bucket: String,
+    key: String,
+}
+
+fn url_origin(url: &Url) -> Option<String> {
+    match (url.scheme(), url.host_str(), url.port()) {
+        (scheme, Some(host), Some(port)) => Some(format!("{}://{}:{}", scheme, host, port)),
+        (scheme, Some(host), None) => Some(format!("{}://{}", scheme, host)),
+        _ => None,
+    }
+}
+
+fn s3_presign_target(
+    style: S3AddressingStyle,
+    base_url: &Url,
+    bundle_url: &Url,
+    endpoint_override: Option<&str>,
+) -> Option<S3PresignTarget> {
+    match style {
+        S3AddressingStyle::Path => {
+            let endpoint = match endpoint_override {
+                Some(e) => e.to_string(),
+                None => url_origin(base_url)?,
+            };
+            let (bucket, key) = presign_target_from_urls(base_url, bundle_url)?;
+            Some(S3PresignTarget {
+                endpoint: Some(endpoint),
+                bucket,
+                key,
+            })
+        }
+        S3AddressingStyle::Virtual => {
+            let host = base_url.host_str()?;
+            let override_host = endpoint_override
+                .and_then(|e| Url::parse(e).ok())
+                .and_then(|u| u.host_str().map(str::to_string));
+            let bucket = match &override_host {
+                Some(endpoint_host) => host.strip_suffix(&format!(".{}", endpoint_host))?,
+                None => match host.find(".s3.").or_else(|| host.find(".s3-")) {
+                    Some(idx) => &host[..idx],
+                    None => host.split('.').next()?,
+                },
+            };
+            if bucket.is_empty() || bucket == host {
+                return None;
+            }
+            let endpoint = match endpoint_override {
+                Some(e) => Some(e.to_string()),
+                // Real AWS: let the SDK derive the endpoint from S3_REGION.
+                None if host.ends_with(".amazonaws.com") => None,
+                None => {
+                    let mut origin = base_url.clone();
+                    origin.set_host(Some(&host[bucket.len() + 1..])).ok()?;
+                    url_origin(&origin)
+                }
+            };
+            let key = bundle_url.path().trim_start_matches('/').to_string();
+            if key.is_empty() {
+                return None;
+            }
+            Some(S3PresignTarget {
+                endpoint,
+                bucket: bucket.to_string(),
+                key,
+            })
+        }
+    }
 }
 
 fn presign_target_from_urls(base_url: &Url, bundle_url: &Url) -> Option<(String, String)> {
@@ -833,6 +1493,8 @@ fn presign_target_from_urls(base_url: &Url, bundle_url: &Url) -> Option<(String,
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::errors::CacheSpaceError;
+    use crate::util::test_logs::CapturedLogs;
     use wasmtime_wasi::WasiCtxBuilder;
     use wasmtime_wasi_http::WasiHttpCtx;
 
@@ -844,12 +1506,98 @@ mod tests {
             max_memory: (max_memory_mb as usize) * 1024 * 1024,
             runtime,
             context: HostExecutionContext::default(),
+            scratch: HashMap::new(),
+            uploads: HttpUploads::default(),
             wasi,
             table,
             http,
         }
     }
 
+    #[test]
+    fn pool_exhaustion_is_classified() {
+        let mut pool = PoolingAllocationConfig::default();
+        pool.total_core_instances(1)
+            .total_memories(1)
+            .total_tables(1);
+        let mut cfg = Config::default();
+        cfg.allocation_strategy(InstanceAllocationStrategy::Pooling(pool));
+        let engine = Engine::new(&cfg).unwrap();
+        let module = wasmtime::Module::new(&engine, "(module (memory 1))").unwrap();
+
+        let mut first = Store::new(&engine, ());
+        let _held = wasmtime::Instance::new(&mut first, &module, &[]).unwrap();
+        let mut second = Store::new(&engine, ());
+        let err = wasmtime::Instance::new(&mut second, &module, &[]).unwrap_err();
+
+        let classified = classify_pool_exhaustion(err.into());
+        assert!(matches!(
+            classified.downcast_ref::<InstancePoolError>(),
+            Some(InstancePoolError::Exhausted { .. })
+        ));
+
+        let other = classify_pool_exhaustion(anyhow::anyhow!("component trapped"));
+        assert!(other.downcast_ref::<InstancePoolError>().is_none());
+    }
+
+    #[test]
+    fn custom_pool_sizes_are_applied() {
+        let cfg = EngineConfig {
+            pool_total_components: 1,
+            pool_total_memories: 1,
+            pool_total_tables: 1,
+            pool_total_stacks: 1,
+            ..EngineConfig::default()
+        };
+        let engine = cfg.build_engine().unwrap();
+        let module = wasmtime::Module::new(&engine, "(module (memory 1))").unwrap();
+
+        let mut first = Store::new(&engine, ());
+        let _held = wasmtime::Instance::new(&mut first, &module, &[]).unwrap();
+        let mut second = Store::new(&engine, ());
+        let err = wasmtime::Instance::new(&mut second, &module, &[]).unwrap_err();
+        assert!(err.is::<wasmtime::PoolConcurrencyLimitError>());
+
+        let invalid = EngineConfig {
+            pool_total_memories: 0,
+            ..EngineConfig::default()
+        };
+        assert!(invalid.build_engine().is_err());
+    }
+
+    #[test]
+    fn engine_config_env_overrides_layer_on_explicit_values() {
+        let base = EngineConfig {
+            pool_total_stacks: 8,
+            default_timeout_ms: Some(2_000),
+            ..EngineConfig::default()
+        };
+        let env = HashMap::from([
+            ("WASM_POOL_TOTAL_MEMORIES", "16"),
+            ("WASM_EPOCH_TICK_MS", "5"),
+            ("EXT_DEFAULT_MEMORY_MB", "64"),
+            ("WASM_POOL_TOTAL_TABLES", "lots"),
+        ]);
+        let mut problems = Vec::new();
+        let cfg = base
+            .clone()
+            .apply_overrides(|key| env.get(key).map(|v| v.to_string()), &mut problems);
+        assert_eq!(cfg.pool_total_memories, 16);
+        assert_eq!(cfg.epoch_tick_ms, 5);
+        assert_eq!(cfg.default_memory_mb, 64);
+        assert_eq!(cfg.pool_total_tables, DEFAULT_POOL_TOTAL_TABLES);
+        assert_eq!(cfg.pool_total_stacks, 8);
+        assert_eq!(cfg.default_timeout_ms, Some(2_000));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("WASM_POOL_TOTAL_TABLES"));
+
+        let cleared = base.apply_overrides(
+            |key| (key == "EXT_DEFAULT_TIMEOUT_MS").then(|| "0".to_string()),
+            &mut problems,
+        );
+        assert_eq!(cleared.default_timeout_ms, None);
+    }
+
     #[test]
     fn memory_growing_enforces_limits() {
         let runtime = HostRuntimeConfig::default();
@@ -862,12 +1610,131 @@ mod tests {
         assert!(!state.memory_growing(0, 16 * 1024 * 1024, None).unwrap());
     }
 
+    #[tokio::test]
+    async fn guest_env_exposes_only_allowlisted_config() {
+        use wasmtime_wasi::cli::WasiCliView as _;
+        use wasmtime_wasi::p2::bindings::cli::environment::Host as _;
+
+        let config = HashMap::from([
+            (
+                "API_BASE".to_string(),
+                "https://api.example.com".to_string(),
+            ),
+            ("FEATURE_BETA".to_string(), "on".to_string()),
+            ("db_password".to_string(), "hunter2".to_string()),
+            ("PATH".to_string(), "/guest/bin".to_string()),
+            ("BAD=KEY".to_string(), "x".to_string()),
+        ]);
+        let allowlist = vec![
+            "API_BASE".to_string(),
+            "FEATURE_*".to_string(),
+            "BAD*".to_string(),
+        ];
+        let env = guest_env(&config, &allowlist);
+
+        let mut state = make_host_state(8, HostRuntimeConfig::default());
+        state.wasi = guest_wasi_ctx(&env);
+        let visible = state.cli().get_environment().unwrap();
+        assert_eq!(
+            visible,
+            vec![
+                (
+                    "API_BASE".to_string(),
+                    "https://api.example.com".to_string()
+                ),
+                ("FEATURE_BETA".to_string(), "on".to_string()),
+            ]
+        );
+        assert!(std::env::var_os("PATH").is_some());
+        assert!(!visible.iter().any(|(key, _)| key == "PATH"));
+
+        assert!(guest_env(&config, &[]).is_empty());
+    }
+
     #[test]
     fn timeout_ms_maps_to_deadline_ticks() {
-        assert_eq!(deadline_ticks_for_timeout(1), 1);
-        assert_eq!(deadline_ticks_for_timeout(EPOCH_TICK_MS), 1);
-        assert_eq!(deadline_ticks_for_timeout(EPOCH_TICK_MS + 1), 1);
-        assert_eq!(deadline_ticks_for_timeout(25), 2);
+        let tick = DEFAULT_EPOCH_TICK_MS;
+        assert_eq!(deadline_ticks_for_timeout(1, tick), 1);
+        assert_eq!(deadline_ticks_for_timeout(tick, tick), 1);
+        assert_eq!(deadline_ticks_for_timeout(tick + 1, tick), 1);
+        assert_eq!(deadline_ticks_for_timeout(25, tick), 2);
+        assert_eq!(deadline_ticks_for_timeout(25, 5), 5);
+    }
+
+    /// Run a guest that sleeps `iterations` x 20ms in the host under a 400ms timeout.
+    async fn run_sleeping_guest(iterations: i32, logs: &CapturedLogs) -> anyhow::Result<()> {
+        let cfg = EngineConfig {
+            pool_total_components: 2,
+            pool_total_memories: 2,
+            pool_total_tables: 2,
+            pool_total_stacks: 2,
+            epoch_tick_ms: 5,
+            slow_execution_warn_pct: 50,
+            ..EngineConfig::default()
+        };
+        let engine = cfg.build_engine()?;
+        let _ticker = EpochTicker::start(&engine, cfg.epoch_tick_ms);
+        let module = wasmtime::Module::new(
+            &engine,
+            r#"(module
+                (import "host" "sleep" (func $sleep))
+                (func (export "run") (param $n i32)
+                    (loop $again
+                        (call $sleep)
+                        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                        (br_if $again (local.get $n)))))"#,
+        )?;
+        let mut linker = wasmtime::Linker::<HostState>::new(&engine);
+        linker.func_wrap_async("host", "sleep", |_caller, (): ()| {
+            Box::new(tokio::time::sleep(Duration::from_millis(20)))
+        })?;
+
+        let mut state = make_host_state(8, HostRuntimeConfig::default());
+        state.context.request_id = Some("req-slow".into());
+        state.context.tenant_id = Some("tenant-a".into());
+        state.context.extension_id = Some("ext-slow".into());
+        let mut store = Store::new(&engine, state);
+        arm_epoch_deadline(&mut store, 400, &cfg);
+
+        let _guard = logs.install();
+        let instance = linker.instantiate_async(&mut store, &module).await?;
+        let run = instance.get_typed_func::<i32, ()>(&mut store, "run")?;
+        Ok(run.call_async(&mut store, iterations).await?)
+    }
+
+    #[tokio::test]
+    async fn slow_execution_is_logged_before_the_timeout() {
+        // ~300ms: past the 200ms warning point, inside the 400ms timeout.
+        let logs = CapturedLogs::default();
+        run_sleeping_guest(15, &logs).await.unwrap();
+        let output = logs.text();
+        let warnings: Vec<&str> = output
+            .lines()
+            .filter(|line| line.contains("approaching its timeout"))
+            .collect();
+        assert_eq!(warnings.len(), 1, "{output}");
+        for field in [
+            "WARN",
+            "request_id=\"req-slow\"",
+            "tenant=\"tenant-a\"",
+            "extension=\"ext-slow\"",
+            "warn_after_ms=200",
+            "timeout_ms=400",
+            "elapsed_ms=",
+        ] {
+            assert!(warnings[0].contains(field), "missing {field}: {output}");
+        }
+
+        let fast = CapturedLogs::default();
+        run_sleeping_guest(2, &fast).await.unwrap();
+        assert!(fast.text().is_empty());
+
+        let timed_out = CapturedLogs::default();
+        let err = run_sleeping_guest(50, &timed_out).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<wasmtime::Trap>(),
+            Some(&wasmtime::Trap::Interrupt)
+        );
     }
 
     #[test]
@@ -930,4 +1797,341 @@ mod tests {
         assert_eq!(bucket, "extensions");
         assert_eq!(key, "tenants/t1/extensions/e1/sha256/h/bundle.tar.zst");
     }
+
+    #[test]
+    fn s3_target_path_style_uses_base_origin_and_first_segment() {
+        let base = Url::parse("http://minio:9000/extensions").unwrap();
+        let bundle =
+            Url::parse("http://minio:9000/extensions/tenants/t1/sha256/h/bundle.tar.zst").unwrap();
+        let target = s3_presign_target(S3AddressingStyle::Path, &base, &bundle, None).unwrap();
+        assert_eq!(target.endpoint.as_deref(), Some("http://minio:9000"));
+        assert_eq!(target.bucket, "extensions");
+        assert_eq!(target.key, "tenants/t1/sha256/h/bundle.tar.zst");
+
+        let target = s3_presign_target(
+            S3AddressingStyle::Path,
+            &base,
+            &bundle,
+            Some("https://s3.internal:9443"),
+        )
+        .unwrap();
+        assert_eq!(target.endpoint.as_deref(), Some("https://s3.internal:9443"));
+    }
+
+    #[test]
+    fn s3_target_virtual_style_on_aws_defers_endpoint_to_region() {
+        let base = Url::parse("https://ext-bundles.s3.us-west-2.amazonaws.com/").unwrap();
+        let bundle = Url::parse(
+            "https://ext-bundles.s3.us-west-2.amazonaws.com/tenants/t1/sha256/h/bundle.tar.zst",
+        )
+        .unwrap();
+        let target = s3_presign_target(S3AddressingStyle::Virtual, &base, &bundle, None).unwrap();
+        assert_eq!(target.endpoint, None);
+        assert_eq!(target.bucket, "ext-bundles");
+        assert_eq!(target.key, "tenants/t1/sha256/h/bundle.tar.zst");
+    }
+
+    #[test]
+    fn s3_target_virtual_style_with_custom_endpoint() {
+        let base = Url::parse("https://my.bucket.storage.example.com").unwrap();
+        let bundle =
+            Url::parse("https://my.bucket.storage.example.com/sha256/h/bundle.tar.zst").unwrap();
+        let target = s3_presign_target(
+            S3AddressingStyle::Virtual,
+            &base,
+            &bundle,
+            Some("https://storage.example.com"),
+        )
+        .unwrap();
+        assert_eq!(
+            target.endpoint.as_deref(),
+            Some("https://storage.example.com")
+        );
+        assert_eq!(target.bucket, "my.bucket");
+        assert_eq!(target.key, "sha256/h/bundle.tar.zst");
+
+        // Without an override the endpoint is the host minus the bucket label.
+        let base = Url::parse("http://bundles.minio.local:9000/").unwrap();
+        let bundle = Url::parse("http://bundles.minio.local:9000/sha256/h/bundle.tar.zst").unwrap();
+        let target = s3_presign_target(S3AddressingStyle::Virtual, &base, &bundle, None).unwrap();
+        assert_eq!(target.endpoint.as_deref(), Some("http://minio.local:9000"));
+        assert_eq!(target.bucket, "bundles");
+    }
+
+    fn make_bundle(marker: &str) -> (Vec<u8>, String) {
+        use sha2::{Digest, Sha256};
+        let mut raw = Vec::new();
+        {
+            let mut tar = tar::Builder::new(&mut raw);
+            let mut hdr = tar::Header::new_gnu();
+            hdr.set_size(marker.len() as u64);
+            hdr.set_mode(0o644);
+            hdr.set_cksum();
+            tar.append_data(&mut hdr, "ui/index.html", marker.as_bytes())
+                .unwrap();
+            tar.finish().unwrap();
+        }
+        let bytes = zstd::stream::encode_all(&raw[..], 0).unwrap();
+        let hex = hex::encode(Sha256::digest(&bytes));
+        (bytes, hex)
+    }
+
+    /// Serves `bundles` from any path containing their key (longest key wins), after `delay`.
+    /// Signature paths only match `.sig` keys and are 404 otherwise.
+    async fn start_slow_bundle_server(
+        bundles: HashMap<String, Vec<u8>>,
+        delay: Duration,
+    ) -> (Url, Arc<std::sync::atomic::AtomicUsize>) {
+        use axum::{
+            extract::Path as AxPath, http::StatusCode, response::IntoResponse, routing::get, Router,
+        };
+        let hits = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = hits.clone();
+        let bundles = Arc::new(bundles);
+        let app = Router::new().route(
+            "/*key",
+            get(move |AxPath(key): AxPath<String>| {
+                let counter = counter.clone();
+                let bundles = bundles.clone();
+                async move {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    tokio::time::sleep(delay).await;
+                    let wants_sig = key.ends_with(".sig");
+                    match bundles
+                        .iter()
+                        .filter(|(needle, _)| key.contains(needle.as_str()))
+                        .filter(|(needle, _)| !wants_sig || needle.ends_with(".sig"))
+                        .max_by_key(|(needle, _)| needle.len())
+                    {
+                        Some((_, bytes)) => bytes.clone().into_response(),
+                        None if wants_sig => StatusCode::NOT_FOUND.into_response(),
+                        None => Vec::new().into_response(),
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (
+            Url::parse(&format!("http://{addr}/bundles/")).unwrap(),
+            hits,
+        )
+    }
+
+    #[tokio::test]
+    async fn different_bundles_extract_concurrently() {
+        let (a_bytes, a_hex) = make_bundle("bundle-a");
+        let (b_bytes, b_hex) = make_bundle("bundle-b");
+        let delay = Duration::from_millis(400);
+        let (base, hits) = start_slow_bundle_server(
+            HashMap::from([(a_hex.clone(), a_bytes), (b_hex.clone(), b_bytes)]),
+            delay,
+        )
+        .await;
+        let cache = tempfile::tempdir().unwrap();
+        let policy = open_policy();
+        let a_key = format!("sha256/{a_hex}/bundle.tar.zst");
+        let b_key = format!("sha256/{b_hex}/bundle.tar.zst");
+
+        let started = std::time::Instant::now();
+        let (a, b) = tokio::join!(
+            ensure_bundle_cached(&policy, &base, cache.path(), &a_key, &a_hex),
+            ensure_bundle_cached(&policy, &base, cache.path(), &b_key, &b_hex),
+        );
+        let elapsed = started.elapsed();
+
+        assert!(a.unwrap().ui_root.join("index.html").exists());
+        assert!(b.unwrap().ui_root.join("index.html").exists());
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(
+            elapsed < delay * 2,
+            "extractions of different hashes serialized: {elapsed:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn same_bundle_extracts_once_under_concurrency() {
+        let (bytes, hex) = make_bundle("bundle-same");
+        let (base, hits) = start_slow_bundle_server(
+            HashMap::from([(hex.clone(), bytes)]),
+            Duration::from_millis(200),
+        )
+        .await;
+        let cache = tempfile::tempdir().unwrap();
+        let policy = open_policy();
+        let key = format!("sha256/{hex}/bundle.tar.zst");
+
+        let (first, second) = tokio::join!(
+            ensure_bundle_cached(&policy, &base, cache.path(), &key, &hex),
+            ensure_bundle_cached(&policy, &base, cache.path(), &key, &hex),
+        );
+
+        assert_eq!(first.unwrap().ui_root, second.unwrap().ui_root);
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    struct FixedFreeSpace(u64);
+
+    impl crate::cache::space::FreeSpaceProbe for FixedFreeSpace {
+        fn available_bytes(&self, _path: &Path) -> std::io::Result<u64> {
+            Ok(self.0)
+        }
+    }
+
+    /// No free-space floor and no signature requirement.
+    fn open_policy() -> BundleCachePolicy {
+        BundleCachePolicy::new(CacheSpaceGuard::new(0, Box::new(FixedFreeSpace(0))), None)
+    }
+
+    #[tokio::test]
+    async fn low_disk_space_refuses_extraction_before_download() {
+        use crate::cache::space::CACHE_SPACE_METRICS;
+
+        let (bytes, hex) = make_bundle("bundle-low-space");
+        let (base, hits) =
+            start_slow_bundle_server(HashMap::from([(hex.clone(), bytes)]), Duration::ZERO).await;
+        let cache = tempfile::tempdir().unwrap();
+        let key = format!("sha256/{hex}/bundle.tar.zst");
+
+        let refused_before = CACHE_SPACE_METRICS.refused_extractions();
+        let starved = BundleCachePolicy::new(
+            CacheSpaceGuard::new(1024, Box::new(FixedFreeSpace(512))),
+            None,
+        );
+        let err = ensure_bundle_cached(&starved, &base, cache.path(), &key, &hex)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CacheSpaceError>(),
+            Some(CacheSpaceError::LowDiskSpace {
+                available_bytes: 512,
+                min_free_bytes: 1024,
+            })
+        ));
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert!(CACHE_SPACE_METRICS.refused_extractions() > refused_before);
+        assert!(!cache.path().join(&hex).exists());
+
+        let roomy = BundleCachePolicy::new(
+            CacheSpaceGuard::new(1024, Box::new(FixedFreeSpace(4096))),
+            None,
+        );
+        let paths = ensure_bundle_cached(&roomy, &base, cache.path(), &key, &hex)
+            .await
+            .unwrap();
+        assert!(paths.ui_root.join("index.html").exists());
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn bundles_need_a_signature_from_a_trusted_key() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let keypair = || SigningKey::from_bytes(&rand::random());
+        let (signer, stranger) = (keypair(), keypair());
+        let policy = BundleCachePolicy::new(
+            CacheSpaceGuard::new(0, Box::new(FixedFreeSpace(0))),
+            Some(TrustedKeys::new(vec![
+                crate::signing::PublicKey::from_bytes(signer.verifying_key().as_bytes()).unwrap(),
+            ])),
+        );
+
+        let (signed_bytes, signed_hex) = make_bundle("bundle-signed");
+        let (forged_bytes, forged_hex) = make_bundle("bundle-forged");
+        let (unsigned_bytes, unsigned_hex) = make_bundle("bundle-unsigned");
+        let signed_sig = signer.sign(format!("sha256:{signed_hex}").as_bytes());
+        let forged_sig = stranger.sign(format!("sha256:{forged_hex}").as_bytes());
+        let (base, hits) = start_slow_bundle_server(
+            HashMap::from([
+                (signed_hex.clone(), signed_bytes),
+                (
+                    format!("{signed_hex}/bundle.sig"),
+                    signed_sig.to_bytes().to_vec(),
+                ),
+                (forged_hex.clone(), forged_bytes),
+                (
+                    format!("{forged_hex}/bundle.sig"),
+                    forged_sig.to_bytes().to_vec(),
+                ),
+                (unsigned_hex.clone(), unsigned_bytes),
+            ]),
+            Duration::ZERO,
+        )
+        .await;
+        let cache = tempfile::tempdir().unwrap();
+
+        let key = format!("sha256/{signed_hex}/bundle.tar.zst");
+        let paths = ensure_bundle_cached(&policy, &base, cache.path(), &key, &signed_hex)
+            .await
+            .unwrap();
+        assert!(paths.ui_root.join("index.html").exists());
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let key = format!("sha256/{forged_hex}/bundle.tar.zst");
+        let err = ensure_bundle_cached(&policy, &base, cache.path(), &key, &forged_hex)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SigningError>(),
+            Some(SigningError::Rejected)
+        ));
+        // Only the signature was fetched; the archive was never downloaded or extracted.
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert!(!cache.path().join(&forged_hex).exists());
+
+        let key = format!("sha256/{unsigned_hex}/bundle.tar.zst");
+        let err = ensure_bundle_cached(&policy, &base, cache.path(), &key, &unsigned_hex)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SigningError>(),
+            Some(SigningError::Missing)
+        ));
+        assert!(!cache.path().join(&unsigned_hex).exists());
+    }
+
+    #[tokio::test]
+    async fn stale_ready_marker_triggers_reextract() {
+        let (bytes, hex) = make_bundle("bundle-heal");
+        let (base, hits) =
+            start_slow_bundle_server(HashMap::from([(hex.clone(), bytes)]), Duration::ZERO).await;
+        let cache = tempfile::tempdir().unwrap();
+        let policy = open_policy();
+        let key = format!("sha256/{hex}/bundle.tar.zst");
+
+        // Marker without any extracted files (e.g. legacy marker after manual cleanup).
+        let bundle_root = cache.path().join(&hex).join("bundle");
+        std::fs::create_dir_all(&bundle_root).unwrap();
+        std::fs::write(bundle_root.join(".ready"), b"ok").unwrap();
+
+        let paths = ensure_bundle_cached(&policy, &base, cache.path(), &key, &hex)
+            .await
+            .unwrap();
+        assert!(paths.ui_root.join("index.html").exists());
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Intact cache is served without refetching.
+        ensure_bundle_cached(&policy, &base, cache.path(), &key, &hex)
+            .await
+            .unwrap();
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Once verified, cache hits trust the marker instead of re-statting every file.
+        std::fs::remove_file(paths.ui_root.join("index.html")).unwrap();
+        ensure_bundle_cached(&policy, &base, cache.path(), &key, &hex)
+            .await
+            .unwrap();
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // After a failed open, a recorded file disappearing invalidates the marker.
+        forget_verified_bundle(&cache.path().join(&hex));
+        ensure_bundle_cached(&policy, &base, cache.path(), &key, &hex)
+            .await
+            .unwrap();
+        assert!(paths.ui_root.join("index.html").exists());
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
 }
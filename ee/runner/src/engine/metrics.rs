@@ -0,0 +1,302 @@
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Default cap on distinct series across all extensions.
+pub const DEFAULT_MAX_SERIES: usize = 10_000;
+/// Default cap on distinct series a single tenant/extension pair may create.
+pub const DEFAULT_MAX_SERIES_PER_EXTENSION: usize = 200;
+const MAX_TAGS: usize = 8;
+const MAX_NAME_LEN: usize = 64;
+const MAX_TAG_VALUE_LEN: usize = 128;
+const METRIC_PREFIX: &str = "alga_ext_";
+
+/// Process-wide registry backing the `metrics` host interface and `/metrics`. Starts with the
+/// default limits; the server applies the configured ones with [`MetricsRegistry::set_limits`].
+pub static EXT_METRICS: Lazy<MetricsRegistry> =
+    Lazy::new(|| MetricsRegistry::new(DEFAULT_MAX_SERIES, DEFAULT_MAX_SERIES_PER_EXTENSION));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MetricKind {
+    Counter,
+    Timing,
+}
+
+/// Why a guest metric was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricRejection {
+    /// Name or tags are not valid Prometheus identifiers, or too many tags.
+    InvalidMetric,
+    /// Recording would create a series beyond the cardinality budget.
+    LimitExceeded,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    name: String,
+    kind: MetricKind,
+    tenant: String,
+    extension: String,
+    tags: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct SeriesValue {
+    count: u64,
+    sum: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    series: BTreeMap<SeriesKey, SeriesValue>,
+    per_extension: HashMap<(String, String), usize>,
+}
+
+/// Aggregates counters and timings per tenant/extension with bounded cardinality.
+pub struct MetricsRegistry {
+    max_series: AtomicUsize,
+    max_series_per_extension: AtomicUsize,
+    inner: Mutex<Inner>,
+}
+
+impl MetricsRegistry {
+    pub fn new(max_series: usize, max_series_per_extension: usize) -> Self {
+        Self {
+            max_series: AtomicUsize::new(max_series),
+            max_series_per_extension: AtomicUsize::new(max_series_per_extension),
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Replace the cardinality limits (`EXT_METRICS_MAX_SERIES`,
+    /// `EXT_METRICS_MAX_SERIES_PER_EXTENSION`); series already recorded are kept.
+    pub fn set_limits(&self, max_series: usize, max_series_per_extension: usize) {
+        self.max_series.store(max_series, Ordering::Relaxed);
+        self.max_series_per_extension
+            .store(max_series_per_extension, Ordering::Relaxed);
+    }
+
+    /// Add `value` to a counter, or one observation of `value` ms to a timing.
+    pub fn record(
+        &self,
+        kind: MetricKind,
+        tenant: &str,
+        extension: &str,
+        name: &str,
+        value: u64,
+        tags: &[(String, String)],
+    ) -> Result<(), MetricRejection> {
+        let name = normalize_identifier(name).ok_or(MetricRejection::InvalidMetric)?;
+        if tags.len() > MAX_TAGS {
+            return Err(MetricRejection::InvalidMetric);
+        }
+        let mut normalized_tags = Vec::with_capacity(tags.len());
+        for (key, val) in tags {
+            let key = normalize_identifier(key).ok_or(MetricRejection::InvalidMetric)?;
+            if key == "tenant" || key == "extension" || key.starts_with("__") {
+                return Err(MetricRejection::InvalidMetric);
+            }
+            normalized_tags.push((key, val.chars().take(MAX_TAG_VALUE_LEN).collect()));
+        }
+        normalized_tags.sort();
+        normalized_tags.dedup_by(|a, b| a.0 == b.0);
+
+        let key = SeriesKey {
+            name,
+            kind,
+            tenant: tenant.to_string(),
+            extension: extension.to_string(),
+            tags: normalized_tags,
+        };
+
+        let mut inner = self.inner.lock().unwrap_or_else(|p| p.into_inner());
+        if !inner.series.contains_key(&key) {
+            let owner = (key.tenant.clone(), key.extension.clone());
+            let owned = inner.per_extension.get(&owner).copied().unwrap_or(0);
+            if inner.series.len() >= self.max_series.load(Ordering::Relaxed)
+                || owned >= self.max_series_per_extension.load(Ordering::Relaxed)
+            {
+                return Err(MetricRejection::LimitExceeded);
+            }
+            inner.per_extension.insert(owner, owned + 1);
+        }
+        let entry = inner.series.entry(key).or_default();
+        match kind {
+            MetricKind::Counter => entry.sum = entry.sum.saturating_add(value),
+            MetricKind::Timing => {
+                entry.count = entry.count.saturating_add(1);
+                entry.sum = entry.sum.saturating_add(value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prometheus text exposition of every series.
+    pub fn render(&self) -> String {
+        let inner = self.inner.lock().unwrap_or_else(|p| p.into_inner());
+        let mut out = String::new();
+        let mut family: Option<(&str, MetricKind)> = None;
+        for (key, value) in &inner.series {
+            if family != Some((key.name.as_str(), key.kind)) {
+                family = Some((key.name.as_str(), key.kind));
+                let _ = match key.kind {
+                    MetricKind::Counter => {
+                        writeln!(out, "# TYPE {METRIC_PREFIX}{}_total counter", key.name)
+                    }
+                    MetricKind::Timing => {
+                        writeln!(out, "# TYPE {METRIC_PREFIX}{}_ms summary", key.name)
+                    }
+                };
+            }
+            let labels = render_labels(key);
+            let _ = match key.kind {
+                MetricKind::Counter => writeln!(
+                    out,
+                    "{METRIC_PREFIX}{}_total{labels} {}",
+                    key.name, value.sum
+                ),
+                MetricKind::Timing => writeln!(
+                    out,
+                    "{METRIC_PREFIX}{name}_ms_count{labels} {}\n{METRIC_PREFIX}{name}_ms_sum{labels} {}",
+                    value.count,
+                    value.sum,
+                    name = key.name
+                ),
+            };
+        }
+        out
+    }
+}
+
+/// Map `.`/`-` to `_` and require a Prometheus-safe identifier.
+fn normalize_identifier(raw: &str) -> Option<String> {
+    let name: String = raw
+        .trim()
+        .chars()
+        .map(|c| if c == '.' || c == '-' { '_' } else { c })
+        .collect();
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    let valid = name.len() <= MAX_NAME_LEN
+        && (first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(name)
+}
+
+fn render_labels(key: &SeriesKey) -> String {
+    let mut labels = format!(
+        "{{tenant=\"{}\",extension=\"{}\"",
+        escape_label(&key.tenant),
+        escape_label(&key.extension)
+    );
+    for (k, v) in &key.tags {
+        let _ = write!(labels, ",{}=\"{}\"", k, escape_label(v));
+    }
+    labels.push('}');
+    labels
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(k: &str, v: &str) -> (String, String) {
+        (k.to_string(), v.to_string())
+    }
+
+    #[test]
+    fn aggregates_and_renders_per_extension() {
+        let registry = MetricsRegistry::new(100, 10);
+        let tags = [tag("route", "/sync")];
+        registry
+            .record(MetricKind::Counter, "t1", "e1", "jobs.run", 2, &tags)
+            .unwrap();
+        registry
+            .record(MetricKind::Counter, "t1", "e1", "jobs.run", 3, &tags)
+            .unwrap();
+        registry
+            .record(MetricKind::Timing, "t1", "e1", "sync", 40, &[])
+            .unwrap();
+        registry
+            .record(MetricKind::Timing, "t1", "e1", "sync", 60, &[])
+            .unwrap();
+
+        let text = registry.render();
+        assert!(text.contains("# TYPE alga_ext_jobs_run_total counter"));
+        assert!(text
+            .contains("alga_ext_jobs_run_total{tenant=\"t1\",extension=\"e1\",route=\"/sync\"} 5"));
+        assert!(text.contains("alga_ext_sync_ms_count{tenant=\"t1\",extension=\"e1\"} 2"));
+        assert!(text.contains("alga_ext_sync_ms_sum{tenant=\"t1\",extension=\"e1\"} 100"));
+    }
+
+    #[test]
+    fn bounds_cardinality_and_rejects_bad_identifiers() {
+        let registry = MetricsRegistry::new(3, 2);
+        for i in 0..2 {
+            registry
+                .record(
+                    MetricKind::Counter,
+                    "t1",
+                    "e1",
+                    "hits",
+                    1,
+                    &[tag("id", &i.to_string())],
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            registry.record(
+                MetricKind::Counter,
+                "t1",
+                "e1",
+                "hits",
+                1,
+                &[tag("id", "2")]
+            ),
+            Err(MetricRejection::LimitExceeded)
+        );
+        // Existing series keep updating once the budget is spent.
+        assert!(registry
+            .record(
+                MetricKind::Counter,
+                "t1",
+                "e1",
+                "hits",
+                1,
+                &[tag("id", "0")]
+            )
+            .is_ok());
+        registry
+            .record(MetricKind::Counter, "t2", "e1", "hits", 1, &[])
+            .unwrap();
+        assert_eq!(
+            registry.record(MetricKind::Counter, "t3", "e1", "hits", 1, &[]),
+            Err(MetricRejection::LimitExceeded)
+        );
+
+        assert_eq!(
+            registry.record(MetricKind::Counter, "t1", "e1", "9lives", 1, &[]),
+            Err(MetricRejection::InvalidMetric)
+        );
+        assert_eq!(
+            registry.record(
+                MetricKind::Counter,
+                "t1",
+                "e1",
+                "ok",
+                1,
+                &[tag("tenant", "x")]
+            ),
+            Err(MetricRejection::InvalidMetric)
+        );
+    }
+}
@@ -1,6 +1,9 @@
 pub mod component;
 pub mod debug;
 pub mod debug_redis;
+pub mod egress;
 pub mod host_api;
+pub mod http_upload;
 pub mod loader;
+pub mod metrics;
 pub mod stderr_pipe;
@@ -1,29 +1,35 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
 
 use axum::{
     extract::{Path as AxPath, Query, State},
     http::{header, HeaderMap, HeaderValue, StatusCode},
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Json,
 };
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 use std::io::ErrorKind;
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio::sync::RwLock;
 use tracing::info;
 use url::Url;
 
 use crate::cache::fs as cache_fs;
-use crate::engine::loader::ensure_bundle_cached;
+use crate::engine::loader::{
+    bundle_records_file, ensure_bundle_cached, forget_verified_bundle, BundleCachePolicy,
+};
 use crate::registry::client::RegistryClient;
 use crate::util::{
-    errors::IntegrityError, etag::etag_for_file, mime::content_type_for, path_sanitize,
+    errors::{BundleFetchError, CacheSpaceError, IntegrityError, SigningError},
+    etag::{etag_for_asset, etag_match},
+    mime::content_type_for,
+    path_sanitize,
+    range::{parse_byte_range, ByteRange},
 };
-use reqwest::Client as HttpClient;
 
 static TENANT_HINTS: Lazy<RwLock<HashMap<(String, String), String>>> =
     Lazy::new(|| RwLock::new(HashMap::new()));
@@ -35,6 +41,10 @@ pub struct AppState {
     pub cache_root: PathBuf,
     pub bundle_store_base: Url,
     pub max_file_bytes: Option<u64>,
+    /// Validate installs against the registry (`EXT_STATIC_STRICT_VALIDATION`).
+    pub strict_validation: bool,
+    /// Shared with the execute loader so both admit bundles under the same checks.
+    pub cache_policy: Arc<BundleCachePolicy>,
 }
 
 #[derive(Deserialize)]
@@ -93,13 +103,18 @@ pub async fn handle_get(
         }
     };
 
+    // Sanitize the requested path before any registry or cache work; empty means root (index.html).
+    // The path extractor has already percent-decoded, so encoded `..`, `/` and NUL are caught here too.
+    let sanitized = match path_sanitize::sanitize(&path_tail) {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::warn!(request_id=%req_id, extension=%extension_id, path=%path_tail.escape_debug(), err=%e.to_string(), "ext_ui rejected request path");
+            return (StatusCode::BAD_REQUEST, "invalid path").into_response();
+        }
+    };
+
     // Strict validation behavior can be relaxed via EXT_STATIC_STRICT_VALIDATION=false
-    let strict = std::env::var("EXT_STATIC_STRICT_VALIDATION")
-        .map(|v| {
-            let lower = v.to_ascii_lowercase();
-            matches!(lower.as_str(), "1" | "true" | "yes")
-        })
-        .unwrap_or(true);
+    let strict = state.strict_validation;
 
     // Resolve tenant via header or registry host lookup
     let mut tenant = query.tenant.clone().unwrap_or_else(|| tenant_id.clone());
@@ -114,46 +129,16 @@ pub async fn handle_get(
         }
     }
     if tenant.is_empty() {
-        if let Ok(base) = std::env::var("REGISTRY_BASE_URL") {
-            if let Ok(mut u) = Url::parse(&base) {
-                u.set_path("api/installs/lookup-by-host");
-                let now_ms = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .map(|d| d.as_millis())
-                    .unwrap_or(0);
-                u.query_pairs_mut()
-                    .append_pair("host", host.split(':').next().unwrap_or(""))
-                    .append_pair("ts", &now_ms.to_string());
-                if let Ok(http) = HttpClient::builder().build() {
-                    let mut rb = http.get(u.clone());
-                    if let Ok(k) = std::env::var("ALGA_AUTH_KEY") {
-                        if !k.is_empty() {
-                            rb = rb.header("x-api-key", k);
-                        }
-                    }
-                    if let Ok(resp) = rb.send().await {
-                        let code = resp.status().as_u16();
-                        if code >= 200 && code < 300 {
-                            match resp.text().await {
-                                Ok(txt) => {
-                                    tracing::info!(host=%host, status=%code, body_len=%txt.len(), body_sample=%txt.chars().take(200).collect::<String>(), "fallback lookup body");
-                                    if let Ok(json) =
-                                        serde_json::from_str::<serde_json::Value>(&txt)
-                                    {
-                                        if let Some(t) =
-                                            json.get("tenant_id").and_then(|v| v.as_str())
-                                        {
-                                            tenant = t.to_string();
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::warn!(err=%e.to_string(), "failed to read fallback lookup body");
-                                }
-                            }
-                        }
-                    }
-                }
+        match state.registry.lookup_by_host(&host).await {
+            Ok(Some(lookup)) => {
+                tracing::info!(host=%host, tenant=%lookup.tenant_id, "fallback lookup resolved tenant");
+                tenant = lookup.tenant_id;
+            }
+            Ok(None) => {
+                tracing::info!(host=%host, "fallback lookup found no install for host");
+            }
+            Err(e) => {
+                tracing::warn!(host=%host, err=%e.to_string(), "fallback lookup failed");
             }
         }
     }
@@ -191,61 +176,26 @@ pub async fn handle_get(
         tracing::info!(request_id=%req_id, extension=%extension_id, "strict validation disabled; skipping registry check");
     }
 
-    // Ensure UI cache is present (first touch may download+extract)
-    let exists = cache_fs::exists_ui_index(&state.cache_root, &hash_hex);
-    tracing::info!(request_id=%req_id, hash=%hash_hex, cache_index_exists=%exists, "ui cache check");
-    if !exists {
-        tracing::info!(request_id=%req_id, hash=%hash_hex, "ui cache ensure start");
-        let obj_key = format!(
-            "tenants/{}/extensions/{}/sha256/{}/bundle.tar.zst",
-            tenant, extension_id, hash_hex
-        );
-        if let Err(e) = ensure_bundle_cached(
-            &state.bundle_store_base,
-            &state.cache_root,
-            &obj_key,
-            &hash_hex,
-        )
-        .await
+    // Ensure UI cache is present (first touch may download+extract); a bundle this process
+    // already verified is trusted without touching the store
+    if let Err(resp) = ensure_ui_cache(&state, req_id, &tenant, &extension_id, &hash_hex).await {
+        return resp;
+    }
+
+    let hash_root = state.cache_root.join(&hash_hex);
+    if missing_from_cache(&hash_root, &Path::new("ui").join(&sanitized)).await
+        || missing_from_cache(&hash_root, Path::new("ui/index.html")).await
+    {
+        // The bundle lists a file that is gone from disk (e.g. the cache was pruned); re-check
+        // the manifest and re-extract, as the execute path does
+        tracing::warn!(request_id=%req_id, hash=%hash_hex, path=%sanitized.to_string_lossy(), "ui asset missing from bundle cache; re-checking bundle");
+        forget_verified_bundle(&hash_root);
+        if let Err(resp) = ensure_ui_cache(&state, req_id, &tenant, &extension_id, &hash_hex).await
         {
-            if let Some(IntegrityError::ArchiveHashMismatch {
-                expected_hex,
-                computed_hex,
-            }) = e.downcast_ref::<IntegrityError>()
-            {
-                tracing::error!(
-                    request_id=%req_id,
-                    tenant=%tenant_id,
-                    extension=%extension_id,
-                    expected_hash=%expected_hex,
-                    computed_hash=%computed_hex,
-                    integrity_archive_sha256_ok=%false,
-                    "archive hash mismatch on fetch"
-                );
-                let body = Json(serde_json::json!({ "code": "archive_hash_mismatch" }));
-                return (StatusCode::BAD_GATEWAY, body).into_response();
-            }
-            tracing::error!(
-                request_id=%req_id,
-                tenant=%tenant_id,
-                extension=%extension_id,
-                hash=%hash_hex,
-                err=%e.to_string(),
-                integrity_archive_sha256_ok=%true,
-                "ui cache ensure failed"
-            );
-            let body = Json(serde_json::json!({ "code": "extract_failed" }));
-            return (StatusCode::INTERNAL_SERVER_ERROR, body).into_response();
+            return resp;
         }
-        tracing::info!(request_id=%req_id, hash=%hash_hex, "ui cache ensure ok");
     }
 
-    // Sanitize the requested path; empty means root (index.html)
-    let sanitized = match path_sanitize::sanitize(&path_tail) {
-        Ok(p) => p,
-        Err(_) => return (StatusCode::BAD_REQUEST, "invalid path").into_response(),
-    };
-
     let ui_root = cache_fs::ui_cache_dir(&state.cache_root, &hash_hex);
     let mut file_path = ui_root.join(&sanitized);
 
@@ -268,14 +218,38 @@ pub async fn handle_get(
         file_path = ui_root.join("index.html");
     }
 
+    // Resolve symlinks and confirm the file is still inside this bundle's UI root
+    let (ui_root, file_path) = match (
+        fs::canonicalize(&ui_root).await,
+        fs::canonicalize(&file_path).await,
+    ) {
+        (Ok(root), Ok(file)) if file.starts_with(&root) => (root, file),
+        (Ok(_), Ok(file)) => {
+            tracing::warn!(request_id=%req_id, extension=%extension_id, file_path=%file.to_string_lossy(), "ext_ui resolved path escapes bundle root");
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+
     // Existence + max size enforcement
-    match fs::metadata(&file_path).await {
+    let file_len = match fs::metadata(&file_path).await {
         Ok(meta) => {
+            // Checked from metadata so oversized files are never buffered
             if let Some(max) = state.max_file_bytes {
                 if meta.len() > max {
+                    tracing::warn!(
+                        request_id=%req_id,
+                        extension=%extension_id,
+                        content_hash=%content_hash,
+                        file_path=%file_path.to_string_lossy(),
+                        file_bytes=meta.len(),
+                        max_file_bytes=max,
+                        "ext_ui asset exceeds EXT_STATIC_MAX_FILE_BYTES"
+                    );
                     return (StatusCode::PAYLOAD_TOO_LARGE, "asset too large").into_response();
                 }
             }
+            meta.len()
         }
         Err(e) => {
             return match e.kind() {
@@ -283,13 +257,13 @@ pub async fn handle_get(
                 _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
             };
         }
-    }
-
-    // Compute ETag and handle If-None-Match
-    let etag = match etag_for_file(&file_path).await {
-        Ok(s) => s,
-        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     };
+
+    // ETag is derived from the bundle hash and the served path; handle If-None-Match
+    let etag = etag_for_asset(
+        &hash_hex,
+        file_path.strip_prefix(&ui_root).unwrap_or(&file_path),
+    );
     if let Some(inm) = headers
         .get(header::IF_NONE_MATCH)
         .and_then(|v| v.to_str().ok())
@@ -309,8 +283,25 @@ pub async fn handle_get(
         }
     }
 
+    // Single-range requests get 206; anything we can't honor falls back to the whole file
+    let range = parse_byte_range(
+        headers.get(header::RANGE).and_then(|v| v.to_str().ok()),
+        file_len,
+    );
+    if range == ByteRange::Unsatisfiable {
+        let mut h = HeaderMap::new();
+        if let Ok(v) = HeaderValue::from_str(&format!("bytes */{}", file_len)) {
+            h.insert(header::CONTENT_RANGE, v);
+        }
+        return (StatusCode::RANGE_NOT_SATISFIABLE, h).into_response();
+    }
+
     // Read file bytes and respond (using streaming would be better later)
-    let data = match fs::read(&file_path).await {
+    let data = match range {
+        ByteRange::Partial { start, end } => read_file_range(&file_path, start, end).await,
+        _ => fs::read(&file_path).await,
+    };
+    let data = match data {
         Ok(b) => b,
         Err(_) => return StatusCode::NOT_FOUND.into_response(),
     };
@@ -321,6 +312,16 @@ pub async fn handle_get(
     if let Ok(len) = HeaderValue::from_str(&data.len().to_string()) {
         h.insert(header::CONTENT_LENGTH, len);
     }
+    h.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    let status = match range {
+        ByteRange::Partial { start, end } => {
+            if let Ok(v) = HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, file_len)) {
+                h.insert(header::CONTENT_RANGE, v);
+            }
+            StatusCode::PARTIAL_CONTENT
+        }
+        _ => StatusCode::OK,
+    };
     h.insert(
         header::ETAG,
         HeaderValue::from_str(&etag).unwrap_or(HeaderValue::from_static("")),
@@ -339,14 +340,125 @@ pub async fn handle_get(
         extension=%extension_id,
         content_hash=%content_hash,
         file_path=%file_path.to_string_lossy(),
-        status=200,
+        status=status.as_u16(),
         duration_ms=%dur_ms,
         bytes=body_len,
         cache_status=%(if use_index { "spa-fallback" } else { "hit" }),
         "ext_ui serve"
     );
 
-    (h, data).into_response()
+    (status, h, data).into_response()
+}
+
+/// Download, verify and extract the bundle unless it is already cached, mapping failures to
+/// the responses `handle_get` returns.
+async fn ensure_ui_cache(
+    state: &AppState,
+    req_id: &str,
+    tenant: &str,
+    extension_id: &str,
+    hash_hex: &str,
+) -> Result<(), Response> {
+    let obj_key = format!(
+        "tenants/{}/extensions/{}/sha256/{}/bundle.tar.zst",
+        tenant, extension_id, hash_hex
+    );
+    if let Err(e) = ensure_bundle_cached(
+        &state.cache_policy,
+        &state.bundle_store_base,
+        &state.cache_root,
+        &obj_key,
+        hash_hex,
+    )
+    .await
+    {
+        if let Some(IntegrityError::ArchiveHashMismatch {
+            expected_hex,
+            computed_hex,
+        }) = e.downcast_ref::<IntegrityError>()
+        {
+            tracing::error!(
+                request_id=%req_id,
+                tenant=%tenant,
+                extension=%extension_id,
+                expected_hash=%expected_hex,
+                computed_hash=%computed_hex,
+                integrity_archive_sha256_ok=%false,
+                "archive hash mismatch on fetch"
+            );
+            let body = Json(serde_json::json!({ "code": "archive_hash_mismatch" }));
+            return Err((StatusCode::BAD_GATEWAY, body).into_response());
+        }
+        if let Some(BundleFetchError::Timeout { timeout_ms }) = e.downcast_ref::<BundleFetchError>()
+        {
+            tracing::error!(
+                request_id=%req_id,
+                tenant=%tenant,
+                extension=%extension_id,
+                hash=%hash_hex,
+                timeout_ms=%timeout_ms,
+                "bundle fetch timed out"
+            );
+            let body = Json(serde_json::json!({ "code": "bundle_fetch_timeout" }));
+            return Err((StatusCode::GATEWAY_TIMEOUT, body).into_response());
+        }
+        if let Some(CacheSpaceError::LowDiskSpace {
+            available_bytes,
+            min_free_bytes,
+        }) = e.downcast_ref::<CacheSpaceError>()
+        {
+            tracing::error!(
+                request_id=%req_id,
+                tenant=%tenant,
+                extension=%extension_id,
+                hash=%hash_hex,
+                available_bytes=%available_bytes,
+                min_free_bytes=%min_free_bytes,
+                "ui cache extraction refused: low disk space"
+            );
+            let body = Json(serde_json::json!({ "code": "cache_disk_full" }));
+            return Err((StatusCode::SERVICE_UNAVAILABLE, body).into_response());
+        }
+        if let Some(err) = e.downcast_ref::<SigningError>() {
+            tracing::error!(
+                request_id=%req_id,
+                tenant=%tenant,
+                extension=%extension_id,
+                hash=%hash_hex,
+                err=%err,
+                "bundle signature verification failed"
+            );
+            let body = Json(serde_json::json!({ "code": "signature_invalid" }));
+            return Err((StatusCode::BAD_GATEWAY, body).into_response());
+        }
+        tracing::error!(
+            request_id=%req_id,
+            tenant=%tenant,
+            extension=%extension_id,
+            hash=%hash_hex,
+            err=%e.to_string(),
+            integrity_archive_sha256_ok=%true,
+            "ui cache ensure failed"
+        );
+        let body = Json(serde_json::json!({ "code": "extract_failed" }));
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, body).into_response());
+    }
+    Ok(())
+}
+
+/// True when `rel` (relative to the hash directory) is recorded in the bundle's `.ready`
+/// manifest but no longer on disk.
+async fn missing_from_cache(hash_root: &Path, rel: &Path) -> bool {
+    fs::metadata(hash_root.join(rel)).await.is_err() && bundle_records_file(hash_root, rel).await
+}
+
+/// Read the inclusive byte span `[start, end]` of a file.
+async fn read_file_range(path: &std::path::Path, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path).await?;
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+    let mut buf = vec![0u8; (end - start + 1) as usize];
+    file.read_exact(&mut buf).await?;
+    Ok(buf)
 }
 
 /// POST /warmup { content_hash }
@@ -370,11 +482,3 @@ fn normalize_hash(content_hash: &str) -> anyhow::Result<String> {
     }
     Ok(hex.to_lowercase())
 }
-
-fn etag_match(inm_header: &str, etag: &str) -> bool {
-    // Simple exact match against a possible list (comma separated ETags)
-    inm_header
-        .split(',')
-        .map(|s| s.trim())
-        .any(|candidate| candidate == "*" || candidate == etag)
-}
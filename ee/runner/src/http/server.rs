@@ -5,20 +5,28 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use once_cell::sync::Lazy;
 use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::Mutex;
 use tower_http::{set_header::SetResponseHeaderLayer, trace::TraceLayer};
+use tracing::Instrument;
 use url::{form_urlencoded, Url};
 
-use crate::cache::fs as cache_fs;
+use crate::config::RunnerConfig;
 use crate::engine::debug;
+use crate::engine::host_api::normalize_allowlist;
 use crate::engine::loader::{HostExecutionContext, ModuleLoader};
 use crate::models::{ExecuteRequest, ExecuteResponse};
 use crate::providers;
-use crate::registry::client::HttpRegistryClient;
+use crate::registry::client::{HttpRegistryClient, RegistryClient};
+use crate::util::errors::{
+    BundleFetchError, CacheSpaceError, InstancePoolError, RegistryLookupError,
+};
+use crate::util::limits::{self, LimitPolicyMode};
+use crate::util::request_id;
 
 // Idempotency cache
 type IdemMap = Arc<Mutex<HashMap<String, crate::models::ExecuteResponse>>>;
@@ -27,6 +35,9 @@ type IdemMap = Arc<Mutex<HashMap<String, crate::models::ExecuteResponse>>>;
 #[derive(Clone)]
 struct CoreState {
     idempotency: IdemMap,
+    registry: Arc<dyn RegistryClient + Send + Sync>,
+    config: Arc<RunnerConfig>,
+    loader: Arc<ModuleLoader>,
 }
 
 // Root state containing both core and ext-ui states.
@@ -35,7 +46,6 @@ struct CoreState {
 struct RootState {
     core: CoreState,
     ext: crate::http::ext_ui::AppState,
-    api_key: Option<String>,
 }
 
 // Allow extracting CoreState from RootState
@@ -53,18 +63,11 @@ impl FromRef<RootState> for crate::http::ext_ui::AppState {
 }
 
 // Build and run the HTTP server
-pub async fn run() -> anyhow::Result<()> {
+pub async fn run(config: RunnerConfig) -> anyhow::Result<()> {
     tracing::info!("═══════════════════════════════════════════════════════");
     tracing::info!("HTTP Server Initialization");
     tracing::info!("═══════════════════════════════════════════════════════");
 
-    // Initialize core state
-    tracing::info!("Initializing core execution state...");
-    let core = CoreState {
-        idempotency: Arc::new(Mutex::new(HashMap::new())),
-    };
-    tracing::info!("✓ Core state initialized (idempotency cache ready)");
-
     // Load ALGA_AUTH_KEY from env or Vault at startup
     tracing::info!("Loading ALGA_AUTH_KEY from environment...");
     let api_key = crate::secrets::load_alga_auth_key().await;
@@ -80,31 +83,48 @@ pub async fn run() -> anyhow::Result<()> {
         ));
     }
     tracing::info!("✓ ALGA_AUTH_KEY loaded successfully");
-
-    // Validate required registry base URL exists and parses
-    tracing::info!("Validating REGISTRY_BASE_URL configuration...");
-    let reg_base = std::env::var("REGISTRY_BASE_URL")
-        .map_err(|_| anyhow::anyhow!("REGISTRY_BASE_URL not set"))?;
-    Url::parse(&reg_base).map_err(|e| anyhow::anyhow!("Invalid REGISTRY_BASE_URL: {}", e))?;
-    tracing::info!("✓ Registry base URL validated: {}", reg_base);
+    tracing::info!("✓ Registry base URL: {}", config.registry_base_url);
+    config.runtime.log_startup_warnings();
+    crate::util::redact::set_body_redaction(config.log_body_redaction);
+    crate::engine::metrics::EXT_METRICS.set_limits(
+        config.metrics_max_series,
+        config.metrics_max_series_per_extension,
+    );
 
     // Initialize registry client
     tracing::info!("Initializing registry client...");
-    let registry = Arc::new(HttpRegistryClient::new(api_key.clone())?);
+    let registry = Arc::new(HttpRegistryClient::from_config(&config, api_key.clone())?);
     tracing::info!("✓ Registry client initialized");
 
+    // One loader for the process so the instance pool and epoch clock are shared
+    tracing::info!("Initializing Wasmtime ModuleLoader...");
+    let loader = Arc::new(ModuleLoader::with_config(&config)?);
+    let cache_policy = loader.cache_policy();
+    tracing::info!("✓ ModuleLoader initialized");
+
+    // Initialize core state
+    tracing::info!("Initializing core execution state...");
+    let config = Arc::new(config);
+    let core = CoreState {
+        idempotency: Arc::new(Mutex::new(HashMap::new())),
+        registry: registry.clone(),
+        config: config.clone(),
+        loader,
+    };
+    tracing::info!(
+        require_approved_versions = config.require_approved_versions,
+        "✓ Core state initialized (idempotency cache ready)"
+    );
+
     // Initialize cache and bundle store
     tracing::info!("Initializing cache and bundle store configuration...");
-    let cache_root = cache_fs::ext_cache_root_from_env();
+    let cache_root = config.cache_root.clone();
     tracing::info!("  ✓ Cache root: {}", cache_root.display());
 
-    let bundle_store_base = Url::parse(
-        &std::env::var("BUNDLE_STORE_BASE")
-            .unwrap_or_else(|_| "http://localhost:9000/alga-ext/".into()),
-    )?;
+    let bundle_store_base = config.bundle_store_url();
     tracing::info!("  ✓ Bundle store base: {}", bundle_store_base);
 
-    let max_file_bytes = crate::util::limits::max_file_bytes_from_env();
+    let max_file_bytes = config.max_file_bytes;
     tracing::info!("  ✓ Max file bytes limit: {:?}", max_file_bytes);
 
     let ext = crate::http::ext_ui::AppState {
@@ -112,10 +132,12 @@ pub async fn run() -> anyhow::Result<()> {
         cache_root,
         bundle_store_base,
         max_file_bytes,
+        strict_validation: config.strict_validation,
+        cache_policy,
     };
     tracing::info!("✓ Extension UI state initialized");
 
-    let state = RootState { core, ext, api_key };
+    let state = RootState { core, ext };
     tracing::info!("✓ Root state assembled");
 
     // Build router with all routes
@@ -123,6 +145,8 @@ pub async fn run() -> anyhow::Result<()> {
     let app = Router::new()
         .route("/v1/execute", post(execute))
         .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics))
         .route("/", get(root_dispatch))
         .route(
             "/ext-ui/:extensionId/:contentHash/*path",
@@ -137,17 +161,15 @@ pub async fn run() -> anyhow::Result<()> {
         ));
     tracing::info!("✓ HTTP routes configured:");
     tracing::info!("  - POST /v1/execute (extension execution)");
-    tracing::info!("  - GET  /healthz (health check)");
+    tracing::info!("  - GET  /healthz (liveness)");
+    tracing::info!("  - GET  /readyz (readiness: cache + bundle store)");
+    tracing::info!("  - GET  /metrics (extension metrics, Prometheus text format)");
     tracing::info!("  - GET  / (root dispatcher)");
     tracing::info!("  - GET  /ext-ui/:extensionId/:contentHash/*path (UI file serving)");
     tracing::info!("  - POST /warmup (cache warmup)");
 
     // Configure server address
-    let port: u16 = std::env::var("PORT")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(8080);
-    let addr: SocketAddr = ([0, 0, 0, 0], port).into();
+    let addr: SocketAddr = ([0, 0, 0, 0], config.port).into();
     tracing::info!("═══════════════════════════════════════════════════════");
     tracing::info!("HTTP Server Ready");
     tracing::info!("  Address: {}", addr);
@@ -162,13 +184,17 @@ pub async fn run() -> anyhow::Result<()> {
 async fn execute(
     State(state): State<CoreState>,
     headers: HeaderMap,
-    Json(req): Json<ExecuteRequest>,
-) -> Json<ExecuteResponse> {
+    Json(mut req): Json<ExecuteRequest>,
+) -> Response {
     let started = Instant::now();
-    let req_id = headers
-        .get("x-request-id")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
+    // Every execution gets an id; it travels in the host context to logs and outbound calls.
+    let req_id = request_id::resolve(
+        headers
+            .get(request_id::REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok()),
+        req.context.request_id.as_deref(),
+    );
+    req.context.request_id = Some(req_id.clone());
 
     let idem = headers
         .get("x-idempotency-key")
@@ -183,6 +209,12 @@ async fn execute(
         .get("x-alga-extension")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
+    let user_id = headers
+        .get("x-alga-user-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string);
     tracing::info!(request_id=%req_id, idempotency=%idem, tenant=%tenant, extension=%ext, "execute start");
     tracing::info!(
         request_id = %req_id,
@@ -199,39 +231,137 @@ async fn execute(
             body_b64: None,
             error: Some("missing_routing_headers".to_string()),
         };
-        return Json(resp);
+        return Json(resp).into_response();
+    }
+
+    // The headers pick the install that is validated; the body context is what the guest sees.
+    // They must name the same install.
+    if req.context.tenant_id != tenant || req.context.extension_id != ext {
+        tracing::warn!(request_id=%req_id, tenant=%tenant, extension=%ext, context_tenant=%req.context.tenant_id, context_extension=%req.context.extension_id, "execute denied: routing headers disagree with request context");
+        return Json(ExecuteResponse {
+            status: 403,
+            headers: Default::default(),
+            body_b64: None,
+            error: Some("context_mismatch".to_string()),
+        })
+        .into_response();
+    }
+
+    // Routing headers are caller-supplied; only fetch the bundle once the registry confirms
+    // the content hash belongs to this tenant's install of the extension. This check follows
+    // EXT_STATIC_STRICT_VALIDATION: with it off the registry client accepts every install.
+    match state
+        .registry
+        .validate_install(tenant, ext, &req.context.content_hash)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::warn!(request_id=%req_id, tenant=%tenant, extension=%ext, content_hash=%req.context.content_hash, "execute denied: content hash not installed for tenant/extension");
+            return Json(ExecuteResponse {
+                status: 403,
+                headers: Default::default(),
+                body_b64: None,
+                error: Some("install_mismatch".to_string()),
+            })
+            .into_response();
+        }
+        Err(e) => {
+            tracing::error!(request_id=%req_id, tenant=%tenant, extension=%ext, err=%e.to_string(), "execute install validation failed");
+            return Json(ExecuteResponse {
+                status: 502,
+                headers: Default::default(),
+                body_b64: None,
+                error: Some("registry_unavailable".to_string()),
+            })
+            .into_response();
+        }
+    }
+
+    if state.config.require_approved_versions {
+        match state
+            .registry
+            .is_version_approved(tenant, ext, &req.context.content_hash)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!(request_id=%req_id, tenant=%tenant, extension=%ext, content_hash=%req.context.content_hash, "execute denied: content hash is not an approved version");
+                return Json(ExecuteResponse {
+                    status: 403,
+                    headers: Default::default(),
+                    body_b64: None,
+                    error: Some("version_not_approved".to_string()),
+                })
+                .into_response();
+            }
+            Err(e) => {
+                tracing::error!(request_id=%req_id, tenant=%tenant, extension=%ext, err=%e.to_string(), "execute approved version lookup failed");
+                return Json(ExecuteResponse {
+                    status: 502,
+                    headers: Default::default(),
+                    body_b64: None,
+                    error: Some("registry_unavailable".to_string()),
+                })
+                .into_response();
+            }
+        }
     }
 
     if !idem.is_empty() {
         let map = state.idempotency.lock().await;
         if let Some(prev) = map.get(&idem) {
-            return Json(prev.clone());
+            return Json(prev.clone()).into_response();
         }
     }
 
+    // Caller-supplied limits are capped by the install's resource policy, falling back to the
+    // runner-wide ceilings when the registry has none or cannot be reached.
+    let registry_policy = match state.registry.resource_policy(tenant, ext).await {
+        Ok(policy) => policy,
+        Err(e) => {
+            tracing::warn!(request_id=%req_id, tenant=%tenant, extension=%ext, err=%e.to_string(), "resource policy lookup failed; applying runner-wide ceilings");
+            None
+        }
+    };
+    let policy = limits::effective_policy(registry_policy, state.config.runner_policy);
+    let overages = limits::clamp_limits(
+        &mut req.limits,
+        &policy,
+        state.config.engine.default_memory_mb,
+        state.config.engine.default_timeout_ms,
+    );
+    for overage in &overages {
+        tracing::warn!(
+            request_id=%req_id,
+            tenant=%tenant,
+            extension=%ext,
+            field=overage.field,
+            requested=?overage.requested,
+            max=overage.max,
+            "execute limit exceeds resource policy"
+        );
+    }
+    // Only explicit requests are refused; unset limits just pick up the ceiling.
+    if state.config.limit_policy_mode == LimitPolicyMode::Reject
+        && overages.iter().any(|o| o.requested.is_some())
+    {
+        return Json(ExecuteResponse {
+            status: 422,
+            headers: Default::default(),
+            body_b64: None,
+            error: Some("limits_exceed_policy".to_string()),
+        })
+        .into_response();
+    }
+
     // Fetch wasm by content hash (scaffold)
     let content_hash = req.context.content_hash.clone();
     let hash = content_hash
         .strip_prefix("sha256:")
         .unwrap_or(&content_hash);
-    tracing::info!(request_id=%req_id, tenant=%tenant, extension=%ext, content_hash=%hash, "Initializing Wasmtime ModuleLoader for extension execution");
-    let loader = match ModuleLoader::new() {
-        Ok(l) => {
-            tracing::info!(request_id=%req_id, "✓ ModuleLoader initialized successfully");
-            l
-        }
-        Err(e) => {
-            tracing::error!(request_id=%req_id, err=%e.to_string(), "FAILED: ModuleLoader initialization failed");
-            tracing::error!(request_id=%req_id, "This error indicates Wasmtime engine configuration failure");
-            let resp = ExecuteResponse {
-                status: 500,
-                headers: Default::default(),
-                body_b64: None,
-                error: Some(format!("engine_init_failed: {}", e)),
-            };
-            return Json(resp);
-        }
-    };
+    tracing::info!(request_id=%req_id, tenant=%tenant, extension=%ext, content_hash=%hash, "Executing extension with the shared ModuleLoader");
+    let loader = &state.loader;
 
     const DEFAULT_WASM_ENTRY: &str = "dist/main.wasm";
     tracing::info!(request_id=%req_id, tenant=%tenant, extension=%ext, entry=DEFAULT_WASM_ENTRY, "Ensuring WASM binary is cached locally");
@@ -247,13 +377,7 @@ async fn execute(
         Err(e) => {
             tracing::error!(request_id=%req_id, tenant=%tenant, extension=%ext, err=%e.to_string(), "FAILED: WASM binary load failed");
             tracing::error!(request_id=%req_id, "This error indicates the extension bundle could not be retrieved or extracted");
-            let resp = ExecuteResponse {
-                status: 502,
-                headers: Default::default(),
-                body_b64: None,
-                error: Some(format!("bundle_fetch_failed: {}", e)),
-            };
-            return Json(resp);
+            return bundle_load_failure(&e);
         }
     };
 
@@ -276,7 +400,7 @@ async fn execute(
             body_b64: None,
             error: Some(format!("unknown_capabilities: {}", joined)),
         };
-        return Json(resp);
+        return Json(resp).into_response();
     }
 
     tracing::info!(
@@ -309,6 +433,8 @@ async fn execute(
                     providers: provider_set.clone(),
                     secrets: None,
                     user: req.user.clone(),
+                    user_id: user_id.clone(),
+                    egress_allowlist: None,
                 };
                 debug::emit_log(
                     &debug_ctx,
@@ -322,7 +448,7 @@ async fn execute(
                     body_b64: None,
                     error: Some("secret_decrypt_failed".to_string()),
                 };
-                return Json(resp);
+                return Json(resp).into_response();
             }
         },
         None => None,
@@ -338,6 +464,12 @@ async fn execute(
         providers: provider_set.clone(),
         secrets: secret_material,
         user: req.user.clone(),
+        user_id,
+        egress_allowlist: req
+            .context
+            .egress_allowlist
+            .as_deref()
+            .map(normalize_allowlist),
     };
 
     // Keep a copy of the execution context so we can emit internal failures to the debug stream
@@ -352,6 +484,12 @@ async fn execute(
             &req,
             host_ctx,
         )
+        .instrument(tracing::info_span!(
+            "execute",
+            request_id = %req_id,
+            tenant = %tenant,
+            extension = %ext
+        ))
         .await
     {
         Ok(v) => v,
@@ -378,13 +516,7 @@ async fn execute(
                 &format!("execute failed: {err_text}"),
             )
             .await;
-            let resp = ExecuteResponse {
-                status: 500,
-                headers: Default::default(),
-                body_b64: None,
-                error: Some(format!("execute_failed: {}", e)),
-            };
-            return Json(resp);
+            return execute_failure(&e);
         }
     };
 
@@ -403,14 +535,7 @@ async fn execute(
         map.insert(idem, resp.clone());
     }
 
-    Json(resp)
-}
-
-#[derive(serde::Deserialize)]
-struct LookupResp {
-    tenant_id: String,
-    extension_id: String,
-    content_hash: String,
+    Json(resp).into_response()
 }
 
 async fn root_dispatch(State(rstate): State<RootState>, headers: HeaderMap) -> Response {
@@ -429,109 +554,23 @@ async fn root_dispatch(State(rstate): State<RootState>, headers: HeaderMap) -> R
 
     tracing::info!(host=%host, "Host header validated - querying registry for extension");
 
-    // Build registry URL: {REGISTRY_BASE_URL}/api/installs/lookup-by-host?host=...
-    let base = match std::env::var("REGISTRY_BASE_URL") {
-        Ok(v) => {
-            tracing::info!(host=%host, REGISTRY_BASE_URL=%v, "Registry base URL loaded from environment");
-            v
-        }
-        Err(e) => {
-            tracing::error!(host=%host, err=%e.to_string(), "CRITICAL: REGISTRY_BASE_URL not set");
-            tracing::error!(host=%host, "Cannot perform extension lookup without registry base URL");
-            return StatusCode::SERVICE_UNAVAILABLE.into_response();
-        }
-    };
-
-    let mut url = match Url::parse(&base) {
-        Ok(u) => {
-            tracing::info!(host=%host, registry_base=%base, "Registry base URL parsed successfully");
-            u
-        }
-        Err(e) => {
-            tracing::error!(host=%host, base=%base, err=%e.to_string(), "REGISTRY_BASE_URL parse failed - invalid URL format");
-            return StatusCode::SERVICE_UNAVAILABLE.into_response();
-        }
-    };
-    url.set_path("api/installs/lookup-by-host");
-    let now_ms = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_millis())
-        .unwrap_or(0);
-    let clean_host = host.split(':').next().unwrap_or("");
-    url.query_pairs_mut()
-        .append_pair("host", clean_host)
-        .append_pair("ts", &now_ms.to_string());
-
-    tracing::info!(host=%host, lookup_url=%url.to_string(), "Registry lookup URL constructed");
-
-    let http = match reqwest::Client::builder().build() {
-        Ok(c) => {
-            tracing::info!(host=%host, "HTTP client initialized for registry communication");
-            c
-        }
-        Err(e) => {
-            tracing::error!(host=%host, err=%e.to_string(), "CRITICAL: Failed to build HTTP client for registry requests");
-            return StatusCode::SERVICE_UNAVAILABLE.into_response();
-        }
-    };
-
-    // Attach ALGA_AUTH_KEY if present for registry auth and log masked presence
-    let mut rb = http.get(url.clone());
-    match rstate.api_key.as_ref() {
-        Some(key) if !key.is_empty() => {
-            let prefix: String = key.chars().take(4).collect();
-            let len = key.len();
-            tracing::info!(host=%host, key_len=len, key_prefix=%prefix, "ALGA_AUTH_KEY present - attaching to registry request");
-            rb = rb.header("x-api-key", key);
-        }
-        _ => {
-            tracing::warn!(host=%host, "ALGA_AUTH_KEY not configured - registry request may be unauthorized");
-        }
-    }
-
-    tracing::info!(host=%host, lookup_url=%url.to_string(), "Sending extension lookup request to registry");
-    tracing::info!(host=%host, "Waiting for registry response (request timeout: default)");
-
-    let resp = match rb.send().await {
-        Ok(r) => {
-            tracing::info!(host=%host, status=%r.status().as_u16(), "Registry response received");
-            r
-        }
-        Err(e) => {
-            tracing::error!(host=%host, lookup_url=%url.to_string(), err=%e.to_string(), "FAILED: Registry request failed - network or server error");
-            tracing::error!(host=%host, "This indicates registry service is unavailable or unreachable");
-            return StatusCode::BAD_GATEWAY.into_response();
-        }
-    };
-
-    if !resp.status().is_success() {
-        let code = resp.status().as_u16();
-        let path = url.path().to_string();
-        tracing::warn!(host=%host, status=%code, url_path=%path, "Registry returned non-success status for host lookup");
-        tracing::warn!(host=%host, "No extension found for this host or extension is not available");
-        return StatusCode::NOT_FOUND.into_response();
-    }
-
-    tracing::info!(host=%host, status=%resp.status().as_u16(), "Registry lookup successful - parsing response body");
-    let text = match resp.text().await {
-        Ok(t) => {
-            tracing::info!(host=%host, response_bytes=%t.len(), "Registry response body received");
-            t
-        }
-        Err(e) => {
-            tracing::error!(host=%host, err=%e.to_string(), "FAILED: Could not read registry response body");
-            return StatusCode::BAD_GATEWAY.into_response();
-        }
-    };
-    tracing::info!(host=%host, body_len=%text.len(), body_sample=%text.chars().take(200).collect::<String>(), "Registry response body content");
-    let body: LookupResp = match serde_json::from_str::<LookupResp>(&text) {
-        Ok(b) => {
-            tracing::info!(host=%host, tenant_id=%b.tenant_id, extension_id=%b.extension_id, content_hash=%b.content_hash, "Registry response parsed successfully");
+    let body = match rstate.ext.registry.lookup_by_host(&host).await {
+        Ok(Some(b)) => {
+            tracing::info!(host=%host, tenant_id=%b.tenant_id, extension_id=%b.extension_id, content_hash=%b.content_hash, "Registry lookup resolved extension for host");
             b
         }
+        Ok(None) => {
+            tracing::warn!(host=%host, "No extension found for this host or extension is not available");
+            return StatusCode::NOT_FOUND.into_response();
+        }
         Err(e) => {
-            tracing::error!(host=%host, err=%e.to_string(), "FAILED: Could not parse registry response JSON");
-            tracing::error!(host=%host, "Registry response may be malformed or in unexpected format");
+            if let Some(RegistryLookupError::Timeout { timeout_ms }) =
+                e.downcast_ref::<RegistryLookupError>()
+            {
+                tracing::error!(host=%host, timeout_ms=%timeout_ms, "FAILED: Registry lookup timed out - returning 504 (see REGISTRY_LOOKUP_TIMEOUT_MS)");
+                return StatusCode::GATEWAY_TIMEOUT.into_response();
+            }
+            tracing::error!(host=%host, err=%e.to_string(), "FAILED: Registry lookup failed - network, server or response error");
             return StatusCode::BAD_GATEWAY.into_response();
         }
     };
@@ -560,16 +599,229 @@ async fn root_dispatch(State(rstate): State<RootState>, headers: HeaderMap) -> R
     Redirect::temporary(&target).into_response()
 }
 
-// Enhanced healthz: verify cache root writability and attempt lightweight HEAD to bundle store.
+/// Seconds clients should wait before retrying when the instance pool is full.
+const POOL_EXHAUSTED_RETRY_AFTER_SECS: u64 = 1;
+
+/// Map a bundle fetch/extract failure: a full cache disk is 503, a store that did not deliver
+/// within `BUNDLE_FETCH_TIMEOUT_MS` is 504, anything else is 502.
+fn bundle_load_failure(e: &anyhow::Error) -> Response {
+    let (status, code) = if e.downcast_ref::<CacheSpaceError>().is_some() {
+        (StatusCode::SERVICE_UNAVAILABLE, "cache_disk_full")
+    } else if e.downcast_ref::<BundleFetchError>().is_some() {
+        (StatusCode::GATEWAY_TIMEOUT, "bundle_fetch_timeout")
+    } else {
+        let resp = ExecuteResponse {
+            status: 502,
+            headers: Default::default(),
+            body_b64: None,
+            error: Some(format!("bundle_fetch_failed: {}", e)),
+        };
+        return Json(resp).into_response();
+    };
+    let resp = ExecuteResponse {
+        status: status.as_u16(),
+        headers: Default::default(),
+        body_b64: None,
+        error: Some(format!("{code}: {e}")),
+    };
+    (status, Json(resp)).into_response()
+}
+
+/// Failed executions keep the JSON envelope; pool exhaustion also sets the HTTP status and
+/// `Retry-After` so the gateway and clients back off.
+fn execute_failure(e: &anyhow::Error) -> Response {
+    let resp = execute_failure_response(e);
+    if resp.status != StatusCode::SERVICE_UNAVAILABLE.as_u16() {
+        return Json(resp).into_response();
+    }
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(
+            axum::http::header::RETRY_AFTER,
+            POOL_EXHAUSTED_RETRY_AFTER_SECS.to_string(),
+        )],
+        Json(resp),
+    )
+        .into_response()
+}
+
+fn execute_failure_response(e: &anyhow::Error) -> ExecuteResponse {
+    if let Some(InstancePoolError::Exhausted { .. }) = e.downcast_ref::<InstancePoolError>() {
+        return ExecuteResponse {
+            status: 503,
+            headers: [(
+                "retry-after".to_string(),
+                POOL_EXHAUSTED_RETRY_AFTER_SECS.to_string(),
+            )]
+            .into_iter()
+            .collect(),
+            body_b64: None,
+            error: Some(format!("pool_exhausted: {}", e)),
+        };
+    }
+    ExecuteResponse {
+        status: 500,
+        headers: Default::default(),
+        body_b64: None,
+        error: Some(format!("execute_failed: {}", e)),
+    }
+}
+
+// Prometheus scrape of metrics emitted by extensions through the `metrics` host interface,
+// followed by the runner's own bundle cache gauges.
+async fn metrics() -> impl axum::response::IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        format!(
+            "{}{}",
+            crate::engine::metrics::EXT_METRICS.render(),
+            crate::cache::space::CACHE_SPACE_METRICS.render()
+        ),
+    )
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct HealthQuery {
+    deep: Option<String>,
+}
+
+/// Object fetched by `/healthz?deep=1`, relative to BUNDLE_STORE_BASE.
+const DEFAULT_HEALTH_PROBE_KEY: &str = "healthz/probe.txt";
+/// Probe objects are meant to be tiny; refuse to hash anything larger.
+const HEALTH_PROBE_MAX_BYTES: usize = 64 * 1024;
+
+/// Last verified (ETag, sha256) per probe URL, so repeat deep checks revalidate with
+/// If-None-Match instead of downloading the object again.
+static HEALTH_PROBE_CHECKSUMS: Lazy<std::sync::Mutex<HashMap<String, (String, String)>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Fetch the probe object and verify it (against `expected_sha256` when given).
+/// Returns a short reason on failure.
+async fn probe_bundle_store_object(
+    client: &reqwest::Client,
+    bundle_base: &str,
+    probe_key: &str,
+    expected_sha256: Option<&str>,
+) -> Result<(), &'static str> {
+    use sha2::{Digest, Sha256};
+
+    let url = format!(
+        "{}/{}",
+        bundle_base.trim_end_matches('/'),
+        probe_key.trim_start_matches('/')
+    );
+    let cached = HEALTH_PROBE_CHECKSUMS
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .get(&url)
+        .cloned();
+
+    let mut req = client.get(&url);
+    if let Some((etag, _)) = &cached {
+        req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let mut resp = req.send().await.map_err(|_| "probe_unreachable")?;
+    let status = resp.status();
+
+    let checksum = if status == reqwest::StatusCode::NOT_MODIFIED && cached.is_some() {
+        cached.map(|(_, sha)| sha).unwrap_or_default()
+    } else if status.is_success() {
+        let etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        // Refuse oversize objects up front when the length is declared, and stop reading as
+        // soon as a streamed body passes the cap.
+        if resp
+            .content_length()
+            .is_some_and(|len| len > HEALTH_PROBE_MAX_BYTES as u64)
+        {
+            return Err("probe_too_large");
+        }
+        let mut hasher = Sha256::new();
+        let mut read = 0usize;
+        while let Some(chunk) = resp.chunk().await.map_err(|_| "probe_unreachable")? {
+            read += chunk.len();
+            if read > HEALTH_PROBE_MAX_BYTES {
+                return Err("probe_too_large");
+            }
+            hasher.update(&chunk);
+        }
+        let sha = hex::encode(hasher.finalize());
+        if let Some(etag) = etag {
+            HEALTH_PROBE_CHECKSUMS
+                .lock()
+                .unwrap_or_else(|p| p.into_inner())
+                .insert(url.clone(), (etag, sha.clone()));
+        }
+        sha
+    } else if status == reqwest::StatusCode::NOT_FOUND {
+        return Err("probe_missing");
+    } else if status == reqwest::StatusCode::FORBIDDEN
+        || status == reqwest::StatusCode::UNAUTHORIZED
+    {
+        return Err("probe_forbidden");
+    } else {
+        return Err("probe_failed");
+    };
+
+    match expected_sha256 {
+        Some(expected) if !checksum.eq_ignore_ascii_case(expected.trim()) => {
+            Err("probe_checksum_mismatch")
+        }
+        _ => Ok(()),
+    }
+}
+
+// Liveness: answers as long as the server loop is running. Dependency checks live in /readyz
+// so a degraded bundle store never gets the pod restarted.
 async fn healthz() -> impl axum::response::IntoResponse {
-    use axum::response::Json as AxJson;
+    (StatusCode::OK, Json(serde_json::json!({ "status": "ok" })))
+}
+
+// Readiness: verify cache root writability and attempt lightweight HEAD to bundle store,
+// returning 503 until both pass. With `?deep=1`, also fetch and verify a known probe object.
+async fn readyz(
+    State(state): State<CoreState>,
+    axum::extract::Query(query): axum::extract::Query<HealthQuery>,
+) -> impl axum::response::IntoResponse {
+    let config = &state.config;
+    let deep = matches!(query.deep.as_deref(), Some("1" | "true"));
+    let probe = deep.then(|| {
+        (
+            config
+                .health_probe_key
+                .clone()
+                .unwrap_or_else(|| DEFAULT_HEALTH_PROBE_KEY.to_string()),
+            config.health_probe_sha256.clone(),
+        )
+    });
+    readiness_report(
+        &config.cache_root,
+        config.bundle_store_base.as_ref().map_or("", Url::as_str),
+        probe,
+        config.cache_max_bytes.as_deref(),
+    )
+    .await
+}
+
+/// Run the readiness checks. `probe` is the deep-check object key and optional sha256.
+async fn readiness_report(
+    cache_root: &std::path::Path,
+    bundle_base: &str,
+    probe: Option<(String, Option<String>)>,
+    cache_max_bytes: Option<&str>,
+) -> (StatusCode, Json<serde_json::Value>) {
     use serde_json::json;
     use tokio::time::{timeout, Duration};
 
-    let cache_root = cache_fs::ext_cache_root_from_env();
     let tmp = cache_root.join("healthz.tmp");
 
-    let cache_writable = match tokio::fs::create_dir_all(&cache_root).await {
+    let cache_writable = match tokio::fs::create_dir_all(cache_root).await {
         Ok(_) => match tokio::fs::write(&tmp, b"ok").await {
             Ok(_) => {
                 let _ = tokio::fs::remove_file(&tmp).await;
@@ -580,8 +832,8 @@ async fn healthz() -> impl axum::response::IntoResponse {
         Err(_) => false,
     };
 
-    let bundle_base = std::env::var("BUNDLE_STORE_BASE").unwrap_or_default();
     let mut degraded_reason = None;
+    let mut deep_status: Option<&'static str> = None;
     if !bundle_base.is_empty() {
         let client = reqwest::Client::builder().build();
         if let Ok(client) = client {
@@ -593,28 +845,746 @@ async fn healthz() -> impl axum::response::IntoResponse {
             if !resp_ok {
                 degraded_reason = Some("bundle_store_unreachable");
             }
+            if let Some((probe_key, expected)) = &probe {
+                let probe =
+                    probe_bundle_store_object(&client, bundle_base, probe_key, expected.as_deref());
+                deep_status = Some(match timeout(Duration::from_millis(3000), probe).await {
+                    Ok(Ok(())) => "ok",
+                    Ok(Err(reason)) => reason,
+                    Err(_) => "probe_timeout",
+                });
+            }
         } else {
             degraded_reason = Some("http_client_init_failed");
         }
     }
+    if let Some(reason) = deep_status.filter(|s| *s != "ok") {
+        tracing::warn!(bundle_base=%bundle_base, reason=%reason, "bundle store deep health check failed");
+    }
 
     if !cache_writable {
         tracing::warn!(path=%cache_root.to_string_lossy(), "cache root not writable");
     }
-    if let Some(reason) = degraded_reason.as_deref() {
-        if reason != "ok" {
-            tracing::warn!(bundle_base=%bundle_base, reason=%reason, "bundle store health degraded");
-        }
+    if let Some(reason) = degraded_reason {
+        tracing::warn!(bundle_base=%bundle_base, reason=%reason, "bundle store health degraded");
     }
-    let status_code = if cache_writable {
+    let ready =
+        cache_writable && degraded_reason.is_none() && deep_status.is_none_or(|s| s == "ok");
+    let status_code = if ready {
         StatusCode::OK
     } else {
         StatusCode::SERVICE_UNAVAILABLE
     };
-    let body = json!({
+    let mut body = json!({
         "cache_writable": cache_writable,
-        "bundle_store": degraded_reason.as_deref().unwrap_or("ok"),
-        "ext_cache_max_bytes": std::env::var("EXT_CACHE_MAX_BYTES").ok(),
+        "bundle_store": degraded_reason.unwrap_or("ok"),
+        "ext_cache_max_bytes": cache_max_bytes,
     });
-    (status_code, AxJson(body))
+    if let Some(deep_status) = deep_status {
+        body["bundle_store_object"] = json!(deep_status);
+    }
+    (status_code, Json(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::client::HostLookup;
+    use axum::body::Body;
+    use axum::http::Request;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    struct CountingRegistry {
+        lookups: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl RegistryClient for CountingRegistry {
+        async fn validate_install(&self, _: &str, _: &str, _: &str) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn lookup_by_host(&self, _host: &str) -> anyhow::Result<Option<HostLookup>> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(HostLookup {
+                tenant_id: "tenant-1".into(),
+                extension_id: "ext-1".into(),
+                content_hash: "sha256:abc".into(),
+            }))
+        }
+    }
+
+    fn root_state(registry: Arc<dyn RegistryClient + Send + Sync>) -> RootState {
+        root_state_with(registry, &[])
+    }
+
+    /// `vars` override the defaults: an unreachable registry and bundle store, and the
+    /// system temp dir as cache root.
+    fn root_state_with(
+        registry: Arc<dyn RegistryClient + Send + Sync>,
+        vars: &[(&str, &str)],
+    ) -> RootState {
+        let cache_root = std::env::temp_dir();
+        let config = RunnerConfig::from_lookup(|key| {
+            if let Some((_, value)) = vars.iter().find(|(name, _)| *name == key) {
+                return Some(value.to_string());
+            }
+            match key {
+                "REGISTRY_BASE_URL" => Some("http://127.0.0.1:1/".to_string()),
+                "BUNDLE_STORE_BASE" => Some("http://127.0.0.1:1/bundles/".to_string()),
+                "EXT_CACHE_ROOT" => Some(cache_root.display().to_string()),
+                _ => None,
+            }
+        })
+        .unwrap();
+        let loader = Arc::new(ModuleLoader::with_config(&config).unwrap());
+        RootState {
+            ext: crate::http::ext_ui::AppState {
+                registry: registry.clone(),
+                cache_root: config.cache_root.clone(),
+                bundle_store_base: config.bundle_store_url(),
+                max_file_bytes: None,
+                strict_validation: config.strict_validation,
+                cache_policy: loader.cache_policy(),
+            },
+            core: CoreState {
+                idempotency: Arc::new(Mutex::new(HashMap::new())),
+                registry,
+                loader,
+                config: Arc::new(config),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn root_dispatch_reuses_shared_registry_client() {
+        let registry = Arc::new(CountingRegistry {
+            lookups: AtomicUsize::new(0),
+        });
+        let app = Router::new()
+            .route("/", get(root_dispatch))
+            .with_state(root_state(registry.clone()));
+
+        for _ in 0..2 {
+            let resp = app
+                .clone()
+                .oneshot(
+                    Request::get("/")
+                        .header("host", "ext.example.com")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::TEMPORARY_REDIRECT);
+            assert_eq!(
+                resp.headers().get("location").unwrap(),
+                "/ext-ui/ext-1/sha256:abc/index.html?tenant=tenant-1&extensionId=ext-1"
+            );
+        }
+
+        assert_eq!(registry.lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn root_dispatch_keeps_one_pooled_registry_connection() {
+        use axum::extract::ConnectInfo;
+
+        // A client built per request would open a new connection for every lookup.
+        let peers = Arc::new(Mutex::new(HashSet::<SocketAddr>::new()));
+        let seen = peers.clone();
+        let stub = Router::new().route(
+            "/api/installs/lookup-by-host",
+            get(move |ConnectInfo(peer): ConnectInfo<SocketAddr>| {
+                let seen = seen.clone();
+                async move {
+                    seen.lock().await.insert(peer);
+                    Json(serde_json::json!({
+                        "tenant_id": "tenant-1",
+                        "extension_id": "ext-1",
+                        "content_hash": "sha256:abc",
+                    }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(
+                listener,
+                stub.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await;
+        });
+
+        let registry = HttpRegistryClient::build(
+            true,
+            Some(Url::parse(&format!("http://{addr}/")).unwrap()),
+            None,
+            std::time::Duration::from_secs(5),
+        )
+        .unwrap();
+        let app = Router::new()
+            .route("/", get(root_dispatch))
+            .with_state(root_state(Arc::new(registry)));
+
+        // Distinct hosts so no lookup is served from the host cache.
+        for host in ["a.example.com", "b.example.com", "c.example.com"] {
+            let resp = app
+                .clone()
+                .oneshot(
+                    Request::get("/")
+                        .header("host", host)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::TEMPORARY_REDIRECT);
+        }
+
+        assert_eq!(peers.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn root_dispatch_times_out_slow_registry_with_504() {
+        let slow = Router::new().route(
+            "/api/installs/lookup-by-host",
+            get(|| async {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                "{}"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, slow).await;
+        });
+
+        let registry = HttpRegistryClient::build(
+            true,
+            Some(Url::parse(&format!("http://{addr}/")).unwrap()),
+            None,
+            std::time::Duration::from_millis(100),
+        )
+        .unwrap();
+        let app = Router::new()
+            .route("/", get(root_dispatch))
+            .with_state(root_state(Arc::new(registry)));
+
+        let started = Instant::now();
+        let resp = app
+            .oneshot(
+                Request::get("/")
+                    .header("host", "ext.example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    /// Bundle store whose root answers HEAD but only serves `objects` (path -> (etag, body)).
+    /// Returns the base URL and a counter of full-body GETs.
+    async fn start_probe_store(
+        objects: HashMap<&'static str, (&'static str, &'static str)>,
+    ) -> (String, Arc<AtomicUsize>) {
+        use axum::extract::Path as AxPath;
+        let full_gets = Arc::new(AtomicUsize::new(0));
+        let counter = full_gets.clone();
+        let objects = Arc::new(objects);
+        let app = Router::new()
+            .route("/bundles", axum::routing::head(|| async { StatusCode::OK }))
+            .route(
+                "/bundles/*key",
+                get(move |AxPath(key): AxPath<String>, headers: HeaderMap| {
+                    let counter = counter.clone();
+                    let objects = objects.clone();
+                    async move {
+                        let Some((etag, body)) = objects.get(key.as_str()).copied() else {
+                            return StatusCode::NOT_FOUND.into_response();
+                        };
+                        if headers.get("if-none-match").and_then(|v| v.to_str().ok()) == Some(etag)
+                        {
+                            return StatusCode::NOT_MODIFIED.into_response();
+                        }
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        ([("etag", etag)], body).into_response()
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (format!("http://{addr}/bundles"), full_gets)
+    }
+
+    #[tokio::test]
+    async fn deep_health_surfaces_missing_probe_that_head_misses() {
+        let (base, _) = start_probe_store(HashMap::new()).await;
+        let client = reqwest::Client::new();
+
+        let head = client.head(&base).send().await.unwrap();
+        assert!(head.status().is_success(), "shallow check passes");
+
+        let result =
+            probe_bundle_store_object(&client, &base, DEFAULT_HEALTH_PROBE_KEY, None).await;
+        assert_eq!(result, Err("probe_missing"));
+    }
+
+    #[tokio::test]
+    async fn deep_health_verifies_checksum_and_revalidates_from_cache() {
+        let (base, full_gets) =
+            start_probe_store(HashMap::from([("healthz/probe.txt", ("\"v1\"", "alga"))])).await;
+        let client = reqwest::Client::new();
+        let sha = hex::encode(<sha2::Sha256 as sha2::Digest>::digest(b"alga"));
+
+        for _ in 0..2 {
+            let result =
+                probe_bundle_store_object(&client, &base, "healthz/probe.txt", Some(&sha)).await;
+            assert_eq!(result, Ok(()));
+        }
+        assert_eq!(
+            full_gets.load(Ordering::SeqCst),
+            1,
+            "second probe revalidated via ETag"
+        );
+
+        let result =
+            probe_bundle_store_object(&client, &base, "healthz/probe.txt", Some("00ff")).await;
+        assert_eq!(result, Err("probe_checksum_mismatch"));
+    }
+
+    #[tokio::test]
+    async fn deep_health_stops_reading_oversize_probe_objects() {
+        let big = "x".repeat(HEALTH_PROBE_MAX_BYTES + 1).leak() as &'static str;
+        let (base, _) =
+            start_probe_store(HashMap::from([("healthz/big.txt", ("\"v1\"", big))])).await;
+        let client = reqwest::Client::new();
+        let result = probe_bundle_store_object(&client, &base, "healthz/big.txt", None).await;
+        assert_eq!(result, Err("probe_too_large"));
+
+        // Without a content-length the body is read only up to the cap; this one never ends.
+        let endless = Router::new().route(
+            "/bundles/*key",
+            get(|| async {
+                let chunks = futures_util::stream::repeat_with(|| {
+                    Ok::<_, std::io::Error>(bytes::Bytes::from_static(&[b'x'; 4096]))
+                });
+                Body::from_stream(chunks)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, endless).await;
+        });
+        let result = probe_bundle_store_object(
+            &client,
+            &format!("http://{addr}/bundles"),
+            "healthz/probe.txt",
+            None,
+        )
+        .await;
+        assert_eq!(result, Err("probe_too_large"));
+    }
+
+    #[tokio::test]
+    async fn liveness_stays_up_while_readiness_reports_bundle_store_down() {
+        let resp = Router::new()
+            .route("/healthz", get(healthz))
+            .oneshot(Request::get("/healthz").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let cache = tempfile::tempdir().unwrap();
+        let (status, Json(body)) =
+            readiness_report(cache.path(), "http://127.0.0.1:1/bundles", None, None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["cache_writable"], true);
+        assert_eq!(body["bundle_store"], "bundle_store_unreachable");
+    }
+
+    #[tokio::test]
+    async fn readiness_passes_with_reachable_bundle_store() {
+        let (base, _) = start_probe_store(HashMap::new()).await;
+        let cache = tempfile::tempdir().unwrap();
+
+        let (status, Json(body)) = readiness_report(cache.path(), &base, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["bundle_store"], "ok");
+
+        let (status, Json(body)) = readiness_report(
+            cache.path(),
+            &base,
+            Some((DEFAULT_HEALTH_PROBE_KEY.to_string(), None)),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["bundle_store_object"], "probe_missing");
+    }
+
+    /// Registry that only knows tenant-1's install of ext-1 at sha256:abc.
+    struct SingleInstallRegistry;
+
+    #[async_trait::async_trait]
+    impl RegistryClient for SingleInstallRegistry {
+        async fn validate_install(
+            &self,
+            tenant_id: &str,
+            extension_id: &str,
+            content_hash: &str,
+        ) -> anyhow::Result<bool> {
+            Ok((tenant_id, extension_id, content_hash) == ("tenant-1", "ext-1", "sha256:abc"))
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_rejects_hash_not_installed_for_tenant() {
+        let app = Router::new()
+            .route("/v1/execute", post(execute))
+            .with_state(root_state(Arc::new(SingleInstallRegistry)));
+        let body = serde_json::json!({
+            "context": {
+                "tenant_id": "tenant-2",
+                "extension_id": "ext-1",
+                "content_hash": "sha256:abc",
+            },
+            "http": { "method": "GET", "path": "/" },
+            "limits": {},
+        });
+
+        let resp = app
+            .oneshot(
+                Request::post("/v1/execute")
+                    .header("content-type", "application/json")
+                    .header("x-alga-tenant", "tenant-2")
+                    .header("x-alga-extension", "ext-1")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let bytes = axum::body::to_bytes(resp.into_body(), 1024 * 1024)
+            .await
+            .unwrap();
+        let out: ExecuteResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(out.status, 403);
+        assert_eq!(out.error.as_deref(), Some("install_mismatch"));
+    }
+
+    #[tokio::test]
+    async fn execute_rejects_headers_that_disagree_with_context() {
+        let app = Router::new()
+            .route("/v1/execute", post(execute))
+            .with_state(root_state(Arc::new(SingleInstallRegistry)));
+        let body = serde_json::json!({
+            "context": {
+                "tenant_id": "tenant-2",
+                "extension_id": "ext-1",
+                "content_hash": "sha256:abc",
+            },
+            "http": { "method": "GET", "path": "/" },
+            "limits": {},
+        });
+
+        let resp = app
+            .oneshot(
+                Request::post("/v1/execute")
+                    .header("content-type", "application/json")
+                    .header("x-alga-tenant", "tenant-1")
+                    .header("x-alga-extension", "ext-1")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let bytes = axum::body::to_bytes(resp.into_body(), 1024 * 1024)
+            .await
+            .unwrap();
+        let out: ExecuteResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(out.status, 403);
+        assert_eq!(out.error.as_deref(), Some("context_mismatch"));
+    }
+
+    /// Registry that accepts any install but has only published sha256:abc.
+    struct PublishedVersionsRegistry;
+
+    #[async_trait::async_trait]
+    impl RegistryClient for PublishedVersionsRegistry {
+        async fn validate_install(&self, _: &str, _: &str, _: &str) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn approved_versions(&self, _: &str, _: &str) -> anyhow::Result<Vec<String>> {
+            Ok(vec!["sha256:ABC".to_string()])
+        }
+    }
+
+    async fn execute_with_hash(state: RootState, content_hash: &str) -> ExecuteResponse {
+        execute_with_limits(state, content_hash, serde_json::json!({})).await
+    }
+
+    async fn execute_with_limits(
+        state: RootState,
+        content_hash: &str,
+        limits: serde_json::Value,
+    ) -> ExecuteResponse {
+        let app = Router::new()
+            .route("/v1/execute", post(execute))
+            .with_state(state);
+        let body = serde_json::json!({
+            "context": {
+                "tenant_id": "tenant-1",
+                "extension_id": "ext-1",
+                "content_hash": content_hash,
+            },
+            "http": { "method": "GET", "path": "/" },
+            "limits": limits,
+        });
+        let resp = app
+            .oneshot(
+                Request::post("/v1/execute")
+                    .header("content-type", "application/json")
+                    .header("x-alga-tenant", "tenant-1")
+                    .header("x-alga-extension", "ext-1")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(resp.into_body(), 1024 * 1024)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn execute_generates_a_request_id_shared_by_its_logs() {
+        let logs = crate::util::test_logs::CapturedLogs::default();
+        let _guard = logs.install();
+        let registry = Arc::new(CountingRegistry {
+            lookups: AtomicUsize::new(0),
+        });
+        // No x-request-id header and no context id; the bundle store is unreachable.
+        let resp = execute_with_hash(root_state(registry), "sha256:abc").await;
+        assert_eq!(resp.status, 502);
+
+        let output = logs.text();
+        let id = output
+            .lines()
+            .find(|line| line.contains("execute start"))
+            .and_then(|line| line.split("request_id=").nth(1))
+            .and_then(|rest| rest.split_whitespace().next())
+            .expect("execute start log carries a request id");
+        assert!(uuid::Uuid::parse_str(id).is_ok(), "{id}");
+        let tagged: Vec<&str> = output
+            .lines()
+            .filter(|line| line.contains("request_id"))
+            .collect();
+        assert!(tagged.len() > 2, "{output}");
+        for line in tagged {
+            assert!(line.contains(id), "{line}");
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_requires_approved_version_when_enabled() {
+        let mut state = root_state(Arc::new(PublishedVersionsRegistry));
+        Arc::make_mut(&mut state.core.config).require_approved_versions = true;
+
+        let denied = execute_with_hash(state.clone(), "sha256:def").await;
+        assert_eq!(denied.status, 403);
+        assert_eq!(denied.error.as_deref(), Some("version_not_approved"));
+
+        // The approved hash passes the gate and reaches the loader, which cannot reach the
+        // bundle store.
+        let approved = execute_with_hash(state.clone(), "abc").await;
+        assert_eq!(approved.status, 502);
+        assert!(
+            approved
+                .error
+                .as_deref()
+                .unwrap_or_default()
+                .starts_with("bundle_fetch_failed:"),
+            "{:?}",
+            approved.error
+        );
+
+        Arc::make_mut(&mut state.core.config).require_approved_versions = false;
+        let ungated = execute_with_hash(state, "sha256:def").await;
+        assert_ne!(ungated.error.as_deref(), Some("version_not_approved"));
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint_exposes_guest_metrics() {
+        use crate::engine::metrics::{MetricKind, EXT_METRICS};
+        EXT_METRICS
+            .record(
+                MetricKind::Counter,
+                "tenant-scrape",
+                "ext-scrape",
+                "widgets_synced",
+                7,
+                &[],
+            )
+            .unwrap();
+
+        let resp = Router::new()
+            .route("/metrics", get(metrics))
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), 1024 * 1024)
+            .await
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains(
+            "alga_ext_widgets_synced_total{tenant=\"tenant-scrape\",extension=\"ext-scrape\"} 7"
+        ));
+        assert!(text.contains("# TYPE alga_runner_cache_extractions_refused_total counter"));
+    }
+
+    #[tokio::test]
+    async fn bundle_fetch_timeout_maps_to_504() {
+        async fn decode(resp: Response) -> (StatusCode, ExecuteResponse) {
+            let status = resp.status();
+            let bytes = axum::body::to_bytes(resp.into_body(), 1024 * 1024)
+                .await
+                .unwrap();
+            (status, serde_json::from_slice(&bytes).unwrap())
+        }
+
+        let timeout = anyhow::Error::new(BundleFetchError::Timeout { timeout_ms: 200 })
+            .context("bundle download failed");
+        let (status, resp) = decode(bundle_load_failure(&timeout)).await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(resp.status, 504);
+        assert!(resp.error.unwrap().starts_with("bundle_fetch_timeout:"));
+
+        let (status, resp) = decode(bundle_load_failure(&anyhow::anyhow!("404"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(resp.status, 502);
+        assert!(resp.error.unwrap().starts_with("bundle_fetch_failed:"));
+    }
+
+    #[test]
+    fn pool_exhaustion_maps_to_503_with_retry_after() {
+        let exhausted = anyhow::Error::new(InstancePoolError::Exhausted {
+            detail: "maximum concurrent limit of 1 for memories reached".into(),
+        });
+        let http = execute_failure(&exhausted);
+        assert_eq!(http.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            http.headers()
+                .get(axum::http::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok()),
+            Some("1")
+        );
+
+        let resp = execute_failure_response(&exhausted);
+        assert_eq!(resp.status, 503);
+        assert_eq!(
+            resp.headers.get("retry-after").map(String::as_str),
+            Some("1")
+        );
+        assert!(resp.error.unwrap().starts_with("pool_exhausted:"));
+
+        let trapped = anyhow::anyhow!("component trapped");
+        assert_eq!(execute_failure(&trapped).status(), StatusCode::OK);
+        let resp = execute_failure_response(&trapped);
+        assert_eq!(resp.status, 500);
+        assert!(resp.headers.is_empty());
+    }
+
+    /// Bundle whose component spins in its start function until the epoch deadline traps it,
+    /// holding its pool slot meanwhile. Returns the archive and its `sha256:` content hash.
+    fn spinning_bundle() -> (Vec<u8>, String) {
+        use sha2::{Digest, Sha256};
+        let wasm = wat::parse_str(
+            r#"(component
+                (core module $m (memory 1) (func $spin (loop $l (br $l))) (start $spin))
+                (core instance (instantiate $m)))"#,
+        )
+        .unwrap();
+        let mut raw = Vec::new();
+        {
+            let mut tar = tar::Builder::new(&mut raw);
+            let mut hdr = tar::Header::new_gnu();
+            hdr.set_size(wasm.len() as u64);
+            hdr.set_mode(0o644);
+            hdr.set_cksum();
+            tar.append_data(&mut hdr, "dist/main.wasm", &wasm[..])
+                .unwrap();
+            tar.finish().unwrap();
+        }
+        let archive = zstd::stream::encode_all(&raw[..], 0).unwrap();
+        let hash = format!("sha256:{}", hex::encode(Sha256::digest(&archive)));
+        (archive, hash)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_executions_share_one_instance_pool() {
+        let (archive, content_hash) = spinning_bundle();
+        let store = Router::new().route("/*key", get(move || async move { archive.clone() }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, store).await;
+        });
+        let cache = tempfile::tempdir().unwrap();
+        let store_base = format!("http://{addr}/bundles/");
+        let cache_root = cache.path().display().to_string();
+        let state = root_state_with(
+            Arc::new(CountingRegistry {
+                lookups: AtomicUsize::new(0),
+            }),
+            &[
+                ("BUNDLE_STORE_BASE", &store_base),
+                ("EXT_CACHE_ROOT", &cache_root),
+                ("WASM_POOL_TOTAL_COMPONENTS", "1"),
+                ("WASM_POOL_TOTAL_MEMORIES", "1"),
+            ],
+        );
+
+        let first = tokio::spawn({
+            let (state, content_hash) = (state.clone(), content_hash.clone());
+            async move {
+                let limits = serde_json::json!({ "timeout_ms": 2000 });
+                execute_with_limits(state, &content_hash, limits).await
+            }
+        });
+        // Wait until the first execution holds the only component slot.
+        let pool = state
+            .core
+            .loader
+            .engine
+            .pooling_allocator_metrics()
+            .unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            while pool.component_instances() == 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("first execution never instantiated");
+        let second = execute_with_limits(state, &content_hash, serde_json::json!({})).await;
+        let first = first.await.unwrap();
+
+        assert_eq!(second.status, 503);
+        let error = second.error.unwrap_or_default();
+        assert!(error.starts_with("pool_exhausted"), "{error}");
+        assert_eq!(first.status, 500, "{:?}", first.error);
+    }
 }
@@ -1,8 +1,10 @@
 pub mod cache;
+pub mod config;
 pub mod engine;
 pub mod http;
 pub mod models;
 pub mod providers;
 pub mod registry;
 pub mod secrets;
+pub mod signing;
 pub mod util;
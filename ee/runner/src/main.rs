@@ -1,10 +1,12 @@
 mod cache;
+mod config;
 mod engine;
 mod http;
 mod models;
 mod providers;
 mod registry;
 mod secrets;
+mod signing;
 mod util;
 
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -43,52 +45,35 @@ async fn main() -> anyhow::Result<()> {
         build_unix
     );
 
-    // Log key environment configuration for easier diagnostics
-    let reg_base = std::env::var("REGISTRY_BASE_URL").unwrap_or_else(|_| "<unset>".into());
-    let bundle_base = std::env::var("BUNDLE_STORE_BASE").unwrap_or_else(|_| "<unset>".into());
-    let strict = std::env::var("EXT_STATIC_STRICT_VALIDATION").unwrap_or_else(|_| "<unset>".into());
-    let cache_bytes =
-        std::env::var("EXT_STATIC_MAX_FILE_BYTES").unwrap_or_else(|_| "<unset>".into());
-    let auth_key_present = std::env::var("ALGA_AUTH_KEY")
-        .ok()
-        .map(|k| !k.is_empty())
-        .unwrap_or(false);
-    let cache_root = std::env::var("EXT_CACHE_ROOT").unwrap_or_else(|_| "<unset>".into());
-    let pool_total = std::env::var("WASM_POOL_TOTAL_COMPONENTS").unwrap_or_else(|_| "256".into());
+    // Parse and validate configuration once; every problem is reported together
+    tracing::info!("Loading runner configuration from environment...");
+    let config = config::RunnerConfig::from_env().inspect_err(|err| {
+        tracing::error!("{err}");
+    })?;
 
     tracing::info!("═══════════════════════════════════════════════════════");
     tracing::info!("Configuration Overview:");
-    tracing::info!("  ✓ Registry Base URL: {}", reg_base);
-    tracing::info!("  ✓ Bundle Store Base: {}", bundle_base);
-    tracing::info!("  ✓ Static File Validation: {}", strict);
-    tracing::info!("  ✓ Max File Size Limit: {} bytes", cache_bytes);
+    tracing::info!("  ✓ Registry Base URL: {}", config.registry_base_url);
+    match &config.bundle_store_base {
+        Some(base) => tracing::info!("  ✓ Bundle Store Base: {}", base),
+        None => tracing::info!("  ✓ Bundle Store Base: <unset>"),
+    }
+    tracing::info!("  ✓ Static File Validation: {}", config.strict_validation);
+    tracing::info!("  ✓ Max File Size Limit: {:?} bytes", config.max_file_bytes);
+    tracing::info!("  ✓ Cache Root: {}", config.cache_root.display());
     tracing::info!(
-        "  ✓ Auth Key Configured: {}",
-        if auth_key_present {
-            "YES"
-        } else {
-            "NO - registry calls may fail"
-        }
+        "  ✓ Wasmtime Pool Size: {} components",
+        config.engine.pool_total_components
     );
-    tracing::info!("  ✓ Cache Root: {}", cache_root);
-    tracing::info!("  ✓ Wasmtime Pool Size: {} components", pool_total);
     tracing::info!("═══════════════════════════════════════════════════════");
 
-    // Validate critical configuration
-    tracing::info!("Validating critical configuration values...");
-    if reg_base == "<unset>" {
-        tracing::warn!("⚠ REGISTRY_BASE_URL not configured - registry operations will fail");
-    }
-    if bundle_base == "<unset>" {
+    if config.bundle_store_base.is_none() {
         tracing::warn!("⚠ BUNDLE_STORE_BASE not configured - extension bundles cannot be fetched");
     }
-    if !auth_key_present {
-        tracing::warn!("⚠ ALGA_AUTH_KEY not present - registry requests may be unauthorized");
-    }
 
     tracing::info!("Configuration validation complete");
     tracing::info!("═══════════════════════════════════════════════════════");
     tracing::info!("Starting HTTP server on configured port...");
 
-    http::server::run().await
+    http::server::run(config).await
 }
@@ -12,6 +12,10 @@ pub struct ExecuteContext {
     pub version_id: Option<String>,
     #[serde(default)]
     pub config: HashMap<String, String>,
+    /// Tenant-specific `http.fetch` hosts, combined with `EXT_EGRESS_ALLOWLIST` per
+    /// `EXT_EGRESS_TENANT_OVERLAY`.
+    #[serde(default)]
+    pub egress_allowlist: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
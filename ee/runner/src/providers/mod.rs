@@ -12,6 +12,7 @@ pub const CAP_SCHEDULER_MANAGE: &str = "cap:scheduler.manage";
 pub const CAP_INVOICE_MANUAL_CREATE: &str = "cap:invoice.manual.create";
 pub const CAP_CLIENT_READ: &str = "cap:client.read";
 pub const CAP_SERVICE_READ: &str = "cap:service.read";
+pub const CAP_METRICS_EMIT: &str = "cap:metrics.emit";
 
 static KNOWN_CAPABILITIES: Lazy<HashSet<&'static str>> = Lazy::new(|| {
     HashSet::from([
@@ -26,6 +27,7 @@ static KNOWN_CAPABILITIES: Lazy<HashSet<&'static str>> = Lazy::new(|| {
         CAP_INVOICE_MANUAL_CREATE,
         CAP_CLIENT_READ,
         CAP_SERVICE_READ,
+        CAP_METRICS_EMIT,
     ])
 });
 
@@ -5,67 +5,172 @@ use std::time::Duration;
 use tokio::time::timeout;
 use url::Url;
 
+use crate::config::RunnerConfig;
+use crate::util::{errors::RegistryLookupError, redact};
+
+/// Install resolved from a request host via `lookup-by-host`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize)]
+pub struct HostLookup {
+    pub tenant_id: String,
+    pub extension_id: String,
+    pub content_hash: String,
+}
+
+/// Per-extension resource ceilings from `resource-policy`. `None` leaves a dimension to the
+/// runner-wide ceiling (if any).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct ResourcePolicy {
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    #[serde(default)]
+    pub max_timeout_ms: Option<u64>,
+}
+
 /// Registry validation client trait. Validates a tenant/extension/content-hash mapping.
 #[async_trait]
 pub trait RegistryClient: Send + Sync {
+    /// `Ok(false)` means the registry answered that the install does not match; `Err` means
+    /// no answer was obtained (unreachable, timed out, or a non-success status).
     async fn validate_install(
         &self,
         tenant_id: &str,
         extension_id: &str,
         content_hash: &str,
     ) -> Result<bool>;
+
+    /// Resolve the install serving `host`. `Ok(None)` means the registry has no mapping;
+    /// `Err` means the registry could not be reached.
+    async fn lookup_by_host(&self, _host: &str) -> Result<Option<HostLookup>> {
+        Ok(None)
+    }
+
+    /// Resource ceilings for an extension install. `Ok(None)` means the registry sets none.
+    async fn resource_policy(
+        &self,
+        _tenant_id: &str,
+        _extension_id: &str,
+    ) -> Result<Option<ResourcePolicy>> {
+        Ok(None)
+    }
+
+    /// Content hashes published (approved to run) for an extension. Empty means none are.
+    async fn approved_versions(
+        &self,
+        _tenant_id: &str,
+        _extension_id: &str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Whether `content_hash` is among the extension's approved versions.
+    async fn is_version_approved(
+        &self,
+        tenant_id: &str,
+        extension_id: &str,
+        content_hash: &str,
+    ) -> Result<bool> {
+        let approved = self.approved_versions(tenant_id, extension_id).await?;
+        Ok(is_approved_version(&approved, content_hash))
+    }
+}
+
+/// Compare hashes with or without the `sha256:` prefix, ignoring case.
+pub fn is_approved_version(approved: &[String], content_hash: &str) -> bool {
+    let normalize = |h: &str| h.trim().trim_start_matches("sha256:").to_ascii_lowercase();
+    let wanted = normalize(content_hash);
+    !wanted.is_empty() && approved.iter().any(|h| normalize(h) == wanted)
 }
 
-/// HTTP-backed client with a short TTL cache. When strict validation is disabled (EXT_STATIC_STRICT_VALIDATION != "true"),
+#[derive(serde::Deserialize)]
+struct ApprovedVersions {
+    #[serde(default)]
+    content_hashes: Vec<String>,
+}
+
+const LOOKUP_CACHE_TTL: Duration = Duration::from_secs(30);
+const POLICY_CACHE_TTL: Duration = Duration::from_secs(60);
+const APPROVED_CACHE_TTL: Duration = Duration::from_secs(60);
+const UNAPPROVED_CACHE_TTL: Duration = Duration::from_secs(10);
+const LOOKUP_MAX_ATTEMPTS: u32 = 3;
+const LOOKUP_RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+pub(crate) const DEFAULT_LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+const VALIDATE_TIMEOUT: Duration = Duration::from_millis(750);
+
+/// HTTP-backed client with a short TTL cache. When strict validation is disabled (EXT_STATIC_STRICT_VALIDATION=false),
 /// this client will always return Ok(true).
 pub struct HttpRegistryClient {
     strict: bool,
     base_url: Option<Url>,
     cache: Cache<String, bool>,
+    lookup_cache: Cache<String, Option<HostLookup>>,
+    policy_cache: Cache<String, Option<ResourcePolicy>>,
+    approved_cache: Cache<String, Vec<String>>,
+    /// Hashes a fresh list did not approve; skips the refetch for repeat requests.
+    unapproved_cache: Cache<String, ()>,
+    lookup_timeout: Duration,
     http: reqwest::Client,
     api_key: Option<String>,
 }
 
 impl HttpRegistryClient {
-    pub fn new(api_key: Option<String>) -> Result<Self> {
-        let strict = std::env::var("EXT_STATIC_STRICT_VALIDATION")
-            .map(|v| v.trim().eq_ignore_ascii_case("true"))
-            .unwrap_or(true);
+    pub fn from_config(config: &RunnerConfig, api_key: Option<String>) -> Result<Self> {
+        if let Some(ref k) = api_key {
+            let prefix: String = k.chars().take(4).collect();
+            tracing::info!(key_len = k.len(), key_prefix = %prefix, "ALGA_AUTH_KEY present for validation client");
+        } else {
+            tracing::warn!("ALGA_AUTH_KEY not set for validation client; strict validation calls may be unauthorized");
+        }
 
-        let base_url = std::env::var("REGISTRY_BASE_URL")
-            .ok()
-            .and_then(|s| Url::parse(&s).ok());
+        Self::build(
+            config.strict_validation,
+            Some(config.registry_base_url.clone()),
+            api_key,
+            config.registry_lookup_timeout,
+        )
+    }
 
+    pub(crate) fn build(
+        strict: bool,
+        base_url: Option<Url>,
+        api_key: Option<String>,
+        lookup_timeout: Duration,
+    ) -> Result<Self> {
         let cache = Cache::builder()
             .max_capacity(10_000)
             .time_to_live(Duration::from_secs(45))
             .build();
 
-        let http = reqwest::Client::builder().build()?;
+        let lookup_cache = Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(LOOKUP_CACHE_TTL)
+            .build();
 
-        // Optional API key auth for registry requests
-        let api_key = api_key.or_else(|| {
-            std::env::var("ALGA_AUTH_KEY").ok().and_then(|s| {
-                let t = s.trim().to_string();
-                if t.is_empty() {
-                    None
-                } else {
-                    Some(t)
-                }
-            })
-        });
+        let policy_cache = Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(POLICY_CACHE_TTL)
+            .build();
 
-        if let Some(ref k) = api_key {
-            let prefix: String = k.chars().take(4).collect();
-            tracing::info!(key_len = k.len(), key_prefix = %prefix, "ALGA_AUTH_KEY present for validation client");
-        } else {
-            tracing::warn!("ALGA_AUTH_KEY not set for validation client; strict validation calls may be unauthorized");
-        }
+        let approved_cache = Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(APPROVED_CACHE_TTL)
+            .build();
+
+        let unapproved_cache = Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(UNAPPROVED_CACHE_TTL)
+            .build();
+
+        let http = reqwest::Client::builder().build()?;
 
         Ok(Self {
             strict,
             base_url,
             cache,
+            lookup_cache,
+            policy_cache,
+            approved_cache,
+            unapproved_cache,
+            lookup_timeout,
             http,
             api_key,
         })
@@ -74,6 +179,137 @@ impl HttpRegistryClient {
     fn cache_key(tenant_id: &str, extension_id: &str, content_hash: &str) -> String {
         format!("{}:{}:{}", tenant_id, extension_id, content_hash)
     }
+
+    /// Single `lookup-by-host` round trip. Only a 404 means the host has no install; transport
+    /// failures and every other non-success status (e.g. 401 from a rotated key) are errors,
+    /// which are never cached.
+    async fn fetch_host_lookup(&self, base: &Url, host: &str) -> Result<Option<HostLookup>> {
+        let mut url = base.clone();
+        url.set_path("api/installs/lookup-by-host");
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        url.query_pairs_mut()
+            .append_pair("host", host)
+            .append_pair("ts", &now_ms.to_string());
+
+        let mut rb = self.http.get(url.clone());
+        if let Some(key) = &self.api_key {
+            rb = rb.header("x-api-key", key);
+        } else {
+            tracing::warn!(host=%host, "No API key configured - registry lookup may be unauthorized");
+        }
+
+        tracing::info!(host=%host, lookup_url=%url.to_string(), "Sending extension lookup request to registry");
+        let resp = rb.send().await?;
+        let status = resp.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            tracing::warn!(host=%host, url_path=%url.path(), "Registry has no install for host");
+            return Ok(None);
+        }
+        if !status.is_success() {
+            anyhow::bail!("registry lookup returned {}", status.as_u16());
+        }
+
+        let text = resp.text().await?;
+        tracing::info!(host=%host, status=%status.as_u16(), body_len=%text.len(), "Registry lookup response received");
+        let lookup = serde_json::from_str::<HostLookup>(&text)
+            .map_err(|e| anyhow::anyhow!("malformed registry lookup response: {}", e))?;
+        Ok(Some(lookup))
+    }
+
+    /// Single `resource-policy` round trip. 404 means the install has no policy.
+    async fn fetch_resource_policy(
+        &self,
+        base: &Url,
+        tenant_id: &str,
+        extension_id: &str,
+    ) -> Result<Option<ResourcePolicy>> {
+        let mut url = base.clone();
+        url.set_path("api/installs/resource-policy");
+        url.query_pairs_mut()
+            .append_pair("tenant", tenant_id)
+            .append_pair("extension", extension_id);
+
+        let mut rb = self.http.get(url.clone());
+        if let Some(key) = &self.api_key {
+            rb = rb.header("x-api-key", key);
+        }
+
+        let resp = rb.send().await?;
+        let status = resp.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            anyhow::bail!("registry resource policy returned {}", status.as_u16());
+        }
+        let policy = resp
+            .json::<ResourcePolicy>()
+            .await
+            .map_err(|e| anyhow::anyhow!("malformed registry resource policy: {}", e))?;
+        Ok(Some(policy))
+    }
+
+    /// Single `approved-versions` round trip. The registry answers an empty list when nothing
+    /// is approved, so a 404 means the route is missing and is an error.
+    async fn fetch_approved_versions(
+        &self,
+        base: &Url,
+        tenant_id: &str,
+        extension_id: &str,
+    ) -> Result<Vec<String>> {
+        let mut url = base.clone();
+        url.set_path("api/installs/approved-versions");
+        url.query_pairs_mut()
+            .append_pair("tenant", tenant_id)
+            .append_pair("extension", extension_id);
+
+        let mut rb = self.http.get(url.clone());
+        if let Some(key) = &self.api_key {
+            rb = rb.header("x-api-key", key);
+        }
+
+        let resp = rb.send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            anyhow::bail!("registry approved versions returned {}", status.as_u16());
+        }
+        let approved = resp
+            .json::<ApprovedVersions>()
+            .await
+            .map_err(|e| anyhow::anyhow!("malformed registry approved versions: {}", e))?;
+        Ok(approved.content_hashes)
+    }
+
+    /// Fetch the approved versions, bypassing and then refreshing the cache.
+    async fn refresh_approved_versions(
+        &self,
+        tenant_id: &str,
+        extension_id: &str,
+    ) -> Result<Vec<String>> {
+        let Some(base) = &self.base_url else {
+            anyhow::bail!("REGISTRY_BASE_URL not configured");
+        };
+        let timeout_ms = self.lookup_timeout.as_millis() as u64;
+        let approved = match timeout(
+            self.lookup_timeout,
+            self.fetch_approved_versions(base, tenant_id, extension_id),
+        )
+        .await
+        {
+            Ok(result) => result?,
+            Err(_) => {
+                tracing::error!(tenant=%tenant_id, extension=%extension_id, timeout_ms, "Registry approved versions lookup timed out");
+                return Err(RegistryLookupError::Timeout { timeout_ms }.into());
+            }
+        };
+        tracing::info!(tenant=%tenant_id, extension=%extension_id, approved=approved.len(), "Registry approved versions resolved");
+        let key = format!("{}:{}", tenant_id, extension_id);
+        self.approved_cache.insert(key, approved.clone()).await;
+        Ok(approved)
+    }
 }
 
 #[async_trait]
@@ -94,10 +330,8 @@ impl RegistryClient for HttpRegistryClient {
 
         tracing::info!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, "Strict validation enabled - checking registry");
 
-        // Missing base URL in strict mode - treat as not validated.
         let Some(base) = &self.base_url else {
-            tracing::warn!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, "Registry base URL not configured in strict mode - denying validation");
-            return Ok(false);
+            anyhow::bail!("REGISTRY_BASE_URL not configured");
         };
 
         let key = Self::cache_key(tenant_id, extension_id, content_hash);
@@ -139,52 +373,41 @@ impl RegistryClient for HttpRegistryClient {
 
         tracing::info!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, registry_url=%url.to_string(), "Sending validation request to registry (750ms timeout)");
 
-        // 750ms budget to avoid head-of-line blocking on hot path
-        let resp = match timeout(Duration::from_millis(750), fut).await {
+        // 750ms budget to avoid head-of-line blocking on hot path. Failures are returned as
+        // errors and never cached: only the registry's own answer is remembered.
+        let resp = match timeout(VALIDATE_TIMEOUT, fut).await {
             Ok(Ok(r)) => {
                 tracing::info!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, status=%r.status().as_u16(), "Registry validation response received");
                 r
             }
             Ok(Err(e)) => {
                 tracing::error!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, err=%e.to_string(), "Registry validation request failed");
-                tracing::warn!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, "On request failure in strict mode - denying validation");
-                self.cache.insert(key, false).await;
-                return Ok(false);
+                return Err(e.into());
             }
             Err(_e) => {
-                tracing::error!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, timeout_ms=750u64, "Registry validation request timed out");
-                tracing::warn!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, "On timeout in strict mode - denying validation");
-                self.cache.insert(key, false).await;
-                return Ok(false);
+                tracing::error!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, timeout_ms=%VALIDATE_TIMEOUT.as_millis(), "Registry validation request timed out");
+                anyhow::bail!(
+                    "registry validation timed out after {}ms",
+                    VALIDATE_TIMEOUT.as_millis()
+                );
             }
         };
 
-        // Interpret JSON { valid: bool } or truthy status 200
+        // Only an explicit JSON { valid: bool } on a 2xx response is an answer
         let status = resp.status();
-        let valid = if status.is_success() {
-            match resp.text().await {
-                Ok(txt) => {
-                    let valid_val = serde_json::from_str::<serde_json::Value>(&txt)
-                        .ok()
-                        .and_then(|v| v.get("valid").and_then(|b| b.as_bool()))
-                        .unwrap_or(false);
-
-                    tracing::info!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, status=%status.as_u16(), body_len=%txt.len(), valid=%valid_val, "Registry validation response parsed");
-                    if txt.len() > 0 {
-                        tracing::debug!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, body_sample=%txt.chars().take(200).collect::<String>(), "Registry response body");
-                    }
-
-                    valid_val
-                }
-                Err(_e) => {
-                    tracing::error!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, "Failed to parse registry validation response body");
-                    false
-                }
-            }
-        } else {
+        if !status.is_success() {
             tracing::warn!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, status=%status.as_u16(), "Registry returned non-success status");
-            false
-        };
+            anyhow::bail!("registry validation returned {}", status.as_u16());
+        }
+        let txt = resp.text().await?;
+        tracing::info!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, status=%status.as_u16(), body_len=%txt.len(), "Registry validation response parsed");
+        if !txt.is_empty() {
+            tracing::debug!(tenant=%tenant_id, extension=%extension_id, content_hash=%content_hash, body_sample=%redact::body_sample(&txt), "Registry response body");
+        }
+        let valid = serde_json::from_str::<serde_json::Value>(&txt)
+            .ok()
+            .and_then(|v| v.get("valid").and_then(|b| b.as_bool()))
+            .ok_or_else(|| anyhow::anyhow!("malformed registry validation response"))?;
 
         self.cache.insert(key, valid).await;
         if valid {
@@ -195,12 +418,128 @@ impl RegistryClient for HttpRegistryClient {
 
         Ok(valid)
     }
+
+    async fn lookup_by_host(&self, host: &str) -> Result<Option<HostLookup>> {
+        let host = host.split(':').next().unwrap_or("").to_ascii_lowercase();
+        let Some(base) = &self.base_url else {
+            anyhow::bail!("REGISTRY_BASE_URL not configured");
+        };
+
+        if let Some(cached) = self.lookup_cache.get(&host).await {
+            tracing::info!(host=%host, found=%cached.is_some(), "Registry host lookup served from cache");
+            return Ok(cached);
+        }
+
+        // The timeout covers every attempt so a hung registry cannot stall callers
+        // for longer than the configured budget.
+        let attempts = async {
+            let mut attempt = 1;
+            loop {
+                match self.fetch_host_lookup(base, &host).await {
+                    Ok(lookup) => return Ok(lookup),
+                    Err(e) if attempt < LOOKUP_MAX_ATTEMPTS => {
+                        let delay = LOOKUP_RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+                        tracing::warn!(host=%host, attempt, delay_ms=%delay.as_millis(), err=%e.to_string(), "Registry host lookup failed - retrying");
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    Err(e) => {
+                        tracing::error!(host=%host, attempts=attempt, err=%e.to_string(), "Registry host lookup failed - giving up");
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        let timeout_ms = self.lookup_timeout.as_millis() as u64;
+        let lookup = match timeout(self.lookup_timeout, attempts).await {
+            Ok(result) => result?,
+            Err(_) => {
+                tracing::error!(host=%host, timeout_ms, "Registry host lookup timed out");
+                return Err(RegistryLookupError::Timeout { timeout_ms }.into());
+            }
+        };
+        self.lookup_cache.insert(host, lookup.clone()).await;
+        Ok(lookup)
+    }
+
+    async fn resource_policy(
+        &self,
+        tenant_id: &str,
+        extension_id: &str,
+    ) -> Result<Option<ResourcePolicy>> {
+        let Some(base) = &self.base_url else {
+            return Ok(None);
+        };
+        let key = format!("{}:{}", tenant_id, extension_id);
+        if let Some(cached) = self.policy_cache.get(&key).await {
+            return Ok(cached);
+        }
+
+        let timeout_ms = self.lookup_timeout.as_millis() as u64;
+        let policy = match timeout(
+            self.lookup_timeout,
+            self.fetch_resource_policy(base, tenant_id, extension_id),
+        )
+        .await
+        {
+            Ok(result) => result?,
+            Err(_) => {
+                tracing::error!(tenant=%tenant_id, extension=%extension_id, timeout_ms, "Registry resource policy lookup timed out");
+                return Err(RegistryLookupError::Timeout { timeout_ms }.into());
+            }
+        };
+        tracing::info!(tenant=%tenant_id, extension=%extension_id, policy=?policy, "Registry resource policy resolved");
+        self.policy_cache.insert(key, policy).await;
+        Ok(policy)
+    }
+
+    async fn approved_versions(&self, tenant_id: &str, extension_id: &str) -> Result<Vec<String>> {
+        let key = format!("{}:{}", tenant_id, extension_id);
+        if let Some(cached) = self.approved_cache.get(&key).await {
+            return Ok(cached);
+        }
+        self.refresh_approved_versions(tenant_id, extension_id)
+            .await
+    }
+
+    /// A cached list that lacks `content_hash` is refetched once, so a version approved since
+    /// the list was cached runs without waiting for the cache to expire. A hash the fresh list
+    /// still lacks is remembered as unapproved for `UNAPPROVED_CACHE_TTL`.
+    async fn is_version_approved(
+        &self,
+        tenant_id: &str,
+        extension_id: &str,
+        content_hash: &str,
+    ) -> Result<bool> {
+        let key = format!("{}:{}", tenant_id, extension_id);
+        if let Some(cached) = self.approved_cache.get(&key).await {
+            if is_approved_version(&cached, content_hash) {
+                return Ok(true);
+            }
+        }
+        let unapproved_key = Self::cache_key(tenant_id, extension_id, content_hash);
+        if self.unapproved_cache.contains_key(&unapproved_key) {
+            return Ok(false);
+        }
+        let approved = self
+            .refresh_approved_versions(tenant_id, extension_id)
+            .await?;
+        let is_approved = is_approved_version(&approved, content_hash);
+        if !is_approved {
+            self.unapproved_cache.insert(unapproved_key, ()).await;
+        }
+        Ok(is_approved)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::{http::StatusCode, response::IntoResponse, routing::get, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
+    use tokio::net::TcpListener;
     struct AllowAll;
     #[async_trait]
     impl RegistryClient for AllowAll {
@@ -224,5 +563,318 @@ mod tests {
     async fn trait_object_smoke() {
         let c: Arc<dyn RegistryClient + Send + Sync> = Arc::new(AllowAll);
         assert!(c.validate_install("t", "e", "sha256:abc").await.unwrap());
+        assert_eq!(c.lookup_by_host("example.com").await.unwrap(), None);
+    }
+
+    /// Mock registry that fails the first `failures` lookups with a 503.
+    async fn start_lookup_registry(failures: usize) -> (Url, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/api/installs/lookup-by-host",
+            get(move || {
+                let counter = counter.clone();
+                async move {
+                    let n = counter.fetch_add(1, Ordering::SeqCst);
+                    if n < failures {
+                        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                    }
+                    axum::Json(serde_json::json!({
+                        "tenant_id": "tenant-1",
+                        "extension_id": "ext-1",
+                        "content_hash": "sha256:abc"
+                    }))
+                    .into_response()
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (Url::parse(&format!("http://{addr}/")).unwrap(), hits)
+    }
+
+    /// Mock registry whose `validate` answers 503 for the first `failures` calls, then
+    /// `{ "valid": valid }`.
+    async fn start_validate_registry(failures: usize, valid: bool) -> (Url, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/api/installs/validate",
+            get(move || {
+                let counter = counter.clone();
+                async move {
+                    let n = counter.fetch_add(1, Ordering::SeqCst);
+                    if n < failures {
+                        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                    }
+                    axum::Json(serde_json::json!({ "valid": valid })).into_response()
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (Url::parse(&format!("http://{addr}/")).unwrap(), hits)
+    }
+
+    #[tokio::test]
+    async fn validate_install_errors_are_not_cached() {
+        let (base, hits) = start_validate_registry(1, true).await;
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        assert!(client
+            .validate_install("t", "e", "sha256:abc")
+            .await
+            .is_err());
+        assert!(client
+            .validate_install("t", "e", "sha256:abc")
+            .await
+            .unwrap());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn validate_install_caches_explicit_denial() {
+        let (base, hits) = start_validate_registry(0, false).await;
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        assert!(!client
+            .validate_install("t", "e", "sha256:abc")
+            .await
+            .unwrap());
+        assert!(!client
+            .validate_install("t", "e", "sha256:abc")
+            .await
+            .unwrap());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn validate_install_unreachable_registry_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        assert!(client
+            .validate_install("t", "e", "sha256:abc")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn lookup_by_host_serves_repeat_hosts_from_cache() {
+        let (base, hits) = start_lookup_registry(0).await;
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        let first = client.lookup_by_host("ext.example.com").await.unwrap();
+        let second = client.lookup_by_host("ext.example.com:443").await.unwrap();
+
+        assert_eq!(
+            first.as_ref().map(|l| l.tenant_id.as_str()),
+            Some("tenant-1")
+        );
+        assert_eq!(first, second);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn lookup_by_host_caches_only_not_found() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/api/installs/lookup-by-host",
+            get(
+                move |axum::extract::Query(q): axum::extract::Query<
+                    std::collections::HashMap<String, String>,
+                >| {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        match q.get("host").map(String::as_str) {
+                            Some("missing.example.com") => StatusCode::NOT_FOUND,
+                            _ => StatusCode::UNAUTHORIZED,
+                        }
+                    }
+                },
+            ),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        let base = Url::parse(&format!("http://{addr}/")).unwrap();
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(
+                client.lookup_by_host("missing.example.com").await.unwrap(),
+                None
+            );
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // An auth failure is not "no install" and is asked again next time.
+        assert!(client.lookup_by_host("denied.example.com").await.is_err());
+        let after_first = hits.load(Ordering::SeqCst);
+        assert!(client.lookup_by_host("denied.example.com").await.is_err());
+        assert!(hits.load(Ordering::SeqCst) > after_first);
+    }
+
+    #[tokio::test]
+    async fn lookup_by_host_retries_server_errors() {
+        let (base, hits) = start_lookup_registry(1).await;
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        let lookup = client.lookup_by_host("ext.example.com").await.unwrap();
+
+        assert_eq!(lookup.map(|l| l.extension_id), Some("ext-1".to_string()));
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn resource_policy_is_fetched_and_cached() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/api/installs/resource-policy",
+            get(
+                move |axum::extract::Query(q): axum::extract::Query<
+                    std::collections::HashMap<String, String>,
+                >| {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        if q.get("extension").map(String::as_str) != Some("ext-1") {
+                            return StatusCode::NOT_FOUND.into_response();
+                        }
+                        axum::Json(serde_json::json!({
+                            "max_memory_mb": 128,
+                            "max_timeout_ms": 5000
+                        }))
+                        .into_response()
+                    }
+                },
+            ),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        let base = Url::parse(&format!("http://{addr}/")).unwrap();
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        let expected = Some(ResourcePolicy {
+            max_memory_mb: Some(128),
+            max_timeout_ms: Some(5000),
+        });
+        assert_eq!(
+            client.resource_policy("tenant-1", "ext-1").await.unwrap(),
+            expected
+        );
+        assert_eq!(
+            client.resource_policy("tenant-1", "ext-1").await.unwrap(),
+            expected
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        assert_eq!(
+            client.resource_policy("tenant-1", "ext-2").await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn approved_versions_are_fetched_and_cached() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let published = Arc::new(std::sync::Mutex::new(vec!["sha256:aaa".to_string()]));
+        let counter = hits.clone();
+        let listed = published.clone();
+        let app = Router::new().route(
+            "/api/installs/approved-versions",
+            get(
+                move |axum::extract::Query(q): axum::extract::Query<
+                    std::collections::HashMap<String, String>,
+                >| {
+                    let counter = counter.clone();
+                    let listed = listed.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        if q.get("extension").map(String::as_str) != Some("ext-1") {
+                            return StatusCode::NOT_FOUND.into_response();
+                        }
+                        let hashes = listed.lock().unwrap().clone();
+                        axum::Json(serde_json::json!({ "content_hashes": hashes })).into_response()
+                    }
+                },
+            ),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        let base = Url::parse(&format!("http://{addr}/")).unwrap();
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(
+                client.approved_versions("tenant-1", "ext-1").await.unwrap(),
+                vec!["sha256:aaa".to_string()]
+            );
+            assert!(client
+                .is_version_approved("tenant-1", "ext-1", "AAA")
+                .await
+                .unwrap());
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // A version approved after the list was cached is found by one refetch.
+        published.lock().unwrap().push("sha256:bbb".to_string());
+        assert!(client
+            .is_version_approved("tenant-1", "ext-1", "sha256:bbb")
+            .await
+            .unwrap());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        // An unapproved hash costs one refetch, then is answered from cache.
+        for _ in 0..3 {
+            assert!(!client
+                .is_version_approved("tenant-1", "ext-1", "sha256:ccc")
+                .await
+                .unwrap());
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        // The registry lists nothing approved as an empty list; 404 is a missing route.
+        assert!(client.approved_versions("tenant-1", "ext-2").await.is_err());
+        assert!(client
+            .is_version_approved("tenant-1", "ext-2", "sha256:aaa")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn lookup_by_host_gives_up_after_bounded_attempts() {
+        let (base, hits) = start_lookup_registry(usize::MAX).await;
+        let client =
+            HttpRegistryClient::build(true, Some(base), None, DEFAULT_LOOKUP_TIMEOUT).unwrap();
+
+        assert!(client.lookup_by_host("ext.example.com").await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), LOOKUP_MAX_ATTEMPTS as usize);
     }
 }
@@ -0,0 +1,228 @@
+// Ed25519 detached signature verification and trust bundle loading
+use std::path::Path;
+
+use base64::Engine as _;
+use ed25519_dalek::{Signature, VerifyingKey};
+
+use crate::util::errors::SigningError;
+
+/// DER prefix of an Ed25519 `SubjectPublicKeyInfo` (RFC 8410); the raw key follows it.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+const PEM_BEGIN: &str = "-----BEGIN PUBLIC KEY-----";
+const PEM_END: &str = "-----END PUBLIC KEY-----";
+const SIGNATURE_LEN: usize = 64;
+
+/// An Ed25519 public key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey(VerifyingKey);
+
+impl std::fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PublicKey({})", hex::encode(self.0.as_bytes()))
+    }
+}
+
+impl PublicKey {
+    /// Accepts the raw 32-byte key or its DER `SubjectPublicKeyInfo` encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SigningError> {
+        let raw = bytes.strip_prefix(&ED25519_SPKI_PREFIX).unwrap_or(bytes);
+        let key: [u8; 32] = raw.try_into().map_err(|_| SigningError::InvalidKey {
+            reason: format!("expected a 32-byte Ed25519 key, got {} bytes", raw.len()),
+        })?;
+        let key = VerifyingKey::from_bytes(&key).map_err(|err| SigningError::InvalidKey {
+            reason: format!("not an Ed25519 curve point: {err}"),
+        })?;
+        Ok(Self(key))
+    }
+}
+
+/// Parse every `PUBLIC KEY` block in `text`; anything outside the blocks is ignored.
+pub fn parse_public_keys_pem(text: &str) -> Result<Vec<PublicKey>, SigningError> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(PEM_BEGIN) {
+        let body = &rest[start + PEM_BEGIN.len()..];
+        let end = body.find(PEM_END).ok_or_else(|| SigningError::InvalidKey {
+            reason: "unterminated PEM public key block".to_string(),
+        })?;
+        let b64: String = body[..end].chars().filter(|c| !c.is_whitespace()).collect();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .map_err(|err| SigningError::InvalidKey {
+                reason: format!("PEM body is not base64: {err}"),
+            })?;
+        keys.push(PublicKey::from_bytes(&der)?);
+        rest = &body[end + PEM_END.len()..];
+    }
+    Ok(keys)
+}
+
+/// Accepts a raw 64-byte signature or its base64 text (surrounding whitespace allowed).
+pub fn decode_signature(raw: &[u8]) -> Result<[u8; SIGNATURE_LEN], SigningError> {
+    let decoded;
+    let bytes = if raw.len() == SIGNATURE_LEN {
+        raw
+    } else {
+        let text = std::str::from_utf8(raw).map_err(|_| SigningError::MalformedSignature {
+            reason: "neither 64 raw bytes nor base64 text".to_string(),
+        })?;
+        decoded = base64::engine::general_purpose::STANDARD
+            .decode(text.trim())
+            .map_err(|err| SigningError::MalformedSignature {
+                reason: format!("signature is not base64: {err}"),
+            })?;
+        decoded.as_slice()
+    };
+    bytes
+        .try_into()
+        .map_err(|_| SigningError::MalformedSignature {
+            reason: format!("expected {SIGNATURE_LEN} bytes, got {}", bytes.len()),
+        })
+}
+
+/// Verify an Ed25519 `signature` over `data` with a single key.
+pub fn verify_detached(
+    public_key: &PublicKey,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<(), SigningError> {
+    let signature = Signature::from_bytes(&decode_signature(signature)?);
+    public_key
+        .0
+        .verify_strict(data, &signature)
+        .map_err(|_| SigningError::Rejected)
+}
+
+/// Keys accepted for bundle signatures. Several keys may be trusted at once so signers can
+/// rotate without a flag day: a signature is valid when any of them verifies it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedKeys {
+    keys: Vec<PublicKey>,
+}
+
+impl TrustedKeys {
+    pub fn new(keys: Vec<PublicKey>) -> Self {
+        Self { keys }
+    }
+
+    /// A trust bundle must contain at least one key.
+    pub fn from_pem(text: &str) -> Result<Self, SigningError> {
+        let keys = parse_public_keys_pem(text)?;
+        if keys.is_empty() {
+            return Err(SigningError::InvalidKey {
+                reason: "trust bundle contains no PEM public keys".to_string(),
+            });
+        }
+        Ok(Self::new(keys))
+    }
+
+    pub fn from_pem_file(path: &Path) -> Result<Self, SigningError> {
+        let text = std::fs::read_to_string(path).map_err(|err| SigningError::InvalidKey {
+            reason: format!("cannot read trust bundle {}: {err}", path.display()),
+        })?;
+        Self::from_pem(&text)
+    }
+
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.keys
+    }
+
+    pub fn verify(&self, data: &[u8], signature: &[u8]) -> Result<(), SigningError> {
+        let signature = decode_signature(signature)?;
+        if self
+            .keys
+            .iter()
+            .any(|key| verify_detached(key, data, &signature).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(SigningError::Rejected)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn keypair() -> SigningKey {
+        SigningKey::from_bytes(&rand::random())
+    }
+
+    fn public_key(pair: &SigningKey) -> PublicKey {
+        PublicKey::from_bytes(pair.verifying_key().as_bytes()).unwrap()
+    }
+
+    fn pem(pair: &SigningKey) -> String {
+        let mut der = ED25519_SPKI_PREFIX.to_vec();
+        der.extend_from_slice(pair.verifying_key().as_bytes());
+        format!(
+            "{PEM_BEGIN}\n{}\n{PEM_END}\n",
+            base64::engine::general_purpose::STANDARD.encode(der)
+        )
+    }
+
+    #[test]
+    fn valid_signature_verifies() {
+        let pair = keypair();
+        let data = b"sha256:abc123";
+        let signature = pair.sign(data);
+
+        verify_detached(&public_key(&pair), data, &signature.to_bytes()).unwrap();
+        let b64 = base64::engine::general_purpose::STANDARD.encode(signature.to_bytes());
+        verify_detached(&public_key(&pair), data, format!("{b64}\n").as_bytes()).unwrap();
+        assert_eq!(
+            parse_public_keys_pem(&pem(&pair)).unwrap(),
+            vec![public_key(&pair)]
+        );
+    }
+
+    #[test]
+    fn tampered_or_malformed_signature_is_rejected() {
+        let pair = keypair();
+        let key = public_key(&pair);
+        let signature = pair.sign(b"sha256:abc123");
+
+        assert!(matches!(
+            verify_detached(&key, b"sha256:abc124", &signature.to_bytes()),
+            Err(SigningError::Rejected)
+        ));
+        let mut flipped = signature.to_bytes().to_vec();
+        flipped[0] ^= 1;
+        assert!(matches!(
+            verify_detached(&key, b"sha256:abc123", &flipped),
+            Err(SigningError::Rejected)
+        ));
+        assert!(matches!(
+            verify_detached(&key, b"sha256:abc123", b"not a signature"),
+            Err(SigningError::MalformedSignature { .. })
+        ));
+    }
+
+    #[test]
+    fn wrong_key_is_rejected_and_any_trusted_key_accepts() {
+        let old = keypair();
+        let new = keypair();
+        let stranger = keypair();
+        let data = b"sha256:abc123";
+
+        assert!(matches!(
+            verify_detached(&public_key(&old), data, &new.sign(data).to_bytes()),
+            Err(SigningError::Rejected)
+        ));
+
+        let trusted =
+            TrustedKeys::from_pem(&format!("# rotating\n{}{}", pem(&old), pem(&new))).unwrap();
+        assert_eq!(trusted.keys().len(), 2);
+        trusted.verify(data, &old.sign(data).to_bytes()).unwrap();
+        trusted.verify(data, &new.sign(data).to_bytes()).unwrap();
+        assert!(matches!(
+            trusted.verify(data, &stranger.sign(data).to_bytes()),
+            Err(SigningError::Rejected)
+        ));
+        assert!(TrustedKeys::from_pem("no keys here").is_err());
+    }
+}
@@ -29,3 +29,135 @@ impl fmt::Display for IntegrityError {
 }
 
 impl Error for IntegrityError {}
+
+/// Registry lookup failures callers need to tell apart from generic transport errors.
+#[derive(Debug, Clone)]
+pub enum RegistryLookupError {
+    /// The lookup (including retries) did not finish within the configured budget.
+    Timeout { timeout_ms: u64 },
+}
+
+impl fmt::Display for RegistryLookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryLookupError::Timeout { timeout_ms } => {
+                write!(f, "registry lookup timed out after {}ms", timeout_ms)
+            }
+        }
+    }
+}
+
+impl Error for RegistryLookupError {}
+
+/// Bundle download failures that map to a distinct response status.
+#[derive(Debug, Clone)]
+pub enum BundleFetchError {
+    /// The bundle store did not deliver the archive within `BUNDLE_FETCH_TIMEOUT_MS`.
+    Timeout { timeout_ms: u64 },
+}
+
+impl fmt::Display for BundleFetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleFetchError::Timeout { timeout_ms } => {
+                write!(f, "bundle fetch timed out after {}ms", timeout_ms)
+            }
+        }
+    }
+}
+
+impl Error for BundleFetchError {}
+
+/// Bundle signature failures; only raised when a trust bundle is configured.
+#[derive(Debug, Clone)]
+pub enum SigningError {
+    /// A trusted public key could not be parsed.
+    InvalidKey { reason: String },
+    /// The bundle store has no detached signature for the bundle.
+    Missing,
+    /// The detached signature is not a 64-byte Ed25519 signature.
+    MalformedSignature { reason: String },
+    /// No trusted key verifies the signature.
+    Rejected,
+}
+
+impl fmt::Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningError::InvalidKey { reason } => write!(f, "invalid signing key: {}", reason),
+            SigningError::Missing => write!(f, "bundle has no detached signature"),
+            SigningError::MalformedSignature { reason } => {
+                write!(f, "malformed bundle signature: {}", reason)
+            }
+            SigningError::Rejected => write!(f, "bundle signature not made by a trusted key"),
+        }
+    }
+}
+
+impl Error for SigningError {}
+
+/// Bundle cache refusals raised before anything is downloaded or extracted.
+#[derive(Debug, Clone)]
+pub enum CacheSpaceError {
+    /// Free space on the cache filesystem is below `EXT_CACHE_MIN_FREE_BYTES`.
+    LowDiskSpace {
+        available_bytes: u64,
+        min_free_bytes: u64,
+    },
+}
+
+impl fmt::Display for CacheSpaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheSpaceError::LowDiskSpace {
+                available_bytes,
+                min_free_bytes,
+            } => {
+                write!(
+                    f,
+                    "bundle cache low on disk space: {} bytes free, {} required",
+                    available_bytes, min_free_bytes
+                )
+            }
+        }
+    }
+}
+
+impl Error for CacheSpaceError {}
+
+/// Wasmtime pooling allocator failures; the runner is at capacity rather than broken.
+#[derive(Debug, Clone)]
+pub enum InstancePoolError {
+    /// No free instance, memory, table or stack slot (see `WASM_POOL_*`).
+    Exhausted { detail: String },
+}
+
+impl fmt::Display for InstancePoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstancePoolError::Exhausted { detail } => {
+                write!(f, "wasm instance pool exhausted: {}", detail)
+            }
+        }
+    }
+}
+
+impl Error for InstancePoolError {}
+
+/// Every problem found while reading the runner's startup configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid runner configuration")?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigError {}
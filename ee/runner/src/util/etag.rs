@@ -1,48 +1,55 @@
-use anyhow::Result;
 use sha2::{Digest, Sha256};
 use std::path::Path;
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, BufReader};
 
-/// Compute a strong ETag for the file using SHA-256 hex, formatted as:
+/// Compute a strong ETag for a file inside a content-addressed bundle, formatted as:
 /// "sha256-<hex>"
-pub async fn etag_for_file(path: &Path) -> Result<String> {
-    let file = File::open(path).await?;
-    let mut reader = BufReader::new(file);
+///
+/// Bundle contents never change for a given content hash, so hashing the bundle hash
+/// together with the file's path inside the bundle identifies the bytes without
+/// reading them.
+pub fn etag_for_asset(content_hash_hex: &str, rel_path: &Path) -> String {
     let mut hasher = Sha256::new();
-    let mut buf = vec![0u8; 64 * 1024];
-
-    loop {
-        let n = reader.read(&mut buf).await?;
-        if n == 0 {
-            break;
-        }
-        hasher.update(&buf[..n]);
-    }
+    hasher.update(content_hash_hex.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(rel_path.to_string_lossy().as_bytes());
+    let hex = hex::encode(hasher.finalize());
+    format!("\"sha256-{}\"", hex)
+}
 
-    let hash = hasher.finalize();
-    let hex = hex::encode(hash);
-    Ok(format!("\"sha256-{}\"", hex))
+/// If-None-Match uses weak comparison, so `W/"x"` matches `"x"`.
+pub fn etag_match(inm_header: &str, etag: &str) -> bool {
+    inm_header
+        .split(',')
+        .map(|s| s.trim())
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
-    use tokio::io::AsyncWriteExt;
-
-    #[tokio::test]
-    async fn deterministic() {
-        let mut tf = NamedTempFile::new().unwrap();
-        tf.as_file_mut().write_all(b"hello world").unwrap();
-        tf.as_file_mut().flush().unwrap();
 
-        let p = tf.path().to_path_buf();
-        let e1 = etag_for_file(&p).await.unwrap();
-        let e2 = etag_for_file(&p).await.unwrap();
+    #[test]
+    fn deterministic() {
+        let e1 = etag_for_asset("abc123", Path::new("assets/app.js"));
+        let e2 = etag_for_asset("abc123", Path::new("assets/app.js"));
         assert_eq!(e1, e2);
         assert!(e1.starts_with("\"sha256-"), "etag format: {}", e1);
         assert!(e1.ends_with("\""));
     }
+
+    #[test]
+    fn varies_with_bundle_and_path() {
+        let base = etag_for_asset("abc123", Path::new("index.html"));
+        assert_ne!(base, etag_for_asset("def456", Path::new("index.html")));
+        assert_ne!(base, etag_for_asset("abc123", Path::new("assets/app.js")));
+    }
+
+    #[test]
+    fn if_none_match_lists_and_weak_tags() {
+        let etag = "\"sha256-aa\"";
+        assert!(etag_match("\"sha256-aa\"", etag));
+        assert!(etag_match("\"other\", W/\"sha256-aa\"", etag));
+        assert!(etag_match("*", etag));
+        assert!(!etag_match("\"sha256-bb\"", etag));
+    }
 }
@@ -1,15 +1,9 @@
 use anyhow::{anyhow, Result};
-use std::env;
 use std::path::Path;
 use tokio::fs;
 
-/// Read EXT_STATIC_MAX_FILE_BYTES from env; return None if unset or invalid.
-pub fn max_file_bytes_from_env() -> Option<u64> {
-    match env::var("EXT_STATIC_MAX_FILE_BYTES") {
-        Ok(v) => v.trim().parse().ok(),
-        Err(_) => None,
-    }
-}
+use crate::models::Limits;
+use crate::registry::client::ResourcePolicy;
 
 /// Enforce a maximum file size based on metadata length. Returns 413-like error.
 pub async fn enforce_max_file_size(path: &Path, max: u64) -> Result<()> {
@@ -21,24 +15,79 @@ pub async fn enforce_max_file_size(path: &Path, max: u64) -> Result<()> {
     Ok(())
 }
 
+/// What to do with execute limits above the resource policy (`EXT_LIMIT_POLICY_MODE`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitPolicyMode {
+    /// Lower the limit to the ceiling and run (default).
+    Clamp,
+    /// Refuse the execution.
+    Reject,
+}
+
+/// Registry ceilings take precedence; gaps fall back to the runner-wide ones.
+pub fn effective_policy(
+    registry: Option<ResourcePolicy>,
+    runner: ResourcePolicy,
+) -> ResourcePolicy {
+    let registry = registry.unwrap_or_default();
+    ResourcePolicy {
+        max_memory_mb: registry.max_memory_mb.or(runner.max_memory_mb),
+        max_timeout_ms: registry.max_timeout_ms.or(runner.max_timeout_ms),
+    }
+}
+
+/// A requested (or defaulted) limit above the policy ceiling.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LimitOverage {
+    pub field: &'static str,
+    /// `None` when the caller left the limit unset and the default exceeds the ceiling.
+    pub requested: Option<u64>,
+    pub max: u64,
+}
+
+/// Lower `limits` to the policy ceilings and report what was changed. Unset limits are compared
+/// against the engine defaults; with no default timeout either, there is no deadline, which
+/// always exceeds a ceiling.
+pub fn clamp_limits(
+    limits: &mut Limits,
+    policy: &ResourcePolicy,
+    default_memory_mb: u64,
+    default_timeout_ms: Option<u64>,
+) -> Vec<LimitOverage> {
+    let mut overages = Vec::new();
+    if let Some(max) = policy.max_memory_mb {
+        if limits.memory_mb.unwrap_or(default_memory_mb) > max {
+            overages.push(LimitOverage {
+                field: "memory_mb",
+                requested: limits.memory_mb,
+                max,
+            });
+            limits.memory_mb = Some(max);
+        }
+    }
+    if let Some(max) = policy.max_timeout_ms {
+        if limits
+            .timeout_ms
+            .or(default_timeout_ms)
+            .is_none_or(|ms| ms > max)
+        {
+            overages.push(LimitOverage {
+                field: "timeout_ms",
+                requested: limits.timeout_ms,
+                max,
+            });
+            limits.timeout_ms = Some(max);
+        }
+    }
+    overages
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
-    #[test]
-    fn env_parsing() {
-        std::env::remove_var("EXT_STATIC_MAX_FILE_BYTES");
-        assert_eq!(max_file_bytes_from_env(), None);
-
-        std::env::set_var("EXT_STATIC_MAX_FILE_BYTES", "1000");
-        assert_eq!(max_file_bytes_from_env(), Some(1000));
-
-        std::env::set_var("EXT_STATIC_MAX_FILE_BYTES", "not-a-number");
-        assert_eq!(max_file_bytes_from_env(), None);
-    }
-
     #[tokio::test]
     async fn size_enforcement() {
         let mut tf = NamedTempFile::new().unwrap();
@@ -52,4 +101,101 @@ mod tests {
         let err = enforce_max_file_size(&p, 5).await.unwrap_err();
         assert!(err.to_string().contains("payload too large"));
     }
+
+    #[test]
+    fn limits_above_policy_are_clamped() {
+        let policy = ResourcePolicy {
+            max_memory_mb: Some(128),
+            max_timeout_ms: Some(5_000),
+        };
+        let mut limits = Limits {
+            timeout_ms: Some(60_000),
+            memory_mb: Some(4_096),
+            fuel: None,
+        };
+
+        let overages = clamp_limits(&mut limits, &policy, 256, None);
+        assert_eq!(limits.memory_mb, Some(128));
+        assert_eq!(limits.timeout_ms, Some(5_000));
+        assert_eq!(
+            overages,
+            vec![
+                LimitOverage {
+                    field: "memory_mb",
+                    requested: Some(4_096),
+                    max: 128
+                },
+                LimitOverage {
+                    field: "timeout_ms",
+                    requested: Some(60_000),
+                    max: 5_000
+                },
+            ]
+        );
+
+        // Within policy: untouched.
+        let mut within = Limits {
+            timeout_ms: Some(1_000),
+            memory_mb: Some(64),
+            fuel: None,
+        };
+        assert!(clamp_limits(&mut within, &policy, 256, None).is_empty());
+        assert_eq!(
+            (within.memory_mb, within.timeout_ms),
+            (Some(64), Some(1_000))
+        );
+
+        // Unset limits pick up the ceiling when the defaults exceed it.
+        let mut unset = Limits::default();
+        assert_eq!(clamp_limits(&mut unset, &policy, 256, None).len(), 2);
+        assert_eq!(
+            (unset.memory_mb, unset.timeout_ms),
+            (Some(128), Some(5_000))
+        );
+    }
+
+    #[test]
+    fn unset_timeout_uses_engine_default() {
+        let policy = ResourcePolicy {
+            max_memory_mb: None,
+            max_timeout_ms: Some(30_000),
+        };
+
+        // The engine default is within the ceiling: nothing to clamp or report.
+        let mut unset = Limits::default();
+        assert!(clamp_limits(&mut unset, &policy, 256, Some(2_000)).is_empty());
+        assert_eq!(unset.timeout_ms, None);
+
+        // A default above the ceiling is lowered like an explicit request.
+        let mut unset = Limits::default();
+        assert_eq!(
+            clamp_limits(&mut unset, &policy, 256, Some(60_000)),
+            vec![LimitOverage {
+                field: "timeout_ms",
+                requested: None,
+                max: 30_000
+            }]
+        );
+        assert_eq!(unset.timeout_ms, Some(30_000));
+    }
+
+    #[test]
+    fn registry_policy_overrides_runner_ceiling() {
+        let runner = ResourcePolicy {
+            max_memory_mb: Some(512),
+            max_timeout_ms: Some(30_000),
+        };
+        let registry = ResourcePolicy {
+            max_memory_mb: Some(128),
+            max_timeout_ms: None,
+        };
+        assert_eq!(
+            effective_policy(Some(registry), runner),
+            ResourcePolicy {
+                max_memory_mb: Some(128),
+                max_timeout_ms: Some(30_000),
+            }
+        );
+        assert_eq!(effective_policy(None, runner), runner);
+    }
 }
@@ -1,8 +1,22 @@
 use axum::http::HeaderValue;
 use std::path::Path;
 
+/// Types browsers enforce strictly: module scripts must be JavaScript and
+/// `WebAssembly.instantiateStreaming` requires `application/wasm`.
+fn pinned_content_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "js" | "mjs" => Some("text/javascript"),
+        "wasm" => Some("application/wasm"),
+        _ => None,
+    }
+}
+
 /// Resolve Content-Type using mime_guess, defaulting to application/octet-stream.
 pub fn content_type_for(path: &Path) -> HeaderValue {
+    if let Some(pinned) = pinned_content_type(path) {
+        return HeaderValue::from_static(pinned);
+    }
     let mime = mime_guess::from_path(path).first_or_octet_stream();
     // It's safe to unwrap since mime types are valid header values
     HeaderValue::from_str(mime.essence_str())
@@ -20,6 +34,9 @@ mod tests {
             ("index.html", "text/html"),
             // mime_guess returns text/javascript for .js
             ("app.js", "text/javascript"),
+            ("module.mjs", "text/javascript"),
+            ("app_bg.wasm", "application/wasm"),
+            ("APP.WASM", "application/wasm"),
             ("styles.css", "text/css"),
             ("data.json", "application/json"),
             ("image.svg", "image/svg+xml"),
@@ -3,3 +3,8 @@ pub mod etag;
 pub mod limits;
 pub mod mime;
 pub mod path_sanitize;
+pub mod range;
+pub mod redact;
+pub mod request_id;
+#[cfg(test)]
+pub(crate) mod test_logs;
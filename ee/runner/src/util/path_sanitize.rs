@@ -4,7 +4,8 @@ use thiserror::Error;
 
 /// Allowed file extensions for static UI assets.
 const ALLOWLIST_EXTS: &[&str] = &[
-    "html", "js", "css", "json", "map", "svg", "png", "jpg", "jpeg", "webp", "woff", "woff2",
+    "html", "js", "mjs", "wasm", "css", "json", "map", "svg", "png", "jpg", "jpeg", "webp", "woff",
+    "woff2",
 ];
 
 #[derive(Debug, Error)]
@@ -155,6 +156,8 @@ mod tests {
     fn enforces_allowlist() {
         assert!(sanitize("index.html").is_ok());
         assert!(sanitize("script.js").is_ok());
+        assert!(sanitize("module.mjs").is_ok());
+        assert!(sanitize("pkg/app_bg.wasm").is_ok());
         assert!(sanitize("data.json").is_ok());
         assert!(matches!(
             sanitize("bad.exe"),
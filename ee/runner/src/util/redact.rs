@@ -0,0 +1,215 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Characters of a body kept when it is logged.
+pub const BODY_SAMPLE_MAX_CHARS: usize = 200;
+
+/// JSON keys whose values are never logged.
+const SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "access_token",
+    "refresh_token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "ssn",
+    "social_security_number",
+    "tax_id",
+    "card_number",
+    "cvv",
+];
+
+/// Whether [`body_sample`] masks sensitive values; set once at startup from the validated
+/// `LOG_BODY_REDACTION` setting (default on).
+static BODY_REDACTION_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn body redaction off only for local debugging, where truncated bodies are logged verbatim.
+pub fn set_body_redaction(enabled: bool) {
+    BODY_REDACTION_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Prepare an upstream/downstream body for logging: mask sensitive values and truncate.
+pub fn body_sample(body: &str) -> String {
+    sample(body, BODY_REDACTION_ENABLED.load(Ordering::Relaxed))
+}
+
+fn sample(body: &str, redact: bool) -> String {
+    let text = if redact {
+        redact_body(body)
+    } else {
+        body.to_string()
+    };
+    truncate(&text, BODY_SAMPLE_MAX_CHARS)
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let mut out: String = text.chars().take(max_chars).collect();
+    if text.chars().nth(max_chars).is_some() {
+        out.push('…');
+    }
+    out
+}
+
+/// Mask sensitive JSON fields (when the body is JSON) and PII-shaped substrings anywhere.
+pub fn redact_body(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(mut value) if value.is_object() || value.is_array() => {
+            redact_json(&mut value);
+            value.to_string()
+        }
+        _ => redact_text(body),
+    }
+}
+
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if SENSITIVE_KEYS.contains(&key.to_ascii_lowercase().as_str()) {
+                    *v = serde_json::Value::String("***".to_string());
+                } else {
+                    redact_json(v);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        serde_json::Value::String(s) => *s = redact_text(s),
+        _ => {}
+    }
+}
+
+/// Mask SSN-shaped numbers, card-length digit runs and email addresses.
+pub fn redact_text(text: &str) -> String {
+    mask_emails(&mask_digit_runs(text))
+}
+
+fn mask_digit_runs(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_run = chars[i].is_ascii_digit() && (i == 0 || !chars[i - 1].is_alphanumeric());
+        if !starts_run {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        // Digits optionally separated by single '-' or ' ' (e.g. 123-45-6789, 4111 1111 1111 1111).
+        let mut end = i;
+        let mut j = i;
+        while j < chars.len() {
+            if chars[j].is_ascii_digit() {
+                j += 1;
+                end = j;
+            } else if (chars[j] == '-' || chars[j] == ' ')
+                && chars.get(j + 1).is_some_and(|c| c.is_ascii_digit())
+            {
+                j += 1;
+            } else {
+                break;
+            }
+        }
+        let bounded = chars.get(end).is_none_or(|c| !c.is_alphanumeric());
+        let run: String = chars[i..end].iter().collect();
+        let digits = run.chars().filter(|c| c.is_ascii_digit()).count();
+        if bounded && is_ssn_shape(&run) {
+            out.push_str("***-**-****");
+        } else if bounded && (13..=19).contains(&digits) {
+            out.push_str("[redacted-number]");
+        } else {
+            out.push_str(&run);
+        }
+        i = end;
+    }
+    out
+}
+
+fn is_ssn_shape(run: &str) -> bool {
+    let groups: Vec<&str> = run.split('-').collect();
+    groups.len() == 3
+        && groups
+            .iter()
+            .zip([3, 2, 4])
+            .all(|(g, len)| g.len() == len && g.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn mask_emails(text: &str) -> String {
+    let is_local = |c: char| c.is_ascii_alphanumeric() || "._%+-".contains(c);
+    let is_domain = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '-';
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (at, &c) in chars.iter().enumerate() {
+        if c != '@' || at < copied {
+            continue;
+        }
+        let mut start = at;
+        while start > copied && is_local(chars[start - 1]) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < chars.len() && is_domain(chars[end]) {
+            end += 1;
+        }
+        let domain: String = chars[at + 1..end].iter().collect();
+        let domain = domain.trim_end_matches('.');
+        if start == at || !domain.contains('.') {
+            continue;
+        }
+        out.extend(&chars[copied..start]);
+        out.push_str("[redacted-email]");
+        copied = at + 1 + domain.chars().count();
+    }
+    out.extend(&chars[copied..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_ssn_in_body_sample() {
+        let logged = sample("{\"note\":\"customer ssn 123-45-6789 on file\"}", true);
+        assert!(!logged.contains("123-45-6789"), "{logged}");
+        assert!(logged.contains("***-**-****"), "{logged}");
+
+        let logged = sample("error: applicant 123-45-6789 rejected", true);
+        assert_eq!(logged, "error: applicant ***-**-**** rejected");
+    }
+
+    #[test]
+    fn masks_cards_emails_and_sensitive_keys() {
+        let logged = sample(
+            "{\"token\":\"abc\",\"contact\":\"jane.doe@example.com\",\"card\":\"4111 1111 1111 1111\"}",
+            true,
+        );
+        assert!(!logged.contains("abc"));
+        assert!(!logged.contains("jane.doe@example.com"));
+        assert!(!logged.contains("4111"));
+        assert!(logged.contains("[redacted-email]"));
+        assert!(logged.contains("[redacted-number]"));
+    }
+
+    #[test]
+    fn leaves_ordinary_text_and_truncates() {
+        assert_eq!(
+            redact_text("order 42 shipped 2024-01-02"),
+            "order 42 shipped 2024-01-02"
+        );
+        assert_eq!(redact_text("id abc123-45-6789x"), "id abc123-45-6789x");
+
+        let long = "a".repeat(BODY_SAMPLE_MAX_CHARS + 10);
+        let logged = sample(&long, true);
+        assert_eq!(logged.chars().count(), BODY_SAMPLE_MAX_CHARS + 1);
+        assert!(logged.ends_with('…'));
+    }
+
+    #[test]
+    fn disabled_redaction_only_truncates() {
+        assert_eq!(sample("ssn 123-45-6789", false), "ssn 123-45-6789");
+    }
+}
@@ -0,0 +1,35 @@
+//! Correlation ids for `/v1/execute` and the outbound calls an execution makes.
+
+/// Header carrying the correlation id on inbound and outbound requests.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// A fresh id for requests that arrive without one.
+pub fn generate() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The first non-blank candidate (header before execute context), or a generated id.
+pub fn resolve(header: Option<&str>, context: Option<&str>) -> String {
+    [header, context]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|id| !id.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(generate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_supplied_ids_and_generates_otherwise() {
+        assert_eq!(resolve(Some(" req-1 "), Some("ctx-1")), "req-1");
+        assert_eq!(resolve(Some(""), Some("ctx-1")), "ctx-1");
+
+        let generated = resolve(None, Some("  "));
+        assert!(uuid::Uuid::parse_str(&generated).is_ok(), "{generated}");
+        assert_ne!(generated, resolve(None, None));
+    }
+}
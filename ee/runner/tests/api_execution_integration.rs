@@ -28,7 +28,8 @@ async fn start_runner_server() -> anyhow::Result<(String, JoinHandle<()>)> {
 
     // Spawn the runner
     let handle = tokio::spawn(async move {
-        if let Err(e) = alga_ext_runner::http::server::run().await {
+        let config = alga_ext_runner::config::RunnerConfig::from_env().expect("runner config");
+        if let Err(e) = alga_ext_runner::http::server::run(config).await {
             eprintln!("Runner server exited with error: {}", e);
         }
     });
@@ -77,6 +78,7 @@ async fn test_execute_endpoint_structure() -> anyhow::Result<()> {
             content_hash: "sha256:dummy".to_string(),
             version_id: Some("v1".to_string()),
             config: HashMap::new(),
+            egress_allowlist: None,
         },
         http: HttpPayload {
             method: "POST".to_string(),
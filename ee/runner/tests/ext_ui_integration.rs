@@ -1,15 +1,11 @@
 use axum::{
-    body,
     body::Body,
-    extract::State,
     http::{header, HeaderValue, Method, Request, StatusCode},
     response::IntoResponse,
     routing::{get, post},
-    Json, Router,
+    Router,
 };
 use bytes::Bytes;
-use serde_json::json;
-use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
@@ -20,15 +16,18 @@ use tower::util::ServiceExt;
 use url::Url;
 use zstd::stream::encode_all as zstd_encode_all;
 
-use alga_ext_runner::http::ext_ui::{handle_get, warmup, AppState as ExtState, WarmupReq};
+use alga_ext_runner::config::RunnerConfig;
+use alga_ext_runner::http::ext_ui::{handle_get, warmup, AppState as ExtState};
 use alga_ext_runner::registry::client::RegistryClient;
-use alga_ext_runner::util::limits;
 use serial_test::serial;
 
 // Added imports
 use alga_ext_runner::cache::fs as cache_fs;
-use alga_ext_runner::engine::loader::verify_archive_sha256;
-use alga_ext_runner::util::errors::IntegrityError;
+use alga_ext_runner::cache::space::{CacheSpaceGuard, DEFAULT_CACHE_MIN_FREE_BYTES};
+use alga_ext_runner::engine::loader::{
+    verify_archive_sha256, BundleCachePolicy, DEFAULT_BUNDLE_FETCH_TIMEOUT,
+};
+use alga_ext_runner::util::errors::{BundleFetchError, IntegrityError};
 
 struct AllowingRegistry;
 #[async_trait::async_trait]
@@ -80,6 +79,15 @@ fn make_bundle_tarzst() -> (Vec<u8>, String) {
         tar.append_data(&mut hdr2, "ui/assets/app.js", &js[..])
             .unwrap();
 
+        // assets/module.wasm (wasm magic + version)
+        let mut hdr3 = tar::Header::new_gnu();
+        let wasm = b"\0asm\x01\0\0\0";
+        hdr3.set_size(wasm.len() as u64);
+        hdr3.set_mode(0o644);
+        hdr3.set_cksum();
+        tar.append_data(&mut hdr3, "ui/assets/module.wasm", &wasm[..])
+            .unwrap();
+
         tar.finish().unwrap();
     }
 
@@ -95,9 +103,9 @@ fn make_bundle_tarzst() -> (Vec<u8>, String) {
 }
 
 async fn start_bundle_http_server(bytes: Vec<u8>) -> (Url, JoinHandle<()>) {
-    // Serve at /sha256/:hex/bundle.tar.zst (hex extracted from request path but we ignore and always return same bytes)
+    // Serve the same bytes for any object key (tenant-scoped keys and bare sha256/<hex>/bundle.tar.zst alike)
     let app = Router::new().route(
-        "/sha256/:hex/bundle.tar.zst",
+        "/*key",
         get({
             let blob = Bytes::from(bytes);
             move || {
@@ -123,23 +131,54 @@ async fn start_bundle_http_server(bytes: Vec<u8>) -> (Url, JoinHandle<()>) {
     (base, handle)
 }
 
+async fn start_stalled_bundle_server(delay: Duration) -> (Url, JoinHandle<()>) {
+    let app = Router::new().route(
+        "/*key",
+        get(move || async move {
+            tokio::time::sleep(delay).await;
+            StatusCode::OK
+        }),
+    );
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (Url::parse(&format!("http://{}/", addr)).unwrap(), handle)
+}
+
+/// Default free-space floor; bundles are checked against their SHA-256 only.
+fn unsigned_policy() -> Arc<BundleCachePolicy> {
+    Arc::new(BundleCachePolicy::new(
+        CacheSpaceGuard::statvfs(DEFAULT_CACHE_MIN_FREE_BYTES),
+        None,
+    ))
+}
+
+/// Configuration from `vars` plus a placeholder registry URL.
+fn test_config(vars: &[(&str, &str)]) -> RunnerConfig {
+    RunnerConfig::from_lookup(|key| {
+        if let Some((_, value)) = vars.iter().find(|(name, _)| *name == key) {
+            return Some(value.to_string());
+        }
+        (key == "REGISTRY_BASE_URL").then(|| "http://127.0.0.1:1/".to_string())
+    })
+    .unwrap()
+}
+
 fn make_test_state(
     cache_root: PathBuf,
     bundle_base: Url,
     strict: bool,
     registry: Arc<dyn RegistryClient + Send + Sync>,
 ) -> ExtState {
-    // Control strict validation via env the handler reads
-    std::env::set_var(
-        "EXT_STATIC_STRICT_VALIDATION",
-        if strict { "true" } else { "false" },
-    );
-
     ExtState {
         registry,
         cache_root,
         bundle_store_base: bundle_base,
-        max_file_bytes: limits::max_file_bytes_from_env(),
+        max_file_bytes: test_config(&[]).max_file_bytes,
+        strict_validation: strict,
+        cache_policy: unsigned_policy(),
     }
 }
 
@@ -150,13 +189,6 @@ fn router_for_state(state: ExtState) -> Router {
         .with_state(state)
 }
 
-async fn request<R>(app: &Router, req: Request<Body>) -> axum::http::Response<Body>
-where
-    R: IntoResponse,
-{
-    app.clone().oneshot(req).await.unwrap()
-}
-
 #[tokio::test]
 #[serial]
 async fn cold_fetch_then_304() {
@@ -171,21 +203,7 @@ async fn cold_fetch_then_304() {
     let state = make_test_state(cache_root.clone(), base, false, Arc::new(AllowingRegistry));
     let app = router_for_state(state.clone());
 
-    // Warmup
-    let warm = Request::builder()
-        .method(Method::POST)
-        .uri("/warmup")
-        .header(header::CONTENT_TYPE, "application/json")
-        .body(Body::from(
-            serde_json::to_vec(&WarmupReq {
-                content_hash: format!("sha256:{}", hex),
-            })
-            .unwrap(),
-        ))
-        .unwrap();
-    let warm_resp = request::<Json<serde_json::Value>>(&app, warm).await;
-    assert_eq!(warm_resp.status(), StatusCode::OK);
-
+    // First GET fetches and extracts the bundle
     // GET index.html
     let get1 = Request::builder()
         .method(Method::GET)
@@ -225,11 +243,134 @@ async fn cold_fetch_then_304() {
         .method(Method::GET)
         .uri(format!("/ext-ui/demo-ext/sha256:{}/index.html", hex))
         .header("x-tenant-id", "tenant-a")
-        .header(header::IF_NONE_MATCH, etag)
+        .header(header::IF_NONE_MATCH, etag.as_str())
         .body(Body::empty())
         .unwrap();
     let resp2 = app.clone().oneshot(get2).await.unwrap();
     assert_eq!(resp2.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(
+        resp2.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+        etag
+    );
+    let body = axum::body::to_bytes(resp2.into_body(), 1024).await.unwrap();
+    assert!(body.is_empty(), "304 must not carry a body");
+}
+
+async fn get_app_js(app: &Router, hex: &str, range: Option<&str>) -> axum::http::Response<Body> {
+    let mut builder = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/ext-ui/demo-ext/sha256:{}/assets/app.js", hex))
+        .header("x-tenant-id", "tenant-a");
+    if let Some(r) = range {
+        builder = builder.header(header::RANGE, r);
+    }
+    app.clone()
+        .oneshot(builder.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn range_requests_return_partial_content() {
+    let (buf, hex) = make_bundle_tarzst();
+    let (base, _handle) = start_bundle_http_server(buf).await;
+    let tmpdir = tempfile::tempdir().unwrap();
+    let state = make_test_state(
+        tmpdir.path().to_path_buf(),
+        base,
+        false,
+        Arc::new(AllowingRegistry),
+    );
+    let app = router_for_state(state);
+
+    // app.js is "console.log('hello');" (21 bytes)
+    let resp = get_app_js(&app, &hex, Some("bytes=0-6")).await;
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_RANGE).unwrap(),
+        "bytes 0-6/21"
+    );
+    let body = axum::body::to_bytes(resp.into_body(), 1024).await.unwrap();
+    assert_eq!(&body[..], b"console");
+
+    let resp = get_app_js(&app, &hex, Some("bytes=100-")).await;
+    assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_RANGE).unwrap(),
+        "bytes */21"
+    );
+
+    let resp = get_app_js(&app, &hex, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+    let body = axum::body::to_bytes(resp.into_body(), 1024).await.unwrap();
+    assert_eq!(&body[..], b"console.log('hello');");
+}
+
+#[tokio::test]
+#[serial]
+async fn pruned_asset_is_re_extracted() {
+    let (buf, hex) = make_bundle_tarzst();
+    let (base, _handle) = start_bundle_http_server(buf).await;
+    let tmpdir = tempfile::tempdir().unwrap();
+    let state = make_test_state(
+        tmpdir.path().to_path_buf(),
+        base,
+        false,
+        Arc::new(AllowingRegistry),
+    );
+    let app = router_for_state(state);
+
+    let resp = get_app_js(&app, &hex, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Prune the asset behind the runner's back; the bundle is still marked verified in-process
+    let app_js = cache_fs::ui_cache_dir(tmpdir.path(), &hex).join("assets/app.js");
+    std::fs::remove_file(&app_js).unwrap();
+
+    let resp = get_app_js(&app, &hex, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let ct = resp.headers().get(header::CONTENT_TYPE).unwrap();
+    assert!(ct.to_str().unwrap().contains("javascript"), "{ct:?}");
+    let body = axum::body::to_bytes(resp.into_body(), 1024).await.unwrap();
+    assert_eq!(&body[..], b"console.log('hello');");
+    assert!(app_js.exists());
+}
+
+#[tokio::test]
+#[serial]
+async fn assets_are_served_with_browser_content_types() {
+    let (buf, hex) = make_bundle_tarzst();
+    let (base, _handle) = start_bundle_http_server(buf).await;
+    let tmpdir = tempfile::tempdir().unwrap();
+    let state = make_test_state(
+        tmpdir.path().to_path_buf(),
+        base,
+        false,
+        Arc::new(AllowingRegistry),
+    );
+    let app = router_for_state(state);
+
+    for (path, expected) in [
+        ("assets/module.wasm", "application/wasm"),
+        ("assets/app.js", "text/javascript"),
+    ] {
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(format!("/ext-ui/demo-ext/sha256:{}/{}", hex, path))
+            .header("x-tenant-id", "tenant-a")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK, "{}", path);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            expected,
+            "{}",
+            path
+        );
+    }
 }
 
 #[tokio::test]
@@ -266,19 +407,81 @@ async fn traversal_is_rejected() {
 
     let tmpdir = tempfile::tempdir().unwrap();
     let cache_root = tmpdir.path().to_path_buf();
-    let state = make_test_state(cache_root, base, false, Arc::new(AllowingRegistry));
+    let state = make_test_state(cache_root.clone(), base, false, Arc::new(AllowingRegistry));
+    let app = router_for_state(state);
+
+    for path in [
+        "../secret.txt",
+        "../../etc/passwd",
+        "assets/../../../etc/passwd",
+        "%2e%2e/%2e%2e/etc/passwd",
+        "..%2f..%2fetc%2fpasswd",
+        "/etc/passwd.json",
+        "index.html%00.png",
+    ] {
+        let get = Request::builder()
+            .method(Method::GET)
+            .uri(format!("/ext-ui/demo-ext/sha256:{}/{}", hex, path))
+            .header("x-tenant-id", "tenant-a")
+            .body(Body::empty())
+            .unwrap();
+
+        let resp = app.clone().oneshot(get).await.unwrap();
+        // Our sanitizer returns 400 for invalid path
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", path);
+    }
+    // Rejected before any bundle fetch
+    assert!(!cache_fs::exists_ui_index(&cache_root, &hex));
+
+    // A legitimate nested path is still served
+    let get = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/ext-ui/demo-ext/sha256:{}/assets/app.js", hex))
+        .header("x-tenant-id", "tenant-a")
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.clone().oneshot(get).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), 1024).await.unwrap();
+    assert_eq!(&body[..], b"console.log('hello');");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+#[serial]
+async fn symlink_escaping_bundle_root_is_not_served() {
+    let (buf, hex) = make_bundle_tarzst();
+    let (base, _handle) = start_bundle_http_server(buf).await;
+
+    let tmpdir = tempfile::tempdir().unwrap();
+    let cache_root = tmpdir.path().to_path_buf();
+    let state = make_test_state(cache_root.clone(), base, false, Arc::new(AllowingRegistry));
     let app = router_for_state(state);
 
+    // Populate the cache, then plant a link pointing outside the UI root
     let get = Request::builder()
         .method(Method::GET)
-        .uri(format!("/ext-ui/demo-ext/sha256:{}/../secret.txt", hex))
+        .uri(format!("/ext-ui/demo-ext/sha256:{}/index.html", hex))
         .header("x-tenant-id", "tenant-a")
         .body(Body::empty())
         .unwrap();
+    assert_eq!(
+        app.clone().oneshot(get).await.unwrap().status(),
+        StatusCode::OK
+    );
+    let outside = tmpdir.path().join("outside.json");
+    std::fs::write(&outside, b"{\"secret\":true}").unwrap();
+    let ui_root = cache_fs::ui_cache_dir(&cache_root, &hex);
+    std::os::unix::fs::symlink(&outside, ui_root.join("assets/leak.json")).unwrap();
 
+    let get = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/ext-ui/demo-ext/sha256:{}/assets/leak.json", hex))
+        .header("x-tenant-id", "tenant-a")
+        .body(Body::empty())
+        .unwrap();
     let resp = app.clone().oneshot(get).await.unwrap();
-    // Our sanitizer returns 400 for invalid path
-    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
 
 #[tokio::test]
@@ -318,30 +521,17 @@ async fn oversize_asset_returns_413() {
     let cache_root = tmpdir.path().to_path_buf();
 
     // Set max file bytes small
-    std::env::set_var("EXT_STATIC_MAX_FILE_BYTES", "1024"); // 1KiB
+    let config = test_config(&[("EXT_STATIC_MAX_FILE_BYTES", "1024")]); // 1KiB
     let state = ExtState {
         registry: Arc::new(AllowingRegistry),
         cache_root,
         bundle_store_base: base,
-        max_file_bytes: limits::max_file_bytes_from_env(),
+        max_file_bytes: config.max_file_bytes,
+        strict_validation: false,
+        cache_policy: unsigned_policy(),
     };
     let app = router_for_state(state);
 
-    // Warmup
-    let warm = Request::builder()
-        .method(Method::POST)
-        .uri("/warmup")
-        .header(header::CONTENT_TYPE, "application/json")
-        .body(Body::from(
-            serde_json::to_vec(&WarmupReq {
-                content_hash: format!("sha256:{}", hex),
-            })
-            .unwrap(),
-        ))
-        .unwrap();
-    let warm_resp = app.clone().oneshot(warm).await.unwrap();
-    assert_eq!(warm_resp.status(), StatusCode::OK);
-
     // Request the oversize asset
     let get = Request::builder()
         .method(Method::GET)
@@ -351,6 +541,19 @@ async fn oversize_asset_returns_413() {
         .unwrap();
     let resp = app.clone().oneshot(get).await.unwrap();
     assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    // Rejected from metadata: the 128KiB asset is never read into the response
+    let body = axum::body::to_bytes(resp.into_body(), 1024).await.unwrap();
+    assert_eq!(&body[..], b"asset too large");
+
+    // Assets under the limit are still served
+    let get = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/ext-ui/demo-ext/sha256:{}/index.html", hex))
+        .header("x-tenant-id", "tenant-a")
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.clone().oneshot(get).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
 }
 
 // New tests for integrity and extraction failure
@@ -454,10 +657,18 @@ async fn loader_verify_archive_sha256_unit() {
         .join(&format!("sha256/{}/bundle.tar.zst", hex))
         .unwrap();
 
+    let tmpdir = tempfile::tempdir().unwrap();
+
     // Success case
-    let tmp = verify_archive_sha256(&url, &hex)
-        .await
-        .expect("verify should pass");
+    let tmp = verify_archive_sha256(
+        &url,
+        &hex,
+        tmpdir.path(),
+        DEFAULT_BUNDLE_FETCH_TIMEOUT,
+        None,
+    )
+    .await
+    .expect("verify should pass");
     assert!(std::fs::metadata(&tmp).is_ok());
     // Cleanup
     let _ = std::fs::remove_file(&tmp);
@@ -466,9 +677,15 @@ async fn loader_verify_archive_sha256_unit() {
     let bad_url = base
         .join(&format!("sha256/{}/bundle.tar.zst", "deadbeef"))
         .unwrap();
-    let err = verify_archive_sha256(&bad_url, "deadbeef")
-        .await
-        .unwrap_err();
+    let err = verify_archive_sha256(
+        &bad_url,
+        "deadbeef",
+        tmpdir.path(),
+        DEFAULT_BUNDLE_FETCH_TIMEOUT,
+        None,
+    )
+    .await
+    .unwrap_err();
     let ie = err
         .downcast_ref::<IntegrityError>()
         .expect("should be IntegrityError");
@@ -482,3 +699,61 @@ async fn loader_verify_archive_sha256_unit() {
         }
     }
 }
+
+#[tokio::test]
+#[serial]
+async fn bundle_fetch_timeout_is_reported_distinctly() {
+    let (base, _handle) = start_stalled_bundle_server(Duration::from_secs(5)).await;
+    let url = base.join("sha256/abc/bundle.tar.zst").unwrap();
+
+    let tmpdir = tempfile::tempdir().unwrap();
+
+    let started = std::time::Instant::now();
+    let err = verify_archive_sha256(&url, "abc", tmpdir.path(), Duration::from_millis(200), None)
+        .await
+        .unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(2));
+    match err.downcast_ref::<BundleFetchError>() {
+        Some(BundleFetchError::Timeout { timeout_ms }) => assert_eq!(*timeout_ms, 200),
+        None => panic!("expected BundleFetchError, got {err:?}"),
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn bundle_fetch_timeout_maps_to_504() {
+    let (base, _handle) = start_stalled_bundle_server(Duration::from_secs(5)).await;
+    let tmpdir = tempfile::tempdir().unwrap();
+    let mut state = make_test_state(
+        tmpdir.path().to_path_buf(),
+        base,
+        false,
+        Arc::new(AllowingRegistry),
+    );
+    state.cache_policy = Arc::new(
+        BundleCachePolicy::new(CacheSpaceGuard::statvfs(DEFAULT_CACHE_MIN_FREE_BYTES), None)
+            .with_fetch_timeout(Duration::from_millis(200)),
+    );
+    let app = router_for_state(state);
+
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(format!(
+            "/ext-ui/demo-ext/sha256:{}/index.html",
+            "ab".repeat(32)
+        ))
+        .header("x-tenant-id", "tenant-a")
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    let body_bytes = axum::body::to_bytes(resp.into_body(), 1024 * 1024)
+        .await
+        .unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(
+        v.get("code").and_then(|x| x.as_str()),
+        Some("bundle_fetch_timeout")
+    );
+}
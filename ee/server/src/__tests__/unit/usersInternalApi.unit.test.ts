@@ -0,0 +1,95 @@
+import { beforeEach, describe, expect, it, vi } from 'vitest'
+
+vi.mock('@ee/lib/extensions/installConfig', () => ({
+  getInstallConfigByInstallId: vi.fn(),
+}))
+
+vi.mock('@ee/lib/extensions/userReadService', () => ({
+  getExtensionUserInfo: vi.fn(),
+}))
+
+const USER_ID = '11111111-1111-4111-8111-111111111111'
+
+describe('usersInternalApi', () => {
+  beforeEach(() => {
+    vi.resetModules()
+    vi.clearAllMocks()
+    process.env.RUNNER_SERVICE_TOKEN = 'runner-test-token'
+  })
+
+  async function mockInstall(providers: string[]) {
+    const { getInstallConfigByInstallId } = await import('@ee/lib/extensions/installConfig')
+    vi.mocked(getInstallConfigByInstallId).mockResolvedValue({
+      tenantId: 'tenant-from-install',
+      installId: 'install-1',
+      versionId: 'version-1',
+      registryId: 'registry-1',
+      providers,
+    } as any)
+  }
+
+  it('rejects requests without the runner token', async () => {
+    const { handleInternalUsersInstallRequest } = await import('@ee/lib/extensions/usersInternalApi')
+    const res = await handleInternalUsersInstallRequest({
+      installId: 'install-1',
+      headers: new Headers(),
+      body: { operation: 'get', userId: USER_ID },
+    })
+
+    expect(res.status).toBe(401)
+    expect(res.body.code).toBe('UNAUTHORIZED')
+  })
+
+  it('returns not-allowed when cap:user.read is missing', async () => {
+    await mockInstall([])
+
+    const { handleInternalUsersInstallRequest } = await import('@ee/lib/extensions/usersInternalApi')
+    const res = await handleInternalUsersInstallRequest({
+      installId: 'install-1',
+      headers: new Headers({ 'x-runner-auth': 'runner-test-token' }),
+      body: { operation: 'get', userId: USER_ID },
+    })
+
+    expect(res.status).toBe(403)
+    expect(res.body.code).toBe('NOT_ALLOWED')
+  })
+
+  it('resolves the user in the install tenant', async () => {
+    await mockInstall(['cap:user.read'])
+    const { getExtensionUserInfo } = await import('@ee/lib/extensions/userReadService')
+    vi.mocked(getExtensionUserInfo).mockResolvedValue({
+      user_id: USER_ID,
+      user_email: 'u1@example.com',
+      user_name: 'User One',
+      user_type: 'internal',
+      client_name: 'Acme',
+    })
+
+    const { handleInternalUsersInstallRequest } = await import('@ee/lib/extensions/usersInternalApi')
+    const res = await handleInternalUsersInstallRequest({
+      installId: 'install-1',
+      headers: new Headers({ 'x-runner-auth': 'runner-test-token' }),
+      body: { operation: 'get', userId: USER_ID },
+    })
+
+    expect(res.status).toBe(200)
+    expect(res.body.user.user_email).toBe('u1@example.com')
+    expect(vi.mocked(getExtensionUserInfo)).toHaveBeenCalledWith('tenant-from-install', USER_ID)
+  })
+
+  it('answers USER_NOT_FOUND for an unknown user', async () => {
+    await mockInstall(['cap:user.read'])
+    const { getExtensionUserInfo } = await import('@ee/lib/extensions/userReadService')
+    vi.mocked(getExtensionUserInfo).mockResolvedValue(null)
+
+    const { handleInternalUsersInstallRequest } = await import('@ee/lib/extensions/usersInternalApi')
+    const res = await handleInternalUsersInstallRequest({
+      installId: 'install-1',
+      headers: new Headers({ 'x-runner-auth': 'runner-test-token' }),
+      body: { operation: 'get', userId: USER_ID },
+    })
+
+    expect(res.status).toBe(404)
+    expect(res.body.code).toBe('USER_NOT_FOUND')
+  })
+})
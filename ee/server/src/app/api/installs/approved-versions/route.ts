@@ -0,0 +1,41 @@
+import { NextResponse } from 'next/server';
+import { approvedVersions as approvedVersionsAction } from '@ee/lib/actions/installDomainActions';
+
+export const dynamic = 'force-dynamic';
+
+export async function GET(request: Request) {
+  const { searchParams } = new URL(request.url);
+  const tenant = searchParams.get('tenant');
+  const extension = searchParams.get('extension'); // registry_id
+  // Request logging with masked auth headers
+  try {
+    const canary = request.headers.get('x-canary');
+    const apiKey = request.headers.get('x-api-key') || '';
+    const keyPrefix = apiKey ? apiKey.slice(0, 4) : '';
+    const keyLen = apiKey ? apiKey.length : 0;
+    console.info('[installs/approved-versions] entry', { tenant, extension, x_canary: canary ?? undefined, api_key_len: keyLen, api_key_prefix: keyPrefix });
+  } catch {}
+
+  if (!tenant || !extension) {
+    const r = NextResponse.json({ error: 'missing or invalid parameters' }, { status: 400 });
+    r.headers.set('Cache-Control', 'no-store');
+    r.headers.set('Vary', 'x-api-key, x-canary');
+    return r;
+  }
+  try {
+    // Always 200: an extension with nothing approved answers an empty list, so the runner
+    // can treat a 404 as a missing route rather than "nothing approved".
+    const out = await approvedVersionsAction({ tenant, extension });
+    try { console.info('[installs/approved-versions] ok', { tenant, extension, count: out.content_hashes.length }); } catch {}
+    const r = NextResponse.json(out);
+    r.headers.set('Cache-Control', 'no-store');
+    r.headers.set('Vary', 'x-api-key, x-canary');
+    return r;
+  } catch (e) {
+    console.error('[installs/approved-versions] error', e);
+    const r = NextResponse.json({ error: 'internal error' }, { status: 500 });
+    r.headers.set('Cache-Control', 'no-store');
+    r.headers.set('Vary', 'x-api-key, x-canary');
+    return r;
+  }
+}
@@ -5,6 +5,8 @@ import { tenantDb } from '@alga-psa/db';
 import { getAdminConnection } from '@alga-psa/db/admin';
 import { assertPsaOnlyTenantAccess } from '@shared/services/productAccessGuard';
 
+const EXTENSION_REGISTRY_GLOBAL_TENANT = '__extension_registry_global__';
+
 function normalizeHost(input?: string | null): string | null {
   if (!input) return null;
   const host = input.split(':')[0].trim().toLowerCase();
@@ -71,3 +73,31 @@ export async function validate(params: { tenant: string; extension: string; hash
     .first(['id']);
   return { valid: !!bundle };
 }
+
+/** installs.approvedVersions: content hashes published for an extension the tenant has installed */
+export async function approvedVersions(params: { tenant: string; extension: string }): Promise<{ content_hashes: string[] }> {
+  const tenant = (params?.tenant || '').trim();
+  const extension = (params?.extension || '').trim();
+  if (!tenant || !extension) return { content_hashes: [] };
+  await assertPsaOnlyTenantAccess(tenant, 'extension_actions');
+
+  const db: Knex = await getAdminConnection();
+  const install = await tenantDb(db, tenant).table('tenant_extension_install')
+    .where({ registry_id: extension })
+    .first(['id']);
+  if (!install) return { content_hashes: [] };
+
+  const registryDb = tenantDb(db, EXTENSION_REGISTRY_GLOBAL_TENANT);
+  const versionIds = (await registryDb.table('extension_version')
+    .where({ registry_id: extension })
+    .select(['id'])).map((v: any) => v.id as string);
+  if (versionIds.length === 0) return { content_hashes: [] };
+
+  const bundles = await registryDb.table('extension_bundle')
+    .whereIn('version_id', versionIds)
+    .select(['content_hash']);
+  const hashes = bundles
+    .map((b: any) => normalizeHash(b.content_hash))
+    .filter((h): h is string => !!h);
+  return { content_hashes: Array.from(new Set(hashes)) };
+}
@@ -10,6 +10,7 @@ export const KNOWN_PROVIDER_CAPABILITIES = [
   'cap:invoice.manual.create',
   'cap:client.read',
   'cap:service.read',
+  'cap:metrics.emit',
 ] as const;
 
 export const DEFAULT_PROVIDER_CAPABILITIES = ['cap:context.read', 'cap:log.emit', 'cap:user.read'] as const;
@@ -17,6 +18,7 @@ export const DEFAULT_PROVIDER_CAPABILITIES = ['cap:context.read', 'cap:log.emit'
 export const CAP_INVOICE_MANUAL_CREATE = 'cap:invoice.manual.create' as const;
 export const CAP_CLIENT_READ = 'cap:client.read' as const;
 export const CAP_SERVICE_READ = 'cap:service.read' as const;
+export const CAP_USER_READ = 'cap:user.read' as const;
 
 export type ProviderCapability = (typeof KNOWN_PROVIDER_CAPABILITIES)[number];
 
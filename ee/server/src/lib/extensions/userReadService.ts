@@ -0,0 +1,41 @@
+import { getConnection, tenantDb } from '@alga-psa/db'
+
+// Shaped like the runner's UserInfo so the response deserializes as-is.
+export interface ExtensionUserInfo {
+  user_id: string
+  user_email: string
+  user_name: string
+  user_type: string
+  client_name: string
+  client_id?: string
+}
+
+export async function getExtensionUserInfo(tenantId: string, userId: string): Promise<ExtensionUserInfo | null> {
+  const knex = await getConnection(tenantId)
+  const db = tenantDb(knex, tenantId)
+
+  const user = await db.table('users')
+    .first(['user_id', 'email', 'first_name', 'last_name', 'user_type', 'contact_id'])
+    .where({ user_id: userId, is_inactive: false })
+  if (!user) {
+    return null
+  }
+
+  const tenant = await db.table('tenants').first(['client_name'])
+  let clientId: string | undefined
+  if (user.user_type === 'client' && user.contact_id) {
+    const contact = await db.table('contacts')
+      .first(['client_id'])
+      .where({ contact_name_id: user.contact_id })
+    clientId = contact?.client_id || undefined
+  }
+
+  return {
+    user_id: user.user_id,
+    user_email: user.email || '',
+    user_name: `${user.first_name || ''} ${user.last_name || ''}`.trim(),
+    user_type: user.user_type,
+    client_name: tenant?.client_name || '',
+    client_id: clientId,
+  }
+}
@@ -0,0 +1,93 @@
+import { z } from 'zod'
+
+import { getInstallConfigByInstallId } from '@ee/lib/extensions/installConfig'
+import { CAP_USER_READ, normalizeCapability } from '@ee/lib/extensions/providers'
+import { isValidRunnerToken } from '@ee/lib/extensions/runnerAuth'
+import { getExtensionUserInfo } from '@ee/lib/extensions/userReadService'
+
+export type UsersInternalResponse = { status: number; body: any }
+
+class UsersInternalError extends Error {
+  code: string
+  status: number
+
+  constructor(code: string, status: number, message: string) {
+    super(message)
+    this.name = 'UsersInternalError'
+    this.code = code
+    this.status = status
+  }
+}
+
+const requestSchema = z.object({
+  operation: z.literal('get'),
+  userId: z.string().uuid(),
+})
+
+function ensureRunnerAuth(headers: Headers): void {
+  if (!isValidRunnerToken(
+    headers.get('x-runner-auth'),
+    process.env.RUNNER_STORAGE_API_TOKEN,
+    process.env.RUNNER_SERVICE_TOKEN,
+  )) {
+    throw new UsersInternalError('UNAUTHORIZED', 401, 'Invalid runner token')
+  }
+}
+
+export async function handleInternalUsersInstallRequest(params: {
+  installId: string
+  headers: Headers
+  body: unknown
+}): Promise<UsersInternalResponse> {
+  try {
+    ensureRunnerAuth(params.headers)
+
+    const input = requestSchema.parse(params.body)
+
+    const config = await getInstallConfigByInstallId(params.installId)
+    if (!config) {
+      return { status: 404, body: { error: 'Install not found', code: 'INSTALL_NOT_FOUND' } }
+    }
+
+    const hasCap = config.providers.includes(normalizeCapability(CAP_USER_READ))
+    if (!hasCap) {
+      return {
+        status: 403,
+        body: { error: `Permission denied: ${CAP_USER_READ} not granted`, code: 'NOT_ALLOWED' },
+      }
+    }
+
+    const user = await getExtensionUserInfo(config.tenantId, input.userId)
+    console.info('[ext-users] get', {
+      tenantId: config.tenantId,
+      extension: config.extensionSlug ?? config.registryId,
+      installId: config.installId,
+      operation: 'get',
+      found: Boolean(user),
+    })
+    if (!user) {
+      return { status: 404, body: { error: 'User not found', code: 'USER_NOT_FOUND' } }
+    }
+    return { status: 200, body: { user } }
+  } catch (error: any) {
+    if (error instanceof UsersInternalError) {
+      return { status: error.status, body: { error: error.message, code: error.code } }
+    }
+
+    if (error instanceof z.ZodError) {
+      return {
+        status: 400,
+        body: {
+          error: 'Invalid request payload',
+          code: 'INVALID_INPUT',
+          details: error.flatten(),
+        },
+      }
+    }
+
+    return {
+      status: 500,
+      body: { error: 'Internal error', code: 'INTERNAL_ERROR' },
+    }
+  }
+}
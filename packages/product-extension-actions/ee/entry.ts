@@ -1,5 +1,5 @@
 // EE implementation for Extension actions
-export { validate, lookupByHost } from '../../../ee/server/src/lib/actions/installDomainActions';
+export { validate, lookupByHost, approvedVersions } from '../../../ee/server/src/lib/actions/installDomainActions';
 export { listAppMenuItemsForTenant } from '../../../ee/server/src/lib/actions/extMenuActions';
 export type { AppMenuItem } from '../../../ee/server/src/lib/actions/extMenuActions';
 
@@ -7,6 +7,10 @@ export const lookupByHost = async (_host: string) => {
   throw new Error('Extension domain lookup is an Enterprise Edition feature');
 };
 
+export const approvedVersions = async (_params: any): Promise<{ content_hashes: string[] }> => {
+  throw new Error('Extension version approval is an Enterprise Edition feature');
+};
+
 export const listAppMenuItemsForTenant = async () => {
   return [] as any[];
 };
@@ -16,6 +20,7 @@ export type AppMenuItem = any;
 export default {
   validate,
   lookupByHost,
+  approvedVersions,
   listAppMenuItemsForTenant,
 };
 
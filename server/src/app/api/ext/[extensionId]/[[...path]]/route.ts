@@ -327,6 +327,7 @@ async function handle(req: NextRequest, ctx: { params: Promise<{ extensionId: st
           'x-request-id': requestId,
           'x-alga-tenant': tenantId,
           'x-alga-extension': extensionId,
+          ...(userInfo?.user_id ? { 'x-alga-user-id': userInfo.user_id } : {}),
           ...(idempotencyKey ? { 'x-idempotency-key': idempotencyKey } : {}),
         },
         body: JSON.stringify({
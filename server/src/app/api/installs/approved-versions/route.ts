@@ -0,0 +1,19 @@
+import { NextRequest, NextResponse } from 'next/server';
+import { approvedVersions } from '@alga-psa/product-extension-actions';
+
+export async function GET(req: NextRequest) {
+  const url = new URL(req.url);
+  const tenant = url.searchParams.get('tenant') || '';
+  const extension = url.searchParams.get('extension') || '';
+  if (!tenant || !extension) {
+    return NextResponse.json({ error: 'missing_parameters' }, { status: 400 });
+  }
+
+  try {
+    const out = await approvedVersions({ tenant, extension });
+    return NextResponse.json(out, { status: 200 });
+  } catch (e: any) {
+    console.error('[installs/approved-versions] error', e?.message || String(e));
+    return NextResponse.json({ error: 'server_error' }, { status: 500 });
+  }
+}
@@ -63,6 +63,23 @@ export function registerInstallRoutes(registry: ApiOpenApiRegistry) {
     }),
   );
 
+  const InstallApprovedVersionsQuery = registry.registerSchema(
+    'InstallApprovedVersionsQuery',
+    zOpenApi.object({
+      tenant: zOpenApi.string().uuid().describe('Tenant UUID from tenant_extension_install.tenant_id.'),
+      extension: zOpenApi.string().uuid().describe('Extension registry UUID from tenant_extension_install.registry_id.'),
+    }),
+  );
+
+  const InstallApprovedVersionsResponse = registry.registerSchema(
+    'InstallApprovedVersionsResponse',
+    zOpenApi.object({
+      content_hashes: zOpenApi
+        .array(zOpenApi.string())
+        .describe('Content hashes (sha256:<64 hex chars>) of every extension_bundle published for the extension. Empty when the tenant has no install of it.'),
+    }),
+  );
+
   registry.registerRoute({
     method: 'get',
     path: '/api/installs/lookup-by-host',
@@ -144,4 +161,43 @@ export function registerInstallRoutes(registry: ApiOpenApiRegistry) {
     },
     edition: 'both',
   });
+
+  registry.registerRoute({
+    method: 'get',
+    path: '/api/installs/approved-versions',
+    summary: 'List approved extension bundle content hashes',
+    description:
+      'Internal endpoint for extension runners started with EXT_REQUIRE_APPROVED_VERSIONS. Returns the content hashes of every extension_bundle published for the extension registry ID, provided the tenant has an install of it. An extension with nothing approved answers 200 with an empty list, never 404, so runners can treat 404 as a missing route. The EE action uses an admin database connection. Requires x-api-key; the Express middleware allows the ALGA_AUTH_KEY runner secret or a valid database API key.',
+    tags: [tag],
+    security: [{ ApiKeyAuth: [] }],
+    request: {
+      query: InstallApprovedVersionsQuery,
+      headers: RunnerCanaryHeaders,
+    },
+    responses: {
+      200: {
+        description: 'Approved content hashes for the extension; empty when none are approved.',
+        schema: InstallApprovedVersionsResponse,
+      },
+      400: {
+        description: 'Required tenant or extension query parameter is missing.',
+        schema: InstallLookupErrorResponse,
+      },
+      401: {
+        description: 'x-api-key is missing or invalid at middleware.',
+        schema: InstallLookupErrorResponse,
+      },
+      500: {
+        description: 'Unexpected lookup failure, or a non-EE build.',
+        schema: InstallLookupErrorResponse,
+      },
+    },
+    extensions: {
+      'x-runner-internal': true,
+      'x-admin-db-connection': true,
+      'x-cache-control': 'no-store',
+      'x-vary': 'x-api-key, x-canary',
+    },
+    edition: 'both',
+  });
 }
@@ -23,6 +23,7 @@ const RATE_LIMIT_BYPASS_PREFIXES = [
   '/api/internal/ext-invoicing/',
   '/api/internal/ext-clients/',
   '/api/internal/ext-services/',
+  '/api/internal/ext-users/',
 ];
 
 function isRateLimitEnforced(): boolean {
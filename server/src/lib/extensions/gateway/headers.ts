@@ -16,7 +16,7 @@ export function filterRequestHeaders(inHeaders: Headers): Record<string, string>
   return out;
 }
 
-const responseAllow = new Set(['content-type', 'cache-control', 'etag']);
+const responseAllow = new Set(['content-type', 'cache-control', 'etag', 'retry-after']);
 
 export function filterResponseHeaders(inHeaders: Headers): Record<string, string> {
   const out: Record<string, string> = {};
@@ -139,6 +139,7 @@ const apiKeySkipPaths = [
   '/api/internal/ext-invoicing/', // Runner invoicing host API uses x-runner-auth token
   '/api/internal/ext-clients/', // Runner client read host API uses x-runner-auth token
   '/api/internal/ext-services/', // Runner service read host API uses x-runner-auth token
+  '/api/internal/ext-users/', // Runner user lookup host API uses x-runner-auth token
 ];
 
 export function shouldSkipApiKeyAuth(pathname: string): boolean {
@@ -280,7 +280,8 @@ export async function apiKeyAuthMiddleware(
     const normalizedPath = path.endsWith('/') && path.length > 1 ? path.slice(0, -1) : path;
     const isRunnerLookup =
       normalizedPath === '/api/installs/lookup-by-host' ||
-      normalizedPath === '/api/installs/validate';
+      normalizedPath === '/api/installs/validate' ||
+      normalizedPath === '/api/installs/approved-versions';
     if (isRunnerLookup) {
       try {
         const secretProvider = await getSecretProviderInstance();